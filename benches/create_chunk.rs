@@ -0,0 +1,81 @@
+//! Meshing a single chunk. Run with `cargo bench --bench create_chunk`.
+
+#![allow(clippy::needless_return)]
+
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	hint::black_box,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use hex_grid::{HexGrid, HexGridSettings};
+
+/// Counts allocations and reallocations, to show how often building a chunk
+/// grows its buffers.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		return System.alloc(layout);
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout);
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		REALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		return System.realloc(ptr, layout, new_size);
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const CHUNK_SIZES: [u32; 3] = [16, 32, 64];
+
+fn settings(chunk_size: u32) -> HexGridSettings {
+	return HexGridSettings {
+		chunk_size,
+		..default()
+	};
+}
+
+/// Prints the allocations made while building one chunk of each size. Sized
+/// for 18 entries per tile, every size used to grow its positions, UVs,
+/// normals and indices once each, for 12 allocations and 4 reallocations.
+fn allocation_churn(_: &mut Criterion) {
+	for chunk_size in CHUNK_SIZES {
+		let settings = settings(chunk_size);
+		let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+		let reallocations = REALLOCATIONS.load(Ordering::Relaxed);
+		black_box(HexGrid::chunk_mesh(UVec2::new(1, 1), &settings));
+		println!(
+			"create_chunk/{chunk_size}: {} allocations, {} reallocations",
+			ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+			REALLOCATIONS.load(Ordering::Relaxed) - reallocations,
+		);
+	}
+}
+
+fn create_chunk(c: &mut Criterion) {
+	let mut group = c.benchmark_group("create_chunk");
+	for chunk_size in CHUNK_SIZES {
+		group.bench_with_input(
+			BenchmarkId::from_parameter(chunk_size),
+			&settings(chunk_size),
+			|b, settings| b.iter(|| HexGrid::chunk_mesh(UVec2::new(1, 1), settings)),
+		);
+	}
+	group.finish();
+}
+
+criterion_group!(benches, allocation_churn, create_chunk);
+criterion_main!(benches);
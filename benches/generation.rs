@@ -0,0 +1,63 @@
+//! Height sampling and whole-map meshing. Run with
+//! `cargo bench --bench generation`, or `cargo bench` for every suite;
+//! criterion keeps the reports under `target/criterion`.
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use hex_grid::{HexCoord, HexGrid, HexGridSettings, HexMap};
+
+fn sample_height(c: &mut Criterion) {
+	let mut group = c.benchmark_group("sample_height");
+	let noise = HexGridSettings::default();
+	let mut stored = noise.clone();
+	HexMap::from_settings(&noise).apply(&mut stored);
+	for (name, settings) in [("noise", noise), ("map", stored)] {
+		// one row of tiles across the map
+		let coords = (0..settings.map_tiles().x as i32)
+			.map(|x| HexCoord::from_offset(x, 7))
+			.collect::<Vec<_>>();
+		// built once, so only the sampling is timed
+		let noise = settings.noise();
+		group.bench_with_input(
+			BenchmarkId::from_parameter(name),
+			&settings,
+			|b, settings| {
+				b.iter(|| {
+					coords
+						.iter()
+						.map(|&coord| HexGrid::height_with(coord, &noise, settings))
+						.sum::<f32>()
+				});
+			},
+		);
+	}
+	group.finish();
+}
+
+/// Every chunk mesh of the map, the work `HexGrid` spawns at startup.
+fn full_map(c: &mut Criterion) {
+	let mut group = c.benchmark_group("full_map");
+	group.sample_size(10);
+	for (map_size, chunk_size) in [(4, 16), (4, 32), (8, 16), (8, 32)] {
+		let settings = HexGridSettings {
+			map_size: UVec2::splat(map_size),
+			chunk_size,
+			..default()
+		};
+		let id = BenchmarkId::from_parameter(format!("{map_size}x{map_size}/{chunk_size}"));
+		group.bench_with_input(id, &settings, |b, settings| {
+			b.iter(|| {
+				for z in 0..map_size {
+					for x in 0..map_size {
+						HexGrid::chunk_mesh(UVec2::new(x, z), settings);
+					}
+				}
+			});
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, sample_height, full_map);
+criterion_main!(benches);
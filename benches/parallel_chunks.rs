@@ -0,0 +1,50 @@
+//! Meshing every chunk of a map on task pools of 1, 2, 4 and every core.
+//! Run with `cargo bench --bench parallel_chunks`.
+
+#![allow(clippy::needless_return)]
+
+use bevy::{
+	prelude::*,
+	tasks::{TaskPool, TaskPoolBuilder},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use hex_grid::{HexGrid, HexGridSettings};
+
+/// Builds every chunk of `settings` on `pool`, the way `create_hex_grid`
+/// spreads them over the `AsyncComputeTaskPool`.
+fn build_map(pool: &TaskPool, settings: &HexGridSettings) -> Vec<Mesh> {
+	return pool.scope(|scope| {
+		for z in 0..settings.map_size.y {
+			for x in 0..settings.map_size.x {
+				scope.spawn(async move { HexGrid::chunk_mesh(UVec2::new(x, z), settings) });
+			}
+		}
+	});
+}
+
+fn parallel_chunks(c: &mut Criterion) {
+	let settings = HexGridSettings {
+		map_size: UVec2::splat(8),
+		..default()
+	};
+	let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+	let mut threads = vec![1, 2, 4, cores];
+	threads.sort();
+	threads.dedup();
+
+	let mut group = c.benchmark_group("parallel_chunks");
+	group.sample_size(10);
+	for threads in threads {
+		let pool = TaskPoolBuilder::new().num_threads(threads).build();
+		group.bench_with_input(
+			BenchmarkId::from_parameter(threads),
+			&settings,
+			|b, settings| b.iter(|| build_map(&pool, settings)),
+		);
+	}
+	group.finish();
+}
+
+criterion_group!(benches, parallel_chunks);
+criterion_main!(benches);
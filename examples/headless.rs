@@ -0,0 +1,34 @@
+//! Generates map data with `MinimalPlugins`, without a window or renderer.
+
+use bevy::prelude::*;
+
+use hex_grid::{HexGrid, HexGridSettings, HexMap};
+
+fn main() {
+	let mut app = App::new();
+	app.add_plugins(MinimalPlugins)
+		.insert_resource(HexGridSettings {
+			map_size: UVec2::new(4, 2),
+			..default()
+		})
+		.add_systems(Startup, generate);
+	// a single update runs the startup systems, no event loop needed
+	app.update();
+}
+
+fn generate(settings: Res<HexGridSettings>) {
+	let mut vertices = 0;
+	let mut triangles = 0;
+	for z in 0..settings.map_size.y {
+		for x in 0..settings.map_size.x {
+			let buffers = HexGrid::chunk_buffers(UVec2::new(x, z), &settings);
+			vertices += buffers.positions.len();
+			triangles += buffers.indices.len() / 3;
+		}
+	}
+	let map = HexMap::from_settings(&settings);
+	println!(
+		"{} tiles, {vertices} vertices, {triangles} triangles",
+		map.tiles.len()
+	);
+}
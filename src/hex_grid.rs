@@ -1,42 +1,828 @@
+use std::sync::Arc;
+
 use bevy::{
+	color::palettes::css,
 	pbr::{wireframe::WireframeConfig, CascadeShadowConfig, DirectionalLightShadowMap},
 	prelude::*,
 	render::{
-		mesh::{Indices, PrimitiveTopology},
+		mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+		primitives::Aabb,
 		render_asset::RenderAssetUsages,
-		render_resource::{Extent3d, TextureDimension, TextureFormat},
+		render_resource::{Extent3d, Face, TextureDimension, TextureFormat},
 	},
+	tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+	utils::{Duration, HashMap, HashSet, Instant},
 };
-use bevy_panorbit_camera::PanOrbitCamera;
+use bevy_panorbit_camera::{PanOrbitCamera, PanOrbitCameraSystemSet};
 
+#[cfg(feature = "egui")]
 use bevy_inspector_egui::prelude::*;
 
-use noise::{NoiseFn, SuperSimplex};
+use iyes_perf_ui::prelude::{PerfUiEntryClock, PerfUiEntryFPS, PerfUiRoot};
+
+mod atlas;
+mod biome;
+mod buffers;
+mod chunk_debug;
+mod coord;
+mod culling;
+mod data;
+mod edges;
+mod edit;
+mod erosion;
+mod export;
+mod fog;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod greedy;
+mod grid_lines;
+mod height;
+mod heightmap;
+mod instancing;
+mod labels;
+mod lod;
+mod map;
+mod minimap;
+#[cfg(feature = "egui")]
+mod panel;
+mod path;
+mod picking;
+mod ramp;
+mod range;
+mod rivers;
+mod rng;
+mod scatter;
+mod spans;
+mod spawn;
+mod streaming;
+mod subdivide;
+mod tiles;
+mod winding;
+
+pub use atlas::AtlasConfig;
+pub use biome::{Biome, BiomeRegistry, Cliffs, Coasts, SlopeShading};
+pub use buffers::ChunkBuffers;
+pub use chunk_debug::{RegenerateChunk, VertexIndexLabels};
+pub use coord::{HexCoord, HexDirection};
+pub use data::{HexData, TileData};
+pub use edges::{EdgeError, EdgeKind, EdgeStructures};
+pub use edit::{ChunkRebuilt, DirtyChunks};
+pub use erosion::ThermalErosion;
+pub use fog::{Fog, FogOfWar, FogRevealer, TileVisibility};
+pub use grid_lines::GridLines;
+pub use height::{NoiseBlend, NoiseLayer, NoiseLayerKind, NoiseType, TerrainNoise};
+pub use heightmap::Heightmap;
+pub use instancing::TileInstance;
+pub use lod::LodSettings;
+pub use map::{HexMap, HexTile, TileSpan};
+#[cfg(feature = "egui")]
+pub use panel::TerrainPanelPlugin;
+pub use path::{find_path, reachable};
+pub use picking::{raycast_plane, raycast_terrain, HexClicked, PickingMode, SelectedHex};
+pub use ramp::ColorRamp;
+pub use range::MoveRange;
+pub use rivers::Rivers;
+pub use rng::GridRng;
+pub use scatter::{Feature, FeaturePlacement, Scatter};
+pub use streaming::{ChunkStreaming, LoadedChunks};
+pub use tiles::TileEntities;
+
+use height::sample_height;
+use spans::add_tile_spans;
+use subdivide::TileLattice;
+
+#[derive(Default)]
+pub struct HexGrid {
+	settings: HexGridSettings,
+}
+
+impl HexGrid {
+	pub fn with_settings(settings: HexGridSettings) -> Self {
+		return Self { settings };
+	}
+
+	/// Builds the full detail mesh of one chunk, positioned relative to the
+	/// chunk's first tile.
+	pub fn chunk_mesh(index: UVec2, settings: &HexGridSettings) -> Mesh {
+		let noise = settings.noise();
+		return create_chunk(index.x, index.y, 0, &noise, settings);
+	}
+
+	/// Tiles of the map that `chunk` generates, row by row.
+	pub fn tiles_in_chunk(
+		chunk: IVec2,
+		settings: &HexGridSettings,
+	) -> impl Iterator<Item = HexCoord> + '_ {
+		let size = settings.chunk_size as i32;
+		let origin = chunk * size;
+		return (0..size)
+			.flat_map(move |z| (0..size).map(move |x| origin + IVec2::new(x, z)))
+			.map(|offset| HexCoord::from_offset(offset.x, offset.y))
+			.filter(|&coord| settings.contains(coord));
+	}
+
+	/// Height of the top face of `coord`, matching the generated mesh.
+	pub fn height_at(coord: HexCoord, settings: &HexGridSettings) -> f32 {
+		return Self::height_with(coord, &settings.noise(), settings);
+	}
+
+	/// `height_at`, sampling from `noise` so that many tiles can share one.
+	pub fn height_with(coord: HexCoord, noise: &TerrainNoise, settings: &HexGridSettings) -> f32 {
+		let offset = coord.to_offset();
+		return sample_height(offset.x, offset.y, noise, settings);
+	}
+
+	/// Height at any point of the map like `height_at`, blended between tile
+	/// centers. The tile containing `xz` and each pair of its neighbors span
+	/// a triangle, and the point takes the barycentric mix of the heights of
+	/// the three centers of the one it falls in. On a shared edge both
+	/// triangles agree, so the result never jumps.
+	///
+	/// This is not the surface of subdivided tiles, whose vertices sample the
+	/// noise between the centers instead of blending their heights, so on
+	/// noise terrain with `subdivisions` above 0 the two differ.
+	pub fn height_at_world(xz: Vec2, settings: &HexGridSettings) -> f32 {
+		let noise = settings.noise();
+		let (tiles, weights) = height::center_triangle(xz, settings);
+		return (0..3)
+			.map(|i| {
+				let offset = settings.wrap_coord(tiles[i]).to_offset();
+				return weights[i] * sample_height(offset.x, offset.y, &noise, settings);
+			})
+			.sum();
+	}
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[cfg_attr(feature = "egui", derive(InspectorOptions))]
+#[reflect(Resource)]
+#[cfg_attr(feature = "egui", reflect(InspectorOptions))]
+pub struct HexGridSettings {
+	/// Number of chunks along x and z.
+	pub map_size: UVec2,
+	/// Number of tiles along each side of a chunk, at least 1.
+	#[cfg_attr(feature = "egui", inspector(min = 1))]
+	pub chunk_size: u32,
+	/// Distance from a tile center to its corners.
+	pub outer_radius: f32,
+	/// Fraction of `outer_radius` each tile top shrinks towards its center,
+	/// leaving seams between tiles. Inset tiles get their own walls down to
+	/// the lowest possible height instead of sharing cliffs.
+	pub tile_inset: f32,
+	/// Splits each edge of a tile top into this many more segments, with
+	/// every vertex but the center following the terrain below it for
+	/// curved tiles instead of flat ones. Neighboring tiles then meet
+	/// without walls, but inset tiles and `chunk_skirt` hang theirs from the
+	/// subdivided edges. Costs a height sample per vertex.
+	pub subdivisions: u32,
+	pub noise_scale: f64,
+	/// Blended to produce the height of each tile. The first layer also masks
+	/// any layer with `masked` set.
+	pub noise_layers: Vec<NoiseLayer>,
+	/// Shape of the octaves of every `NoiseLayerKind::Simple` layer.
+	pub noise_type: NoiseType,
+	/// Seconds of movement applied to noise layers with a `speed`.
+	pub terrain_time: f64,
+	/// Advances `terrain_time` every frame, rebuilding the chunks as their
+	/// layers move. Costs a full chunk rebuild per frame while any layer has a
+	/// `speed`.
+	pub animate_terrain: bool,
+	/// Sinks the terrain towards its lowest height near the map edges,
+	/// leaving an island. Higher exponents keep more of the map untouched.
+	pub island_falloff: Option<f32>,
+	/// Scales the summed noise layers, or the brightness of `heightmap`.
+	pub height_amplitude: f32,
+	/// Added to every tile height after scaling.
+	pub height_offset: f32,
+	/// Grayscale image replacing the noise layers, stretched over the map with
+	/// +z at the top. Black tiles sit at `height_offset` and white ones
+	/// `height_amplitude` above, blending between pixels when the image
+	/// doesn't match the tile grid.
+	pub height_image: Option<Handle<Image>>,
+	/// The brightness of `height_image`, read in once it loads. Set directly
+	/// to shape the terrain without an image.
+	#[reflect(ignore)]
+	pub heightmap: Option<Arc<Heightmap>>,
+	/// Snaps tile heights to this many evenly spaced levels.
+	pub height_steps: Option<u32>,
+	/// Height of a translucent water plane spanning the map.
+	pub sea_level: Option<f32>,
+	/// Biomes used to color the terrain, the first match of each tile
+	/// winning.
+	pub biomes: BiomeRegistry,
+	/// Fades the colors of neighboring height bands into each other over this
+	/// height around each boundary, instead of snapping between them. Tiles
+	/// of biomes picked by slope, cliffs or coasts keep their plain color.
+	pub biome_blend: Option<f32>,
+	/// Gradient image coloring every vertex by its height instead of the
+	/// biomes, its first row running from the lowest possible height to the
+	/// highest. Slope shading and ambient occlusion darken it.
+	pub color_ramp: Option<Handle<Image>>,
+	/// The colors of `color_ramp`, read in once it loads. Set directly to
+	/// color by a ramp without an image.
+	#[reflect(ignore)]
+	pub ramp: Option<Arc<ColorRamp>>,
+	/// Gives steep tiles a cliff biome.
+	pub cliffs: Option<Cliffs>,
+	/// Gives land tiles next to water a coast biome. Needs `sea_level`.
+	pub coasts: Option<Coasts>,
+	/// Darkens tiles sunk below their neighbors by up to this fraction.
+	pub ambient_occlusion: Option<f32>,
+	/// Darkens tiles by their slope, over or in place of their biome color,
+	/// or over their atlas cell or ramp color.
+	pub slope_shading: Option<SlopeShading>,
+	/// Textures tiles with the atlas cell of their biome instead of coloring
+	/// them with the biome color. Slope shading and ambient occlusion still
+	/// darken it.
+	pub atlas: Option<AtlasConfig>,
+	pub textures: MaterialTextures,
+	/// Draws walls and tile tops from behind too, for cameras that go below
+	/// the terrain. Every face already winds outwards from the higher tile,
+	/// so only their backs, hidden inside the terrain, are culled otherwise.
+	pub double_sided: bool,
+	pub seed: u32,
+	pub wireframe: bool,
+	/// Toggles the global wireframe at runtime. Has no effect unless
+	/// `WireframePlugin` is added.
+	pub wireframe_toggle_key: Option<KeyCode>,
+	/// Draws the world axes and the corners of the tile at the origin.
+	pub debug_gizmos: bool,
+	/// Toggles the debug gizmos at runtime.
+	pub debug_gizmos_toggle_key: Option<KeyCode>,
+	/// Labels the tiles within this many tiles of the camera focus with
+	/// their axial coordinates.
+	pub coord_labels: Option<u32>,
+	/// Draws the tile borders into the chunk material, a lighter alternative
+	/// to `wireframe` that keeps the surface solid. Not drawn with an atlas,
+	/// whose cells the tile UVs point into instead.
+	pub grid_lines: Option<GridLines>,
+	/// Outline drawn around the hovered tile.
+	pub hover_outline: Option<Color>,
+	/// How the tile under the cursor is found. Maps whose hills hide the
+	/// tiles behind them pick more precisely with `PickingMode::Terrain`.
+	pub picking: PickingMode,
+	/// Outlines the tiles reachable from the hovered tile.
+	pub move_range: Option<MoveRange>,
+	/// Hides the tiles no `FogRevealer` is near under an overlay. What has
+	/// been seen is kept in `FogOfWar`.
+	pub fog: Option<Fog>,
+	pub normal_mode: NormalMode,
+	/// Merges vertices snapping to the same grid of this spacing, sharing corners between
+	/// neighboring tiles and their walls. Only shrinks smooth shaded meshes,
+	/// flat shading unrolls every triangle again. A shared corner can't keep
+	/// the UV square of each tile it belongs to, so welded chunks stretch the
+	/// texture across the whole chunk like LOD meshes, or sample the middle
+	/// of each vertex's atlas cell.
+	pub weld_tolerance: Option<f32>,
+	/// Covers runs of neighboring tiles with the same height and color with
+	/// a few large triangles instead of a fan per tile, for terraced maps
+	/// with wide plateaus. Skipped for inset or subdivided tiles, and with an
+	/// atlas or grid lines, whose UVs only work within each tile.
+	pub merge_flat_tops: bool,
+	pub orientation: HexOrientation,
+	/// World position of the center of the first tile at height 0. Moves
+	/// everything the grid spawns, and the world positions `HexCoord`
+	/// converts from and to, so maps built from different settings side by
+	/// side keep their own coordinates.
+	pub origin: Vec3,
+	/// Moves the map so the average of its tile centers lands on `origin`
+	/// instead of the first tile, for cameras orbiting the middle of the map.
+	pub center_origin: bool,
+	pub wrap: WrapMode,
+	pub shape: MapShape,
+	pub camera: CameraSettings,
+	/// Attach a `ChunkMesh` with each chunk's triangles for building colliders.
+	pub generate_collider: bool,
+	/// Draws every tile as a scaled copy of one hex prism instead of baking
+	/// chunk meshes. Each tile is its own entity sharing the prism and its
+	/// biome's material, which Bevy batches into instanced draws. There is no
+	/// custom instance buffer, so the map costs one entity per tile, about a
+	/// million for the default settings. Nothing is merged either: no LOD,
+	/// streaming, colliders or atlas, and every tile is a column down to the
+	/// lowest possible height rather than sharing cliff walls with its
+	/// neighbors.
+	pub instanced: bool,
+	/// Spawns an entity per tile as children of its chunk, carrying the
+	/// tile's `HexCoord` and placed at the center of its top. Look them up
+	/// through `TileEntities`.
+	pub tile_entities: bool,
+	/// Decorations spawned on eligible tiles as children of their chunk, the
+	/// same ones every time for the same seed.
+	pub scatter: Vec<Scatter>,
+	/// Loads chunks around the camera focus instead of the whole map at once.
+	pub streaming: Option<ChunkStreaming>,
+	/// Rebuilds distant chunks with coarser meshes.
+	pub lod: Option<LodSettings>,
+	/// Hangs a skirt this deep below the outer edges of full detail chunks,
+	/// so any crack along a seam shows terrain instead of the sky. Inset
+	/// tiles already have walls down to the lowest height.
+	pub chunk_skirt: Option<f32>,
+	/// Hides chunks outside the camera frustum before Bevy's own per-mesh
+	/// culling runs.
+	pub chunk_culling: bool,
+	/// Regenerates the map whenever these settings change, e.g. from the
+	/// inspector.
+	pub regenerate_on_change: bool,
+	/// Builds tiles from stored heights instead of the noise layers.
+	#[reflect(ignore)]
+	pub map: Option<Arc<HexMap>>,
+	/// Tiles left out of the chunk meshes, set through
+	/// `HexGrid::set_tile_visible`.
+	#[reflect(ignore)]
+	pub hidden_tiles: Arc<HashSet<HexCoord>>,
+}
+
+#[derive(Reflect, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum NormalMode {
+	/// One normal per face, so tile tops and cliff walls shade separately.
+	/// Every triangle gets three vertices of its own, sharing none.
+	#[default]
+	Flat,
+	/// Normals averaged across the faces sharing each vertex.
+	Smooth,
+}
+
+#[derive(Reflect, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum HexOrientation {
+	/// Corners point along z, rows of tiles run along x.
+	#[default]
+	PointyTop,
+	/// Corners point along x, columns of tiles run along z.
+	FlatTop,
+}
+
+#[derive(Reflect, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum WrapMode {
+	#[default]
+	None,
+	/// The last column of tiles along x borders the first.
+	Cylinder,
+	/// Wraps along x and z. Needs an even number of tile rows to keep the
+	/// staggered rows lined up across the seam, and falls back to `Cylinder`
+	/// with a warning otherwise.
+	Torus,
+}
+
+/// Outline of the generated tiles within the `map_size` rectangle, centered
+/// on its middle tile. Tiles outside count as off the map: they get no mesh,
+/// and the tiles along the outline are left open like the map edges. Meant
+/// for maps that don't wrap.
+#[derive(Reflect, Clone, Copy, Default, PartialEq, Debug)]
+pub enum MapShape {
+	#[default]
+	Rectangle,
+	/// Tiles at most `radius` steps from the middle tile. Needs a map at
+	/// least `2 * radius + 1` tiles across to fit.
+	Hexagon { radius: u32 },
+	/// Tiles whose center lies within `radius` tile widths of the middle
+	/// tile's.
+	Circle { radius: f32 },
+}
+
+/// Overrides for the initial camera, which otherwise frames the whole map.
+#[derive(Reflect, Clone, Copy, Default, Debug)]
+pub struct CameraSettings {
+	pub position: Option<Vec3>,
+	pub target: Option<Vec3>,
+	/// Distance from the target, keeping the direction of `position`.
+	pub radius: Option<f32>,
+	pub zoom_lower_limit: Option<f32>,
+	pub zoom_upper_limit: Option<f32>,
+	/// Keeps the orbit focus over the map, panning no further than this past
+	/// its edges.
+	pub focus_margin: Option<f32>,
+}
+
+/// Extra maps for the terrain material, sampled with the same UVs as the
+/// atlas. Chunks get tangents generated when a normal map is set.
+#[derive(Reflect, Clone, Default, Debug)]
+pub struct MaterialTextures {
+	pub normal_map: Option<Handle<Image>>,
+	pub metallic_roughness: Option<Handle<Image>>,
+	pub occlusion: Option<Handle<Image>>,
+}
+
+impl Default for HexGridSettings {
+	fn default() -> Self {
+		return Self {
+			map_size: UVec2::splat(32),
+			chunk_size: 32,
+			outer_radius: 1.,
+			tile_inset: 0.,
+			subdivisions: 0,
+			noise_scale: 350.,
+			noise_layers: NoiseLayer::default_terrain(),
+			noise_type: NoiseType::Simplex,
+			terrain_time: 0.,
+			animate_terrain: false,
+			island_falloff: None,
+			height_amplitude: 1.,
+			height_offset: 0.,
+			height_image: None,
+			heightmap: None,
+			height_steps: None,
+			sea_level: None,
+			biomes: Biome::default_bands().into(),
+			biome_blend: None,
+			color_ramp: None,
+			ramp: None,
+			cliffs: None,
+			coasts: None,
+			ambient_occlusion: None,
+			slope_shading: None,
+			atlas: None,
+			textures: MaterialTextures::default(),
+			seed: 1223939298,
+			double_sided: false,
+			wireframe: false,
+			wireframe_toggle_key: Some(KeyCode::F3),
+			debug_gizmos: false,
+			debug_gizmos_toggle_key: Some(KeyCode::F4),
+			coord_labels: None,
+			hover_outline: Some(css::YELLOW.into()),
+			picking: PickingMode::Plane,
+			grid_lines: None,
+			move_range: None,
+			fog: None,
+			normal_mode: NormalMode::Flat,
+			weld_tolerance: None,
+			merge_flat_tops: false,
+			orientation: HexOrientation::PointyTop,
+			origin: Vec3::ZERO,
+			center_origin: false,
+			wrap: WrapMode::None,
+			shape: MapShape::Rectangle,
+			camera: CameraSettings::default(),
+			generate_collider: false,
+			instanced: false,
+			tile_entities: false,
+			scatter: Vec::new(),
+			streaming: None,
+			lod: None,
+			chunk_skirt: None,
+			chunk_culling: false,
+			regenerate_on_change: true,
+			map: None,
+			hidden_tiles: default(),
+		};
+	}
+}
+
+impl HexGridSettings {
+	/// Distance from a tile center to the middle of its edges.
+	pub fn inner_radius(&self) -> f32 {
+		return self.outer_radius * 0.866_025_4;
+	}
+
+	pub fn hex_corners(&self) -> [Vec3; 6] {
+		let outer = self.outer_radius;
+		let inner = self.inner_radius();
+		return [
+			Vec3::new(0., 0., outer),
+			Vec3::new(inner, 0., 0.5 * outer),
+			Vec3::new(inner, 0., -0.5 * outer),
+			Vec3::new(0., 0., -outer),
+			Vec3::new(-inner, 0., -0.5 * outer),
+			Vec3::new(-inner, 0., 0.5 * outer),
+		]
+		.map(|corner| self.orient(corner));
+	}
+
+	/// Vertex color for terrain at `height`.
+	pub fn biome_color(&self, height: f32) -> Color {
+		let color = match self.biome_blend {
+			Some(width) => self.biomes.blended_color(height, width),
+			None => biome::biome_at(&self.biomes, height).map(|biome| biome.color),
+		};
+		return color.unwrap_or(Color::WHITE);
+	}
+
+	/// Heights of the neighbors of the tile at offset coordinates (`x`, `z`)
+	/// if its color reads them, or none.
+	fn color_neighbors(&self, x: i32, z: i32, noise: &TerrainNoise) -> Vec<f32> {
+		let shaded = self.slope_shading.is_some() || self.ambient_occlusion.is_some();
+		if !shaded && !biome::reads_neighbors(self) {
+			return Vec::new();
+		}
+		return biome::neighbor_heights(x, z, noise, self);
+	}
+
+	/// Color of the top of the tile at offset coordinates (`x`, `z`), from
+	/// the heights of its `neighbors` on the map.
+	fn tile_color(&self, x: i32, z: i32, height: f32, neighbors: &[f32]) -> Color {
+		// only tiles colored by their height band blend into the next one
+		let band = biome::biome_at(&self.biomes, height);
+		let color = match biome::tile_biome(x, z, height, neighbors, self) {
+			None => Color::WHITE,
+			Some(biome) if band.is_some_and(|band| std::ptr::eq(band, biome)) => {
+				self.biome_color(height)
+			}
+			Some(biome) => biome.color,
+		};
+		let Some(shading) = &self.slope_shading else {
+			return color;
+		};
+		return shading.shade(color, biome::tile_slope(height, neighbors));
+	}
+
+	/// Vertex color of the top of the tile at offset coordinates (`x`, `z`):
+	/// its `tile_color` darkened by ambient occlusion. Over an atlas texture
+	/// or a color ramp only the darkening is left, as a tint.
+	fn tile_vertex_color(&self, x: i32, z: i32, height: f32, neighbors: &[f32]) -> Color {
+		let color = match (
+			&self.slope_shading,
+			self.atlas.is_some() || self.ramp.is_some(),
+		) {
+			(None, true) => Color::WHITE,
+			(Some(shading), true) => {
+				shading.shade(Color::WHITE, biome::tile_slope(height, neighbors))
+			}
+			(_, false) => self.tile_color(x, z, height, neighbors),
+		};
+		let Some(strength) = self.ambient_occlusion else {
+			return color;
+		};
+		let light = height::ambient_light(height, neighbors, strength, self);
+		let rgba = color.to_srgba();
+		return Color::srgba(
+			rgba.red * light,
+			rgba.green * light,
+			rgba.blue * light,
+			rgba.alpha,
+		);
+	}
+
+	/// UV bounds of the atlas cell for terrain at `height`, or the whole
+	/// texture without an atlas.
+	pub fn atlas_rect(&self, height: f32) -> Rect {
+		return self.biome_rect(biome::biome_at(&self.biomes, height));
+	}
+
+	/// UV bounds of the atlas cell of `biome`, or the whole texture without
+	/// an atlas.
+	pub fn biome_rect(&self, biome: Option<&Biome>) -> Rect {
+		let Some(atlas) = &self.atlas else {
+			return Rect::new(0., 0., 1., 1.);
+		};
+		return atlas.cell_rect(biome.map_or(0, |biome| biome.atlas_cell));
+	}
+
+	/// `biome_rect` of the biome `tile_biome` gives the tile at offset
+	/// coordinates (`x`, `z`), from the heights of its `neighbors`.
+	fn tile_rect(&self, x: i32, z: i32, height: f32, neighbors: &[f32]) -> Rect {
+		if self.atlas.is_none() {
+			return self.biome_rect(None);
+		}
+		return self.biome_rect(biome::tile_biome(x, z, height, neighbors, self));
+	}
+
+	/// Size of the whole map in tiles.
+	pub fn map_tiles(&self) -> UVec2 {
+		return self.map_size * self.chunk_size;
+	}
+
+	/// The wrapping in effect. A torus with an odd number of tile rows would
+	/// misalign the staggered rows across its seam, so it only wraps along x.
+	pub fn wrap_mode(&self) -> WrapMode {
+		if self.wrap == WrapMode::Torus && self.map_tiles().y % 2 == 1 {
+			return WrapMode::Cylinder;
+		}
+		return self.wrap;
+	}
+
+	/// Noise sources of `noise_layers`, seeded from `seed`. Building them
+	/// costs more than sampling a height, so build them once for many tiles.
+	pub fn noise(&self) -> TerrainNoise {
+		return TerrainNoise::new(self.seed, &self.noise_layers);
+	}
+
+	/// Whether the map has no tiles, with a `map_size` or `chunk_size` of 0.
+	pub fn is_empty(&self) -> bool {
+		return self.map_tiles().cmpeq(UVec2::ZERO).any();
+	}
+
+	/// Moves offset coordinates past a wrapped edge back onto the map.
+	pub fn wrap_offset(&self, offset: IVec2) -> IVec2 {
+		let tiles = self.map_tiles().as_ivec2().max(IVec2::ONE);
+		return match self.wrap_mode() {
+			WrapMode::None => offset,
+			WrapMode::Cylinder => IVec2::new(offset.x.rem_euclid(tiles.x), offset.y),
+			WrapMode::Torus => offset.rem_euclid(tiles),
+		};
+	}
+
+	/// Moves `coord` past a wrapped edge back onto the map.
+	pub fn wrap_coord(&self, coord: HexCoord) -> HexCoord {
+		let offset = self.wrap_offset(coord.to_offset());
+		return HexCoord::from_offset(offset.x, offset.y);
+	}
+
+	/// Whether the chunk meshes draw `coord`, a generated tile that isn't
+	/// one of `hidden_tiles`.
+	pub fn shows(&self, coord: HexCoord) -> bool {
+		return self.contains(coord) && !self.hidden_tiles.contains(&coord);
+	}
+
+	/// Whether `coord` is one of the generated tiles, within the map and
+	/// its `shape`.
+	pub fn contains(&self, coord: HexCoord) -> bool {
+		let offset = coord.to_offset();
+		let tiles = self.map_tiles().as_ivec2();
+		if offset.x < 0 || offset.y < 0 || offset.x >= tiles.x || offset.y >= tiles.y {
+			return false;
+		}
+		let center = HexCoord::from_offset(tiles.x / 2, tiles.y / 2);
+		return match self.shape {
+			MapShape::Rectangle => true,
+			MapShape::Hexagon { radius } => coord.distance(&center) <= radius,
+			MapShape::Circle { radius } => {
+				let distance = coord.to_world(self).distance(center.to_world(self));
+				distance <= radius * self.inner_radius() * 2.
+			}
+		};
+	}
+
+	/// World position of the center of the first tile at height 0, which is
+	/// `origin` unless `center_origin` moves the map.
+	pub fn world_origin(&self) -> Vec3 {
+		if !self.center_origin {
+			return self.origin;
+		}
+		let tiles = self.map_tiles();
+		// odd rows are shifted half a tile along x
+		let odd_rows = (tiles.y / 2) as f32 / tiles.y.max(1) as f32;
+		let tiles = tiles.as_vec2();
+		let center = Vec3::new(
+			(tiles.x - 1. + odd_rows) * self.inner_radius(),
+			0.,
+			(tiles.y - 1.).max(0.) * self.outer_radius * 0.75,
+		);
+		return self.origin - self.orient(center);
+	}
+
+	/// World-space xz extent covered by every tile of the map.
+	pub fn map_bounds(&self) -> Rect {
+		let tiles = self.map_tiles().as_vec2();
+		let inner = self.inner_radius();
+		let outer = self.outer_radius;
+		let min = Vec3::new(-inner, 0., -outer);
+		let max = Vec3::new(
+			tiles.x * inner * 2.,
+			0.,
+			(tiles.y - 1.).max(0.) * outer * 1.5 + outer,
+		);
+		let origin = self.world_origin().xz();
+		return Rect::from_corners(
+			origin + self.orient(min).xz(),
+			origin + self.orient(max).xz(),
+		);
+	}
+
+	/// World-space bounds of every vertex a chunk can generate, including LOD
+	/// skirts.
+	pub fn chunk_aabb(&self, index: UVec2) -> Aabb {
+		let size = self.chunk_size as f32;
+		let inner = self.inner_radius();
+		let outer = self.outer_radius;
+		let (low, high) = height::height_bounds(self);
+		let skirt = self.lod.as_ref().map_or(0., |lod| lod.skirt_depth);
+		let skirt = skirt.max(self.chunk_skirt.unwrap_or(0.));
+		// odd rows are shifted half a tile along x, or the even ones back in
+		// chunks starting on an odd row, and LOD meshes reach the first row
+		// and column of the next chunk
+		let stagger = ((index.y * self.chunk_size) % 2) as f32 * inner;
+		let min = self.orient(Vec3::new(-inner - stagger, low - skirt, -outer));
+		let max = self.orient(Vec3::new(
+			(size * 2. + 1.) * inner,
+			high,
+			size * outer * 1.5,
+		));
+		let origin = chunk_origin(index, self);
+		return Aabb::from_min_max(origin + min.min(max), origin + min.max(max));
+	}
 
-use iyes_perf_ui::{diagnostics::PerfUiEntryFPS, time::PerfUiEntryClock, PerfUiRoot};
+	/// Camera position and target with the whole map in view of a camera with
+	/// the default field of view, overridden by `camera`.
+	pub fn camera_framing(&self) -> (Vec3, Vec3) {
+		let bounds = self.map_bounds();
+		let ground = self.origin.y + self.sea_level.unwrap_or(0.);
+		let target = self
+			.camera
+			.target
+			.unwrap_or(bounds.center().extend(ground).xzy());
+		let fov = PerspectiveProjection::default().fov;
+		// looking across the map from its first corner, 45° down
+		let fit = bounds.size().length() * 0.5 / (fov * 0.5).sin();
+		let direction = match self.camera.position {
+			Some(position) => (position - target).normalize_or_zero(),
+			None => Vec3::new(-1., 2f32.sqrt(), -1.).normalize(),
+		};
+		let position = match (self.camera.position, self.camera.radius) {
+			(_, Some(radius)) => target + direction * radius,
+			(Some(position), None) => position,
+			(None, None) => target + direction * fit.max(1.),
+		};
+		return (position, target);
+	}
 
-pub struct HexGrid;
+	/// `focus` moved back over the map, to within `camera.focus_margin` of
+	/// its edges, or left where it is without a margin.
+	pub fn clamp_camera_focus(&self, focus: Vec3) -> Vec3 {
+		let Some(margin) = self.camera.focus_margin else {
+			return focus;
+		};
+		let bounds = self.map_bounds();
+		let margin = Vec2::splat(margin.max(0.));
+		let xz = focus.xz().clamp(bounds.min - margin, bounds.max + margin);
+		return Vec3::new(xz.x, focus.y, xz.y);
+	}
 
-const MAP_SIZE: u32 = 32;
-const WIREFRAME: bool = false;
-const OUTER_RADIUS: f32 = 1.;
-const INNER_RADIUS: f32 = OUTER_RADIUS * 0.866025404;
-const CHUNK_SIZE: u32 = 32;
-const HEX_CORNERS: [Vec3; 6] = [
-	Vec3::new(0., 0., OUTER_RADIUS),
-	Vec3::new(INNER_RADIUS, 0., 0.5 * OUTER_RADIUS),
-	Vec3::new(INNER_RADIUS, 0., -0.5 * OUTER_RADIUS),
-	Vec3::new(0., 0., -OUTER_RADIUS),
-	Vec3::new(-INNER_RADIUS, 0., -0.5 * OUTER_RADIUS),
-	Vec3::new(-INNER_RADIUS, 0., 0.5 * OUTER_RADIUS),
-];
+	/// Maps a position laid out pointy-top into the configured orientation.
+	/// Flat-top is the pointy-top layout mirrored across the x = z diagonal,
+	/// which keeps every index relationship but reverses triangle winding.
+	fn orient(&self, pos: Vec3) -> Vec3 {
+		return match self.orientation {
+			HexOrientation::PointyTop => pos,
+			HexOrientation::FlatTop => pos.zyx(),
+		};
+	}
+}
 
 impl Plugin for HexGrid {
 	fn build(&self, app: &mut App) {
-		app.add_systems(Startup, (create_hex_grid, setup))
-			.add_systems(Update, draw_gizmos)
-			.insert_resource(DirectionalLightShadowMap { size: 2048 });
-		if WIREFRAME {
+		assert!(
+			self.settings.chunk_size > 0,
+			"chunk_size must be at least 1"
+		);
+		app.register_type::<HexGridSettings>()
+			.register_type::<HexCoord>()
+			.register_type::<Chunk>()
+			.register_type::<FogRevealer>()
+			.register_type::<DebugDrawSettings>()
+			.init_resource::<SelectedHex>()
+			.init_resource::<LoadedChunks>()
+			.init_resource::<HexData>()
+			.init_resource::<MapGeneration>()
+			.init_resource::<DirtyChunks>()
+			.init_resource::<TileEntities>()
+			.init_resource::<FogOfWar>()
+			.init_resource::<DebugDrawSettings>()
+			.init_resource::<VertexIndexLabels>()
+			.add_event::<HexClicked>()
+			.add_event::<RegenerateMap>()
+			.add_event::<RegenerateChunk>()
+			.add_event::<ChunkRebuilt>()
+			.add_event::<MapGenerationComplete>()
+			.add_event::<SetChunkMaterial>()
+			.insert_resource(self.settings.clone())
+			.insert_resource(GridRng::new(self.settings.seed))
+			.add_systems(Startup, (create_hex_grid, setup))
+			.add_systems(
+				Update,
+				(
+					// meshes are inserted before streaming can despawn their entity
+					(
+						ramp::load_color_ramp,
+						heightmap::load_height_image,
+						regenerate_on_change,
+						regenerate_map,
+						chunk_debug::regenerate_chunks,
+						height::animate_terrain,
+						edit::rebuild_dirty_chunks,
+						swap_chunk_material,
+						spawn_chunk_meshes,
+						report_map_generation,
+						streaming::stream_chunks,
+						lod::update_chunk_lods,
+						culling::cull_chunks,
+						data::populate_hex_data,
+						tiles::forget_tile_entities,
+						tiles::spawn_tile_entities,
+						scatter::spawn_features,
+						fog::update_fog,
+						fog::draw_fog,
+					)
+						.chain()
+						.run_if(resource_exists::<ChunkMaterial>),
+					spawn_water.run_if(resource_changed::<HexGridSettings>),
+					warn_odd_torus.run_if(resource_changed::<HexGridSettings>),
+					rng::reseed_rng.run_if(resource_changed::<HexGridSettings>),
+					instancing::spawn_tile_instances.run_if(
+						resource_changed::<HexGridSettings>.or_else(on_event::<RegenerateMap>()),
+					),
+					(toggle_debug_gizmos, draw_axes, draw_corner_arrows).chain(),
+					draw_selected_hex,
+					range::draw_move_range,
+					labels::draw_coord_labels,
+					chunk_debug::draw_vertex_indices,
+					toggle_wireframe,
+					bound_camera_focus.after(PanOrbitCameraSystemSet),
+					picking::pick_hex,
+				),
+			)
+			.insert_resource(DirectionalLightShadowMap { size: 2048 })
+			.insert_resource(DebugGizmos(self.settings.debug_gizmos));
+		if self.settings.wireframe {
 			app.insert_resource(WireframeConfig {
 				global: true,
 				default_color: Color::WHITE,
@@ -45,20 +831,30 @@ impl Plugin for HexGrid {
 	}
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, settings: Res<HexGridSettings>) {
 	commands.spawn((
 		PerfUiRoot::default(),
 		PerfUiEntryFPS::default(),
 		PerfUiEntryClock::default(),
 	));
 
+	let (position, target) = settings.camera_framing();
+	let bounds = settings.map_bounds();
+	// keep the far side of the map inside the far plane
+	let far = position.distance(target) + bounds.size().length();
 	commands.spawn((
 		Camera3dBundle {
-			transform: Transform::from_xyz(0., 50., 0.)
-				.looking_at(Vec3::new(50., 0., 50.), Vec3::Y),
+			transform: Transform::from_translation(position).looking_at(target, Vec3::Y),
+			projection: Projection::Perspective(PerspectiveProjection {
+				far: far.max(PerspectiveProjection::default().far),
+				..default()
+			}),
 			..default()
 		},
 		PanOrbitCamera {
+			focus: target,
+			zoom_lower_limit: settings.camera.zoom_lower_limit,
+			zoom_upper_limit: settings.camera.zoom_upper_limit,
 			..Default::default()
 		},
 	));
@@ -77,404 +873,2220 @@ fn setup(mut commands: Commands) {
 	});
 }
 
-fn draw_gizmos(mut gizmos: Gizmos) {
-	gizmos.arrow(Vec3::ZERO, Vec3::Y * 1.5, Color::GREEN);
-	gizmos.arrow(Vec3::ZERO, Vec3::Z * 1.5, Color::BLUE);
-	gizmos.arrow(Vec3::ZERO, Vec3::X * 1.5, Color::RED);
-
-	for i in 0..6 {
-		gizmos.arrow(
-			HEX_CORNERS[i],
-			HEX_CORNERS[i] + Vec3::Y * (i + 1) as f32,
-			Color::ALICE_BLUE,
+/// Pulls the camera focus back within `CameraSettings::focus_margin` of
+/// the map after it pans.
+fn bound_camera_focus(mut cameras: Query<&mut PanOrbitCamera>, settings: Res<HexGridSettings>) {
+	if settings.camera.focus_margin.is_none() {
+		return;
+	}
+	for mut camera in &mut cameras {
+		let (focus, target) = (
+			settings.clamp_camera_focus(camera.focus),
+			settings.clamp_camera_focus(camera.target_focus),
 		);
+		if focus != camera.focus || target != camera.target_focus {
+			camera.focus = focus;
+			camera.target_focus = target;
+			// the transform already moved towards the old focus this frame
+			camera.force_update = true;
+		}
 	}
 }
 
-fn create_hex_grid(
-	mut commands: Commands,
-	mut materials: ResMut<Assets<StandardMaterial>>,
-	mut images: ResMut<Assets<Image>>,
-	mut meshes: ResMut<Assets<Mesh>>,
+fn toggle_wireframe(
+	keys: Res<ButtonInput<KeyCode>>,
+	settings: Res<HexGridSettings>,
+	wireframe: Option<ResMut<WireframeConfig>>,
 ) {
-	let debug_material = materials.add(StandardMaterial {
-		// base_color_texture: Some(images.add(uv_debug_texture())),
-		..default()
-	});
+	let (Some(key), Some(mut wireframe)) = (settings.wireframe_toggle_key, wireframe) else {
+		return;
+	};
+	if keys.just_pressed(key) {
+		wireframe.global = !wireframe.global;
+	}
+}
 
-	let noise = SuperSimplex::new(1223939298);
-	for z in 0..MAP_SIZE {
-		for x in 0..MAP_SIZE {
-			let pos = to_hex_pos(Vec3::new(x as f32, 0., z as f32) * CHUNK_SIZE as f32);
-			let mesh = create_chunk(x, z, &noise);
-			commands.spawn(PbrBundle {
-				mesh: meshes.add(mesh),
-				material: debug_material.clone(),
-				transform: Transform::from_translation(pos),
-				..default()
-			});
-		}
+/// Whether the debug gizmos are drawn, starting at
+/// `HexGridSettings::debug_gizmos`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugGizmos(pub bool);
+
+fn toggle_debug_gizmos(
+	keys: Res<ButtonInput<KeyCode>>,
+	settings: Res<HexGridSettings>,
+	mut debug: ResMut<DebugGizmos>,
+) {
+	let Some(key) = settings.debug_gizmos_toggle_key else {
+		return;
+	};
+	if keys.just_pressed(key) {
+		debug.0 = !debug.0;
 	}
 }
 
-fn create_chunk(c_x: u32, c_z: u32, noise: &SuperSimplex) -> Mesh {
-	const COUNT: usize = (CHUNK_SIZE * CHUNK_SIZE * 3 * 6) as usize;
-	let mut verts = Vec::with_capacity(COUNT);
-	let mut uvs = Vec::with_capacity(COUNT);
-	let mut normals = Vec::with_capacity(COUNT);
-	let mut indices = Vec::with_capacity(COUNT);
+/// Look of the debug gizmos. Insert it before adding the plugin to
+/// override the defaults.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct DebugDrawSettings {
+	/// Length of the world axis arrows.
+	pub axis_length: f32,
+	/// Colors of the x, y and z axis arrows.
+	pub axis_colors: [Color; 3],
+	pub corner_arrows: bool,
+	/// Corner `i` gets an arrow `i + 1` times this long.
+	pub corner_arrow_step: f32,
+	pub corner_color: Color,
+}
 
-	for z in 0..CHUNK_SIZE {
-		for x in 0..CHUNK_SIZE {
-			let height = sample_height(x + c_x * CHUNK_SIZE, z + c_z * CHUNK_SIZE, noise);
-			let off_pos = Vec3::new(x as f32, height, z as f32);
-			let grid_pos = to_hex_pos(off_pos);
-			create_tile(grid_pos, &mut verts, &mut uvs, &mut normals, &mut indices);
-		}
+impl Default for DebugDrawSettings {
+	fn default() -> Self {
+		return Self {
+			axis_length: 1.5,
+			axis_colors: [css::RED.into(), css::LIME.into(), css::BLUE.into()],
+			corner_arrows: true,
+			corner_arrow_step: 1.,
+			corner_color: css::ALICE_BLUE.into(),
+		};
 	}
-	for z in 0..CHUNK_SIZE {
-		for x in 0..CHUNK_SIZE {
-			let idx = ((x * 7) + (z * CHUNK_SIZE * 7)) as u32;
-			add_tile_sides(x, z, idx, &mut indices, &mut normals, &verts);
-		}
+}
+
+fn draw_axes(mut gizmos: Gizmos, debug: Res<DebugGizmos>, draw: Res<DebugDrawSettings>) {
+	if !debug.0 {
+		return;
+	}
+	for (axis, color) in [Vec3::X, Vec3::Y, Vec3::Z]
+		.into_iter()
+		.zip(draw.axis_colors)
+	{
+		gizmos.arrow(Vec3::ZERO, axis * draw.axis_length, color);
 	}
+}
 
-	add_chunk_sides(
-		c_x,
-		c_z,
-		&mut verts,
-		&mut indices,
-		&mut normals,
-		&mut uvs,
-		noise,
+/// Numbers the corners of the tile at the origin by arrow length.
+fn draw_corner_arrows(
+	mut gizmos: Gizmos,
+	debug: Res<DebugGizmos>,
+	draw: Res<DebugDrawSettings>,
+	settings: Res<HexGridSettings>,
+) {
+	if !debug.0 || !draw.corner_arrows {
+		return;
+	}
+	for (i, corner) in settings.hex_corners().into_iter().enumerate() {
+		let length = (i + 1) as f32 * draw.corner_arrow_step;
+		gizmos.arrow(corner, corner + Vec3::Y * length, draw.corner_color);
+	}
+}
+
+fn draw_selected_hex(
+	mut gizmos: Gizmos,
+	mut noise: Local<Option<TerrainNoise>>,
+	selected: Res<SelectedHex>,
+	settings: Res<HexGridSettings>,
+) {
+	let (Some(color), Some(coord)) = (settings.hover_outline, selected.0) else {
+		return;
+	};
+	// tile edits leave the noise alone, so it only changes with the settings
+	if settings.is_changed() {
+		*noise = None;
+	}
+	let noise = noise.get_or_insert_with(|| settings.noise());
+	let offset = coord.to_offset();
+	let height = sample_height(offset.x, offset.y, noise, &settings);
+	outline_tile(
+		&mut gizmos,
+		coord.to_world(&settings) + Vec3::Y * height,
+		color,
+		&settings,
 	);
+}
 
-	let mesh = Mesh::new(
-		PrimitiveTopology::TriangleList,
-		RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-	)
-	.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, verts)
-	.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
-	// .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
-	.with_inserted_indices(Indices::U32(indices))
-	.with_duplicated_vertices()
-	.with_computed_flat_normals();
-	return mesh;
+/// Outlines the top of the tile centered on `center`.
+fn outline_tile(gizmos: &mut Gizmos, center: Vec3, color: Color, settings: &HexGridSettings) {
+	// lift the outline slightly so it doesn't z-fight with the tile top
+	let center = center + Vec3::Y * 0.01;
+	let corners = settings.hex_corners();
+	gizmos.linestrip(
+		corners
+			.iter()
+			.chain(&corners[..1])
+			.map(|corner| center + *corner),
+		color,
+	);
 }
 
-fn to_hex_pos(pos: Vec3) -> Vec3 {
-	let x = (pos.x + pos.z * 0.5 - (pos.z / 2.).floor()) * (INNER_RADIUS * 2.);
-	return Vec3::new(x, pos.y, pos.z * OUTER_RADIUS * 1.5);
+/// A generated chunk of `chunk_size` × `chunk_size` tiles.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Chunk {
+	pub index: UVec2,
+	/// Level of detail the current mesh was built at, 0 being every tile.
+	pub lod: u32,
 }
 
-fn add_chunk_sides(
-	c_x: u32,
-	c_z: u32,
-	verts: &mut Vec<Vec3>,
-	indices: &mut Vec<u32>,
-	normals: &mut Vec<Vec3>,
-	uvs: &mut Vec<Vec2>,
-	noise: &SuperSimplex,
-) {
-	if c_x < MAP_SIZE - 1 {
-		//draw top side
-		let x = CHUNK_SIZE - 1;
-		for z in 0..CHUNK_SIZE {
-			let c_tile = ((x * 7) + (z * 7 * CHUNK_SIZE)) as u32 + 1;
-			let mut height = sample_height(x + 1 + c_x * CHUNK_SIZE, z + c_z * CHUNK_SIZE, noise);
-			let mut off_pos = Vec3::new(x as f32, height, z as f32);
-			let mut grid_pos = to_hex_pos(off_pos);
-			let mut center = Vec3::new(grid_pos.x, 0., grid_pos.z);
-
-			let mut idx = verts.len() as u32;
-
-			let mut p = grid_pos + HEX_CORNERS[2];
-			verts.push(p);
-			uvs.push(p.xz() / CHUNK_SIZE as f32);
-			normals.push((p - center).normalize());
-
-			p = grid_pos + HEX_CORNERS[1];
-			verts.push(p);
-			uvs.push(p.xz() / CHUNK_SIZE as f32);
-			normals.push((p - center).normalize());
-			create_quad(c_tile + 1, c_tile + 2, idx, idx + 1, indices, verts);
-
-			if z % 2 == 1 {
-				if z > 0 {
-					height =
-						sample_height(x + 1 + c_x * CHUNK_SIZE, z - 1 + c_z * CHUNK_SIZE, noise);
-					off_pos = Vec3::new(x as f32, height, z as f32);
-					grid_pos = to_hex_pos(off_pos);
-					center = Vec3::new(grid_pos.x, 0., grid_pos.z);
-
-					idx = verts.len() as u32;
-					p = grid_pos + HEX_CORNERS[2];
-					verts.push(p);
-					uvs.push(p.xz() / CHUNK_SIZE as f32);
-					normals.push((p - center).normalize());
-
-					p = grid_pos + HEX_CORNERS[3];
-					verts.push(p);
-					uvs.push(p.xz() / CHUNK_SIZE as f32);
-					normals.push((p - center).normalize());
-
-					create_quad(c_tile + 2, c_tile + 3, idx + 1, idx, indices, verts);
-				}
-				if z < CHUNK_SIZE - 1 {
-					height =
-						sample_height(x + 1 + c_x * CHUNK_SIZE, z + 1 + c_z * CHUNK_SIZE, noise);
-					off_pos = Vec3::new(x as f32, height, z as f32);
-					grid_pos = to_hex_pos(off_pos);
-					center = Vec3::new(grid_pos.x, 0., grid_pos.z);
-
-					idx = verts.len() as u32;
-					p = grid_pos + HEX_CORNERS[0];
-					verts.push(p);
-					uvs.push(p.xz() / CHUNK_SIZE as f32);
-					normals.push((p - center).normalize());
-
-					p = grid_pos + HEX_CORNERS[1];
-					verts.push(p);
-					uvs.push(p.xz() / CHUNK_SIZE as f32);
-					normals.push((p - center).normalize());
-
-					create_quad(c_tile, c_tile + 1, idx + 1, idx, indices, verts);
-				}
+/// Triangle data of a chunk in its local space, for building a trimesh
+/// collider with whichever physics crate the game uses. The crate itself has
+/// no physics dependency, so there is no feature flag to enable.
+#[derive(Component, Debug, Clone, Default)]
+pub struct ChunkMesh {
+	pub vertices: Vec<Vec3>,
+	pub triangles: Vec<[u32; 3]>,
+}
+
+impl ChunkMesh {
+	pub fn from_mesh(mesh: &Mesh) -> Self {
+		let vertices = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+			Some(VertexAttributeValues::Float32x3(positions)) => {
+				positions.iter().map(|p| Vec3::from(*p)).collect::<Vec<_>>()
 			}
-		}
+			_ => Vec::new(),
+		};
+		let indices = match mesh.indices() {
+			Some(indices) => indices.iter().map(|i| i as u32).collect::<Vec<_>>(),
+			None => (0..vertices.len() as u32).collect(),
+		};
+		let triangles = indices
+			.chunks_exact(3)
+			.map(|tri| [tri[0], tri[1], tri[2]])
+			.collect();
+		return Self {
+			vertices,
+			triangles,
+		};
 	}
-	if c_z < MAP_SIZE - 1 {
-		//draw right side
-		let z = CHUNK_SIZE - 1;
-		for x in 0..CHUNK_SIZE {
-			let c_tile = ((x * 7) + (z * 7 * CHUNK_SIZE)) as u32 + 1;
-			let mut height = sample_height(x + c_x * CHUNK_SIZE, z + 1 + c_z * CHUNK_SIZE, noise);
-			let mut off_pos = Vec3::new(x as f32, height, z as f32);
-			let mut grid_pos = to_hex_pos(off_pos);
-			let mut center = Vec3::new(grid_pos.x, 0., grid_pos.z);
+}
+
+/// Mesh generation still running on the async compute pool.
+#[derive(Component)]
+struct ChunkTask(Task<(Mesh, Option<ChunkMesh>)>);
 
-			let idx = verts.len() as u32;
+/// Material every chunk is drawn with. Send `SetChunkMaterial` to swap it.
+#[derive(Resource, Deref)]
+pub struct ChunkMaterial {
+	#[deref]
+	current: Handle<StandardMaterial>,
+	generated: Handle<StandardMaterial>,
+}
 
-			let mut p = grid_pos + HEX_CORNERS[0];
-			verts.push(p);
-			uvs.push(p.xz() / CHUNK_SIZE as f32);
-			normals.push((p - center).normalize());
+impl ChunkMaterial {
+	/// The material built from `HexGridSettings`, kept up to date with the
+	/// atlas across regenerations.
+	pub fn generated(&self) -> &Handle<StandardMaterial> {
+		return &self.generated;
+	}
+}
 
-			p = grid_pos + HEX_CORNERS[5];
-			verts.push(p);
-			uvs.push(p.xz() / CHUNK_SIZE as f32);
-			normals.push((p - center).normalize());
-			create_quad(c_tile + 5, c_tile, idx, idx + 1, indices, verts);
+/// Draws every chunk with another material, keeping their meshes.
+#[derive(Event, Debug, Clone)]
+pub struct SetChunkMaterial(pub Handle<StandardMaterial>);
 
-			height = sample_height(x + 1 + c_x * CHUNK_SIZE, z + 1 + c_z * CHUNK_SIZE, noise);
-			off_pos = Vec3::new(x as f32, height, z as f32);
-			grid_pos = to_hex_pos(off_pos);
-			center = Vec3::new(grid_pos.x, 0., grid_pos.z);
+fn swap_chunk_material(
+	mut events: EventReader<SetChunkMaterial>,
+	mut material: ResMut<ChunkMaterial>,
+	mut chunks: Query<&mut Handle<StandardMaterial>, With<Chunk>>,
+) {
+	let Some(SetChunkMaterial(handle)) = events.read().last() else {
+		return;
+	};
+	material.current = handle.clone();
+	for mut chunk in &mut chunks {
+		*chunk = handle.clone();
+	}
+}
 
-			p = grid_pos + HEX_CORNERS[0];
-			verts.push(p);
-			uvs.push(p.xz() / CHUNK_SIZE as f32);
-			normals.push((p - center).normalize());
+fn create_hex_grid(
+	mut commands: Commands,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	mut images: ResMut<Assets<Image>>,
+	mut loaded: ResMut<LoadedChunks>,
+	mut generation: ResMut<MapGeneration>,
+	settings: Res<HexGridSettings>,
+) {
+	let mut material = StandardMaterial::default();
+	apply_textures(&mut material, &mut images, &settings);
+	let material = materials.add(material);
+	commands.insert_resource(ChunkMaterial {
+		current: material.clone(),
+		generated: material,
+	});
+	spawn_map(&mut commands, &mut loaded, &mut generation, &settings);
+}
 
-			p = grid_pos + HEX_CORNERS[1];
-			verts.push(p);
-			uvs.push(p.xz() / CHUNK_SIZE as f32);
-			normals.push((p - center).normalize());
-			create_quad(c_tile, c_tile + 1, idx + 3, idx + 2, indices, verts);
+/// Points `material` at the atlas or the grid lines, or the UV debug texture
+/// without either, and at `HexGridSettings::textures`, and sets which faces it
+/// culls.
+fn apply_textures(
+	material: &mut StandardMaterial,
+	images: &mut Assets<Image>,
+	settings: &HexGridSettings,
+) {
+	let textures = &settings.textures;
+	material.base_color_texture = match (&settings.atlas, settings.grid_lines) {
+		(Some(atlas), grid_lines) => {
+			if grid_lines.is_some() {
+				warn!("grid_lines aren't drawn with an atlas");
+			}
+			Some(atlas.texture.clone())
 		}
+		(None, Some(grid_lines)) => Some(images.add(grid_lines.image(settings))),
+		(None, None) => Some(images.add(uv_debug_texture())),
+	};
+	material.normal_map_texture = textures.normal_map.clone();
+	material.metallic_roughness_texture = textures.metallic_roughness.clone();
+	material.occlusion_texture = textures.occlusion.clone();
+	material.double_sided = settings.double_sided;
+	material.cull_mode = (!settings.double_sided).then_some(Face::Back);
+}
+
+/// Rebuilds every chunk from the current `HexGridSettings`, e.g. after
+/// changing the seed.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct RegenerateMap;
+
+fn regenerate_on_change(settings: Res<HexGridSettings>, mut events: EventWriter<RegenerateMap>) {
+	// the initial map is built at startup
+	if settings.regenerate_on_change && settings.is_changed() && !settings.is_added() {
+		events.send(RegenerateMap);
 	}
 }
 
-fn add_tile_sides(
-	x: u32,
-	z: u32,
-	idx: u32,
-	indices: &mut Vec<u32>,
-	normals: &mut Vec<Vec3>,
-	verts: &Vec<Vec3>,
+fn regenerate_map(
+	mut commands: Commands,
+	mut events: EventReader<RegenerateMap>,
+	chunks: Query<&Handle<Mesh>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	mut images: ResMut<Assets<Image>>,
+	mut loaded: ResMut<LoadedChunks>,
+	mut data: ResMut<HexData>,
+	mut generation: ResMut<MapGeneration>,
+	material: Res<ChunkMaterial>,
+	settings: Res<HexGridSettings>,
 ) {
-	let c_tile = idx + 1;
-	const TILE_WIDTH: u32 = 7;
-	const ROW_WIDTH: u32 = CHUNK_SIZE * TILE_WIDTH;
-
-	if x < CHUNK_SIZE - 1 {
-		let n_tile = c_tile + TILE_WIDTH;
-		create_quad(
-			c_tile + 1,
-			c_tile + 2,
-			n_tile + 4,
-			n_tile + 5,
-			indices,
-			verts,
-		);
+	// any number of requests in a frame rebuild the map once
+	if events.is_empty() {
+		return;
 	}
-
-	if z < CHUNK_SIZE - 1 {
-		if z % 2 == 0 {
-			let d_tile = c_tile + ROW_WIDTH;
-			create_quad(c_tile, c_tile + 1, d_tile + 3, d_tile + 4, indices, verts);
-		} else if x < CHUNK_SIZE - 1 {
-			let d_tile = c_tile + ROW_WIDTH + TILE_WIDTH;
-			create_quad(c_tile, c_tile + 1, d_tile + 3, d_tile + 4, indices, verts);
-		}
+	events.clear();
+	if settings.chunk_size == 0 {
+		warn!("chunk_size must be at least 1, keeping the current map");
+		return;
 	}
 
-	if x > 0 && z % 2 == 0 {
-		let d_tile = c_tile + ROW_WIDTH - TILE_WIDTH;
-		create_quad(c_tile + 5, c_tile, d_tile + 2, d_tile + 3, indices, verts);
+	if let Some(material) = materials.get_mut(material.generated()) {
+		apply_textures(material, &mut images, &settings);
 	}
-	if z % 2 == 1 && z < CHUNK_SIZE - 1 {
-		let d_tile = c_tile + ROW_WIDTH;
-		create_quad(c_tile + 5, c_tile, d_tile + 2, d_tile + 3, indices, verts);
+
+	for (_, entity) in loaded.drain() {
+		if let Ok(mesh) = chunks.get(entity) {
+			meshes.remove(mesh);
+		}
+		commands.entity(entity).despawn_recursive();
 	}
+	data.clear();
+	spawn_map(&mut commands, &mut loaded, &mut generation, &settings);
 }
 
-fn create_quad(v1: u32, v2: u32, v3: u32, v4: u32, indices: &mut Vec<u32>, verts: &Vec<Vec3>) {
-	let vert1 = verts[v1 as usize];
-	let vert3 = verts[v3 as usize];
-	if vert1.y == vert3.y {
+fn spawn_map(
+	commands: &mut Commands,
+	loaded: &mut LoadedChunks,
+	generation: &mut MapGeneration,
+	settings: &HexGridSettings,
+) {
+	*generation = MapGeneration::default();
+	// streamed maps only spawn the chunks around the camera
+	if settings.streaming.is_some() || settings.instanced {
 		return;
 	}
-	indices.push(v1);
-	indices.push(v3);
-	indices.push(v2);
+	generation.started = Some(Instant::now());
 
-	indices.push(v1);
-	indices.push(v4);
-	indices.push(v3);
+	let noise = settings.noise();
+	for z in 0..settings.map_size.y {
+		for x in 0..settings.map_size.x {
+			let index = UVec2::new(x, z);
+			let entity = spawn_chunk(commands, index, 0, &noise, settings);
+			loaded.insert(index.as_ivec2(), entity);
+			generation.pending.insert(entity);
+		}
+	}
 }
 
-fn create_tile(
-	pos: Vec3,
-	verts: &mut Vec<Vec3>,
-	uvs: &mut Vec<Vec2>,
-	normals: &mut Vec<Vec3>,
-	indices: &mut Vec<u32>,
-) {
-	let idx = verts.len() as u32;
-	let center = Vec3::new(pos.x, 0., pos.z);
-	normals.push(Vec3::Y);
-	uvs.push(pos.xz() / CHUNK_SIZE as f32);
-	verts.push(pos);
-	for i in 0..6 {
-		let p = pos + HEX_CORNERS[i];
-		verts.push(p);
-		uvs.push(p.xz() / CHUNK_SIZE as f32);
-		normals.push((p - center).normalize());
-		indices.push(idx);
-		indices.push(idx + 1 + i as u32);
-		indices.push(idx + 1 + ((i as u32 + 1) % 6));
-	}
+/// Sent once all chunks of a newly generated map have their mesh. Streamed
+/// and instanced maps report right away, as they have no initial chunks.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct MapGenerationComplete;
+
+/// Chunks of the current map still waiting for their mesh.
+#[derive(Resource, Debug, Default)]
+pub struct MapGeneration {
+	pending: HashSet<Entity>,
+	reported: bool,
+	started: Option<Instant>,
+	chunks: usize,
+	vertices: usize,
+	triangles: usize,
 }
 
-const NOISE_SCALE: f64 = 350.;
-const SEA_LEVEL: f64 = 5.;
+/// How long the last generated map took from spawning its chunks until the
+/// last one got its mesh, and how large the meshes came out. Insert it to
+/// have the plugin fill it in whenever a map completes. Streamed and
+/// instanced maps have no initial chunks and record nothing.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GenerationMetrics {
+	pub duration: Duration,
+	pub chunks: usize,
+	pub vertices: usize,
+	pub triangles: usize,
+}
+
+impl MapGeneration {
+	pub fn remaining(&self) -> usize {
+		return self.pending.len();
+	}
 
-fn sample_height(x: u32, y: u32, noise: &SuperSimplex) -> f32 {
-	let mut elevation = 0.;
+	pub fn is_complete(&self) -> bool {
+		return self.pending.is_empty();
+	}
+}
 
-	let x_s = x as f64 / NOISE_SCALE;
-	let y_s = y as f64 / NOISE_SCALE;
+fn report_map_generation(
+	mut generation: ResMut<MapGeneration>,
+	mut complete: EventWriter<MapGenerationComplete>,
+	mut metrics: Option<ResMut<GenerationMetrics>>,
+) {
+	if generation.is_complete() && !generation.reported {
+		generation.reported = true;
+		complete.send(MapGenerationComplete);
+		if let (Some(metrics), Some(started)) = (&mut metrics, generation.started) {
+			**metrics = GenerationMetrics {
+				duration: started.elapsed(),
+				chunks: generation.chunks,
+				vertices: generation.vertices,
+				triangles: generation.triangles,
+			};
+		}
+	}
+}
 
-	let first_layer = sample_layer(noise, x_s, y_s, 2.14, 0.87, 0.77, -0.2, 2.93, 4);
-	elevation += first_layer;
-	elevation += sample_layer(noise, x_s, y_s, 2.85, 2., 1., 0., -0.23, 4);
-	elevation += mask(
-		first_layer,
-		sample_layer_rigid(noise, x_s, y_s, 2.6, 4., 1.57, 0., 10.44, 0.35, 4),
-	);
-	elevation += mask(
-		first_layer,
-		sample_layer_rigid(noise, x_s, y_s, 3.87, 5.8, 0., 0., -1., 4.57, 3),
-	);
+/// Spawns a chunk entity whose mesh is generated in the background.
+fn spawn_chunk(
+	commands: &mut Commands,
+	index: UVec2,
+	lod: u32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Entity {
+	let task = chunk_task(index, lod, noise, settings);
+	let transform = Transform::from_translation(chunk_origin(index, settings));
+	return commands
+		.spawn((
+			Chunk { index, lod },
+			task,
+			SpatialBundle::from_transform(transform),
+		))
+		.id();
+}
 
-	return elevation as f32;
-}
-
-fn mask(first_layer: f64, value: f64) -> f64 {
-	let mask = (first_layer - SEA_LEVEL).max(0.);
-	return value * mask;
-}
-
-fn sample_layer(
-	noise: &SuperSimplex,
-	x: f64,
-	z: f64,
-	base_roughness: f64,
-	roughness: f64,
-	persistence: f64,
-	min_value: f64,
-	strength: f64,
-	layers: usize,
-) -> f64 {
-	let mut freq: f64 = base_roughness;
-	let mut amp: f64 = 1.;
-	let mut value = 0.;
-
-	for _ in 0..layers {
-		let v = noise.get([x * freq, z * freq]);
-		value += (v + 1.) * 0.5 * amp;
-		freq *= roughness;
-		amp *= persistence;
-	}
-	value -= min_value;
-	return value * strength;
-}
-
-fn sample_layer_rigid(
-	noise: &SuperSimplex,
-	x: f64,
-	z: f64,
-	base_roughness: f64,
-	roughness: f64,
-	persistence: f64,
-	min_value: f64,
-	strength: f64,
-	weight_multi: f64,
-	layers: usize,
-) -> f64 {
-	let mut freq: f64 = base_roughness;
-	let mut amp: f64 = 1.;
-	let mut value = 0.;
-	let mut weight = 1.;
-	for _ in 0..layers {
-		let mut v = 1. - noise.get([x * freq, z * freq]).abs();
-		v *= v;
-		v *= weight;
-		weight = v * weight_multi;
-		weight = weight.clamp(0., 1.);
-		value += v * amp;
-		freq *= roughness;
-		amp *= persistence;
-	}
-	value -= min_value;
-	return value * strength;
+fn chunk_task(
+	index: UVec2,
+	lod: u32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> ChunkTask {
+	let (noise, settings) = (noise.clone(), settings.clone());
+	let task = AsyncComputeTaskPool::get().spawn(async move {
+		let mesh = create_chunk(index.x, index.y, lod, &noise, &settings);
+		let collider = settings
+			.generate_collider
+			.then(|| ChunkMesh::from_mesh(&mesh));
+		return (mesh, collider);
+	});
+	return ChunkTask(task);
 }
 
-fn uv_debug_texture() -> Image {
-	const TEXTURE_SIZE: usize = 8;
+fn warn_odd_torus(settings: Res<HexGridSettings>) {
+	if settings.wrap != settings.wrap_mode() {
+		warn!(
+			"WrapMode::Torus needs an even number of tile rows, got {}; wrapping along x only",
+			settings.map_tiles().y
+		);
+	}
+}
 
-	let mut palette: [u8; 32] = [
-		255, 102, 159, 255, 255, 159, 102, 255, 236, 255, 102, 255, 121, 255, 102, 255, 102, 255,
-		198, 255, 102, 198, 255, 255, 121, 102, 255, 255, 236, 102, 255, 255,
-	];
+#[derive(Component)]
+pub struct Water;
 
-	let mut texture_data = [0; TEXTURE_SIZE * TEXTURE_SIZE * 4];
-	for y in 0..TEXTURE_SIZE {
-		let offset = TEXTURE_SIZE * y * 4;
-		texture_data[offset..(offset + TEXTURE_SIZE * 4)].copy_from_slice(&palette);
-		palette.rotate_right(4);
+/// Replaces the water plane so it follows `sea_level` and the map size.
+fn spawn_water(
+	mut commands: Commands,
+	water: Query<(Entity, &Handle<Mesh>, &Handle<StandardMaterial>), With<Water>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	settings: Res<HexGridSettings>,
+) {
+	for (entity, mesh, material) in &water {
+		meshes.remove(mesh);
+		materials.remove(material);
+		commands.entity(entity).despawn_recursive();
 	}
 
-	Image::new_fill(
-		Extent3d {
-			width: TEXTURE_SIZE as u32,
-			height: TEXTURE_SIZE as u32,
-			depth_or_array_layers: 1,
-		},
+	let bounds = settings.map_bounds();
+	let Some(sea_level) = settings.sea_level else {
+		return;
+	};
+	if bounds.is_empty() {
+		return;
+	}
+	commands.spawn((
+		Water,
+		PbrBundle {
+			mesh: meshes.add(
+				Plane3d::default()
+					.mesh()
+					.size(bounds.width(), bounds.height()),
+			),
+			material: materials.add(StandardMaterial {
+				base_color: Color::srgba(0.1, 0.3, 0.6, 0.6),
+				alpha_mode: AlphaMode::Blend,
+				perceptual_roughness: 0.1,
+				..default()
+			}),
+			transform: Transform::from_translation(
+				bounds.center().extend(settings.origin.y + sea_level).xzy(),
+			),
+			..default()
+		},
+	));
+}
+
+fn spawn_chunk_meshes(
+	mut commands: Commands,
+	mut tasks: Query<(Entity, &Chunk, &mut ChunkTask)>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut generation: ResMut<MapGeneration>,
+	material: Res<ChunkMaterial>,
+	settings: Res<HexGridSettings>,
+) {
+	for (entity, chunk, mut task) in &mut tasks {
+		let Some((mesh, collider)) = block_on(poll_once(&mut task.0)) else {
+			continue;
+		};
+		if generation.pending.remove(&entity) {
+			let vertices = mesh.count_vertices();
+			generation.chunks += 1;
+			generation.vertices += vertices;
+			generation.triangles += mesh.indices().map_or(vertices, |indices| indices.len()) / 3;
+		}
+		let mut chunk_commands = commands.entity(entity);
+		match collider {
+			Some(collider) => chunk_commands.insert(collider),
+			None => chunk_commands.remove::<ChunkMesh>(),
+		};
+		// Bevy only computes bounds for entities without any, so rebuilt
+		// meshes would keep culling by the first one's
+		if let Some(aabb) = mesh.compute_aabb() {
+			chunk_commands.insert(aabb);
+		}
+		chunk_commands.remove::<ChunkTask>().insert(PbrBundle {
+			mesh: meshes.add(mesh),
+			material: material.clone(),
+			transform: Transform::from_translation(chunk_origin(chunk.index, &settings)),
+			..default()
+		});
+	}
+}
+
+/// World position of the middle of a chunk.
+fn chunk_center(index: UVec2, settings: &HexGridSettings) -> Vec3 {
+	return (chunk_origin(index, settings) + chunk_origin(index + 1, settings)) * 0.5;
+}
+
+/// World position of the first tile of a chunk.
+fn chunk_origin(index: UVec2, settings: &HexGridSettings) -> Vec3 {
+	let offset = (index * settings.chunk_size).as_vec2();
+	return settings.world_origin() + to_hex_pos(Vec3::new(offset.x, 0., offset.y), settings);
+}
+
+fn create_chunk(
+	c_x: u32,
+	c_z: u32,
+	lod: u32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Mesh {
+	if lod > 0 {
+		return lod::create_lod_chunk(c_x, c_z, lod, noise, settings);
+	}
+
+	let chunk_size = settings.chunk_size;
+	let inset = settings.tile_inset.clamp(0., 1.);
+	let lattice = TileLattice::new(settings.subdivisions);
+	let stride = lattice.stride();
+	// 7 vertices and 6 triangles per tile top unless subdivided, and at most
+	// three walls of 4 vertices and 2 triangles per tile and along the two
+	// stitched chunk edges, or six around each inset tile
+	let tiles = (chunk_size * chunk_size) as usize;
+	let walls = match inset > 0. {
+		true => 6 * tiles,
+		false => 3 * tiles + 4 * chunk_size as usize,
+	};
+	let vert_count = stride * tiles + 4 * walls;
+	let index_count = 3 * lattice.triangles.len() * tiles + 6 * walls;
+	let corners = settings.hex_corners().map(|corner| corner * (1. - inset));
+	let origin = (UVec2::new(c_x, c_z) * chunk_size).as_ivec2();
+	let mut verts = Vec::with_capacity(vert_count);
+	let mut uvs = Vec::with_capacity(vert_count);
+	let mut normals = Vec::with_capacity(vert_count);
+	let mut indices = Vec::with_capacity(index_count);
+
+	let mut tile_colors = Vec::with_capacity(stride * tiles);
+	// the atlas cell of each tile, which its walls take too when higher
+	let mut cells = Vec::with_capacity(tiles);
+	// tiles outside `HexGridSettings::shape` and hidden ones keep their
+	// vertices, so every tile sits at the same place in the mesh, but get no
+	// triangles
+	let mut present = Vec::with_capacity(tiles);
+	for z in 0..chunk_size {
+		for x in 0..chunk_size {
+			let (g_x, g_z) = (origin.x + x as i32, origin.y + z as i32);
+			let first_index = indices.len();
+			let coord = HexCoord::from_offset(g_x, g_z);
+			present.push(settings.shows(coord));
+			let height = sample_height(g_x, g_z, noise, settings);
+			let neighbors = settings.color_neighbors(g_x, g_z, noise);
+			let color = settings.tile_vertex_color(g_x, g_z, height, &neighbors);
+			tile_colors.extend(std::iter::repeat_n(color, stride));
+			let cell = settings.tile_rect(g_x, g_z, height, &neighbors);
+			cells.push(cell);
+			let off_pos = Vec3::new(x as f32, height, z as f32);
+			let grid_pos = to_chunk_pos(off_pos, origin.y, settings);
+			if settings.subdivisions == 0 {
+				create_tile(
+					grid_pos,
+					cell,
+					&corners,
+					settings,
+					&mut verts,
+					&mut uvs,
+					&mut normals,
+					&mut indices,
+				);
+			} else {
+				let world = coord.to_world(settings).xz();
+				subdivide::create_subdivided_tile(
+					grid_pos,
+					world,
+					cell,
+					&lattice,
+					&corners,
+					noise,
+					settings,
+					&mut verts,
+					&mut uvs,
+					&mut normals,
+					&mut indices,
+				);
+			}
+			if !present.last().unwrap() {
+				indices.truncate(first_index);
+			}
+		}
+	}
+	let per_tile_uvs = settings.atlas.is_some() || settings.grid_lines.is_some();
+	if settings.merge_flat_tops && inset == 0. && settings.subdivisions == 0 && !per_tile_uvs {
+		greedy::merge_tile_tops(
+			origin.y,
+			chunk_size,
+			&verts,
+			&tile_colors,
+			&present,
+			&mut indices,
+		);
+	}
+	// inset tiles no longer share corners with their neighbors, so each one
+	// stands on its own
+	if inset > 0. {
+		add_tile_skirts(
+			&present,
+			&cells,
+			&lattice,
+			&mut verts,
+			&mut uvs,
+			&mut normals,
+			&mut indices,
+			settings,
+		);
+		add_tile_spans(
+			c_x,
+			c_z,
+			&corners,
+			noise,
+			settings,
+			&mut verts,
+			&mut uvs,
+			&mut normals,
+			&mut indices,
+		);
+		return finish_mesh(verts, uvs, normals, indices, &tile_colors, settings);
+	}
+
+	// subdivided tiles meet their neighbors along the terrain between them,
+	// leaving no walls to build
+	if settings.subdivisions == 0 {
+		for z in 0..chunk_size {
+			for x in 0..chunk_size {
+				let idx = (x * 7) + (z * chunk_size * 7);
+				add_tile_sides(
+					x,
+					z,
+					idx,
+					origin,
+					&cells,
+					settings,
+					&mut verts,
+					&mut uvs,
+					&mut normals,
+					&mut indices,
+				);
+			}
+		}
+
+		add_chunk_sides(
+			c_x,
+			c_z,
+			&cells,
+			&mut verts,
+			&mut indices,
+			&mut normals,
+			&mut uvs,
+			noise,
+			settings,
+		);
+	}
+
+	if let Some(depth) = settings.chunk_skirt {
+		add_chunk_skirt(
+			c_x,
+			c_z,
+			depth,
+			&lattice,
+			&cells,
+			&mut verts,
+			&mut indices,
+			&mut normals,
+			&mut uvs,
+			settings,
+		);
+	}
+
+	add_tile_spans(
+		c_x,
+		c_z,
+		&corners,
+		noise,
+		settings,
+		&mut verts,
+		&mut uvs,
+		&mut normals,
+		&mut indices,
+	);
+	return finish_mesh(verts, uvs, normals, indices, &tile_colors, settings);
+}
+
+fn finish_mesh(
+	mut verts: Vec<Vec3>,
+	mut uvs: Vec<Vec2>,
+	mut normals: Vec<Vec3>,
+	mut indices: Vec<u32>,
+	tile_colors: &[Color],
+	settings: &HexGridSettings,
+) -> Mesh {
+	// vertices past `tile_colors` are colored by their height, while atlas
+	// textures and ramp colors are tinted by them
+	let (low, high) = match settings.ramp {
+		Some(_) => height::height_bounds(settings),
+		None => (0., 0.),
+	};
+	let mut colors = verts
+		.iter()
+		.enumerate()
+		.map(|(i, v)| {
+			let tile = tile_colors.get(i).copied();
+			return match (&settings.atlas, &settings.ramp) {
+				(Some(_), _) => tile.unwrap_or(Color::WHITE),
+				(None, Some(ramp)) => {
+					let ramp = ramp.sample((v.y - low) / (high - low).max(f32::EPSILON));
+					let (a, b) = (ramp.to_srgba(), tile.unwrap_or(Color::WHITE).to_srgba());
+					Color::srgba(a.red * b.red, a.green * b.green, a.blue * b.blue, a.alpha)
+				}
+				(None, None) => tile.unwrap_or_else(|| settings.biome_color(v.y)),
+			};
+		})
+		.collect::<Vec<_>>();
+
+	if let Some(tolerance) = settings.weld_tolerance {
+		uvs = verts.iter().map(|v| chunk_uv(*v, settings)).collect();
+		weld_vertices(
+			&mut verts,
+			&mut uvs,
+			&mut normals,
+			&mut colors,
+			&mut indices,
+			tolerance,
+		);
+	}
+
+	if settings.orientation == HexOrientation::FlatTop {
+		for tri in indices.chunks_exact_mut(3) {
+			tri.swap(1, 2);
+		}
+	}
+
+	if settings.normal_mode == NormalMode::Smooth {
+		normals = compute_smooth_normals(&verts, &indices);
+	}
+
+	let colors = colors
+		.iter()
+		.map(|color| color.to_linear().to_f32_array())
+		.collect::<Vec<_>>();
+
+	debug_assert!(indices.iter().all(|&i| (i as usize) < verts.len()));
+	let mut mesh = Mesh::new(
+		PrimitiveTopology::TriangleList,
+		RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+	)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, verts)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+	.with_inserted_indices(Indices::U32(indices));
+	if settings.normal_mode == NormalMode::Flat {
+		mesh.duplicate_vertices();
+		mesh.compute_flat_normals();
+	}
+	if settings.textures.normal_map.is_some() {
+		if let Err(err) = mesh.generate_tangents() {
+			warn!("chunk has no tangents for its normal map: {err}");
+		}
+	}
+	return mesh;
+}
+
+/// Texture coordinate of `pos` within a chunk stretched across the whole
+/// chunk, or the middle of its atlas cell.
+fn chunk_uv(pos: Vec3, settings: &HexGridSettings) -> Vec2 {
+	if settings.atlas.is_some() {
+		return settings.atlas_rect(pos.y).center();
+	}
+	// orienting is its own inverse
+	let pos = settings.orient(pos);
+	let tile = Vec2::new(
+		pos.x / (settings.inner_radius() * 2.),
+		pos.z / (settings.outer_radius * 1.5),
+	);
+	return (tile / settings.chunk_size as f32).clamp(Vec2::ZERO, Vec2::ONE);
+}
+
+/// Replaces vertices of the same color that snap to the same `tolerance`
+/// sized cell with the first one found, dropping triangles that collapse. The
+/// first vertex also lends its UV and normal to the others.
+fn weld_vertices(
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	colors: &mut Vec<Color>,
+	indices: &mut Vec<u32>,
+	tolerance: f32,
+) {
+	let tolerance = tolerance.max(f32::EPSILON);
+	let mut cells = HashMap::<(IVec3, [u32; 4]), u32>::with_capacity(verts.len());
+	let mut remap = Vec::with_capacity(verts.len());
+	let mut kept = 0;
+	for i in 0..verts.len() {
+		let cell = (verts[i] / tolerance).round().as_ivec3();
+		// corners of tiles colored by a stored biome keep their own color
+		let color = colors[i].to_srgba().to_f32_array().map(f32::to_bits);
+		let index = *cells.entry((cell, color)).or_insert_with(|| {
+			verts[kept] = verts[i];
+			uvs[kept] = uvs[i];
+			normals[kept] = normals[i];
+			colors[kept] = colors[i];
+			kept += 1;
+			return kept as u32 - 1;
+		});
+		remap.push(index);
+	}
+	verts.truncate(kept);
+	uvs.truncate(kept);
+	normals.truncate(kept);
+	colors.truncate(kept);
+
+	let mut welded = Vec::with_capacity(indices.len());
+	for tri in indices.chunks_exact(3) {
+		let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| remap[i as usize]);
+		if a != b && b != c && a != c {
+			welded.extend([a, b, c]);
+		}
+	}
+	*indices = welded;
+}
+
+/// Averages the area-weighted normals of every face sharing a vertex.
+fn compute_smooth_normals(verts: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+	let mut normals = vec![Vec3::ZERO; verts.len()];
+	for tri in indices.chunks_exact(3) {
+		let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| verts[i as usize]);
+		let face = (b - a).cross(c - a);
+		for i in tri {
+			normals[*i as usize] += face;
+		}
+	}
+	return normals
+		.into_iter()
+		.map(|n| n.try_normalize().unwrap_or(Vec3::Y))
+		.collect();
+}
+
+fn to_hex_pos(pos: Vec3, settings: &HexGridSettings) -> Vec3 {
+	let x = (pos.x + pos.z * 0.5 - (pos.z / 2.).floor()) * (settings.inner_radius() * 2.);
+	return settings.orient(Vec3::new(x, pos.y, pos.z * settings.outer_radius * 1.5));
+}
+
+/// Like `to_hex_pos`, relative to the origin of a chunk starting at tile row
+/// `first_row`. Chunks starting on an odd row stagger their even rows.
+fn to_chunk_pos(pos: Vec3, first_row: i32, settings: &HexGridSettings) -> Vec3 {
+	let parity = Vec3::Z * first_row.rem_euclid(2) as f32;
+	return to_hex_pos(pos + parity, settings) - to_hex_pos(parity, settings);
+}
+
+fn add_chunk_sides(
+	c_x: u32,
+	c_z: u32,
+	cells: &[Rect],
+	verts: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+	normals: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) {
+	// an empty map has no edges to stitch
+	if settings.is_empty() {
+		return;
+	}
+	let chunk_size = settings.chunk_size;
+	let origin = (UVec2::new(c_x, c_z) * chunk_size).as_ivec2();
+	let index = IVec2::new(c_x as i32, c_z as i32);
+	let last = chunk_size.saturating_sub(1);
+	for z in 0..chunk_size {
+		for x in 0..chunk_size {
+			// only tiles along the edges have neighbors in other chunks
+			if x > 0 && x < last && z > 0 && z < last {
+				continue;
+			}
+			let tile = (x + z * chunk_size) as usize;
+			let c_tile = tile * 7 + 1;
+			let coord = HexCoord::from_offset(origin.x + x as i32, origin.y + z as i32);
+			if !settings.shows(coord) {
+				continue;
+			}
+			for dir in HexDirection::ALL {
+				let neighbor = coord.neighbor(dir);
+				let n = neighbor.to_offset();
+				// each wall between two chunks belongs to the one that comes
+				// first row by row, which may be across a wrapped edge
+				let n_index = neighbor.chunk(chunk_size);
+				let later = n_index.y > index.y || (n_index.y == index.y && n_index.x > index.x);
+				if !later || !settings.shows(settings.wrap_coord(neighbor)) {
+					continue;
+				}
+				// the tile's own edge, lowered to the height of the neighbor
+				let height = sample_height(n.x, n.y, noise, settings);
+				let i = dir.index();
+				let (a, b) = (verts[c_tile + i], verts[c_tile + (i + 1) % 6]);
+				let quad = [
+					a,
+					b,
+					Vec3::new(b.x, height, b.z),
+					Vec3::new(a.x, height, a.z),
+				];
+				let cell = match verts[c_tile - 1].y >= height || settings.atlas.is_none() {
+					true => cells[tile],
+					false => {
+						let neighbors = biome::biome_neighbors(n.x, n.y, noise, settings);
+						settings.tile_rect(n.x, n.y, height, &neighbors)
+					}
+				};
+				create_quad(quad, cell, settings, verts, uvs, normals, indices);
+			}
+		}
+	}
+}
+
+/// Walls `depth` straight down from every edge of a tile top that faces
+/// another chunk or off the map, including outside the map shape.
+fn add_chunk_skirt(
+	c_x: u32,
+	c_z: u32,
+	depth: f32,
+	lattice: &TileLattice,
+	cells: &[Rect],
+	verts: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+	normals: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	settings: &HexGridSettings,
+) {
+	// no tile of an empty map faces off it
+	if settings.is_empty() {
+		return;
+	}
+	let chunk_size = settings.chunk_size;
+	let origin = (UVec2::new(c_x, c_z) * chunk_size).as_ivec2();
+	let index = IVec2::new(c_x as i32, c_z as i32);
+	let last = chunk_size.saturating_sub(1);
+	for z in 0..chunk_size {
+		for x in 0..chunk_size {
+			// inner tiles only border the chunk's own, unless the map shape
+			// cuts through it
+			let inner = x > 0 && x < last && z > 0 && z < last;
+			if inner && settings.shape == MapShape::Rectangle {
+				continue;
+			}
+			let cell = cells[(x + z * chunk_size) as usize];
+			let tile = (x + z * chunk_size) as usize * lattice.stride();
+			let coord = HexCoord::from_offset(origin.x + x as i32, origin.y + z as i32);
+			if !settings.shows(coord) {
+				continue;
+			}
+			for dir in HexDirection::ALL {
+				let neighbor = coord.neighbor(dir);
+				let own = neighbor.chunk(chunk_size) == index;
+				if own && settings.contains(neighbor) {
+					continue;
+				}
+				for side in lattice.edges[dir.index()].windows(2) {
+					let (a, b) = (
+						verts[tile + side[0] as usize],
+						verts[tile + side[1] as usize],
+					);
+					let quad = [a, b, b - Vec3::Y * depth, a - Vec3::Y * depth];
+					create_quad(quad, cell, settings, verts, uvs, normals, indices);
+				}
+			}
+		}
+	}
+}
+
+/// Walls from the edges of every tile top down to the lowest height the map
+/// can reach.
+fn add_tile_skirts(
+	present: &[bool],
+	cells: &[Rect],
+	lattice: &TileLattice,
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+	settings: &HexGridSettings,
+) {
+	let (low, _) = height::height_bounds(settings);
+	for tile in (0..present.len()).filter(|&tile| present[tile]) {
+		let cell = cells[tile];
+		let tile = tile * lattice.stride();
+		for side in lattice.edges.iter().flat_map(|edge| edge.windows(2)) {
+			let (a, b) = (
+				verts[tile + side[0] as usize],
+				verts[tile + side[1] as usize],
+			);
+			let quad = [a, b, Vec3::new(b.x, low, b.z), Vec3::new(a.x, low, a.z)];
+			create_quad(quad, cell, settings, verts, uvs, normals, indices);
+		}
+	}
+}
+
+fn add_tile_sides(
+	x: u32,
+	z: u32,
+	idx: u32,
+	origin: IVec2,
+	cells: &[Rect],
+	settings: &HexGridSettings,
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+) {
+	let chunk_size = settings.chunk_size as i32;
+	let c_tile = idx + 1;
+	// staggering follows the global row, not the row within the chunk
+	let coord = HexCoord::from_offset(origin.x + x as i32, origin.y + z as i32);
+	// tiles outside the map shape or hidden have nothing to wall off
+	if !settings.shows(coord) {
+		return;
+	}
+	for dir in HexDirection::ALL {
+		let local = coord.neighbor(dir).to_offset() - origin;
+		if local.cmplt(IVec2::ZERO).any() || local.cmpge(IVec2::splat(chunk_size)).any() {
+			continue;
+		}
+		if !settings.shows(coord.neighbor(dir)) {
+			continue;
+		}
+		// of each pair of neighbors only the first one builds their wall
+		let n_idx = (local.x + local.y * chunk_size) as u32 * 7;
+		if n_idx < idx {
+			continue;
+		}
+		// corner i of the tile meets corner i + 4 of the neighbor across edge i
+		let n_tile = n_idx + 1;
+		// both tiles lie in the block of tile tops, ahead of any wall
+		debug_assert!(n_tile + 6 <= (chunk_size * chunk_size * 7) as u32);
+		let i = dir.index() as u32;
+		let quad = [
+			c_tile + i,
+			c_tile + (i + 1) % 6,
+			n_tile + (i + 3) % 6,
+			n_tile + (i + 4) % 6,
+		]
+		.map(|v| verts[v as usize]);
+		let upper = match verts[idx as usize].y >= verts[n_idx as usize].y {
+			true => idx,
+			false => n_idx,
+		};
+		let cell = cells[upper as usize / 7];
+		create_quad(quad, cell, settings, verts, uvs, normals, indices);
+	}
+}
+
+/// Height difference below which a wall has nothing to show.
+const WALL_EPSILON: f32 = 1e-4;
+
+/// Adds a wall between the edge `v1`-`v2` of one tile and the matching edge
+/// `v4`-`v3` of its neighbor. Walls get their own vertices so they can be
+/// textured independently of the tile tops, using `cell`, the atlas cell of
+/// the upper tile.
+///
+/// With the tile's corners running around its top like in `create_tile`,
+/// both triangles wind so they face away from whichever side is higher, down
+/// onto the lower tile, whichever of the two tiles that is.
+fn create_quad(
+	quad: [Vec3; 4],
+	cell: Rect,
+	settings: &HexGridSettings,
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+) {
+	let [v1, v2, v3, v4] = quad;
+	// each triangle collapses where its side of the wall has no height to
+	// span, so only walls between equal heights vanish entirely
+	let first = (v2.y - v3.y).abs() > WALL_EPSILON;
+	let second = (v1.y - v4.y).abs() > WALL_EPSILON;
+	if !first && !second {
+		return;
+	}
+	let idx = verts.len() as u32;
+	// v runs from 0 along the upper edge of the wall to 1 along the lower one
+	let (top, bottom) = if v1.y + v2.y > v3.y + v4.y {
+		(0., 1.)
+	} else {
+		(1., 0.)
+	};
+	let normal = match first {
+		true => (v3 - v1).cross(v2 - v1),
+		false => (v4 - v1).cross(v3 - v1),
+	};
+	let cell = match (&settings.atlas, settings.grid_lines) {
+		// walls sample the inside of the grid lines' hexagon
+		(None, Some(_)) => Rect::from_center_size(Vec2::splat(0.5), Vec2::ZERO),
+		_ => cell,
+	};
+	verts.extend(quad);
+	uvs.extend(
+		[
+			Vec2::new(0., top),
+			Vec2::new(1., top),
+			Vec2::new(1., bottom),
+			Vec2::new(0., bottom),
+		]
+		.map(|uv| cell.min + uv * cell.size()),
+	);
+	normals.extend([normal.normalize_or_zero(); 4]);
+
+	if first {
+		indices.push(idx);
+		indices.push(idx + 2);
+		indices.push(idx + 1);
+	}
+	if second {
+		indices.push(idx);
+		indices.push(idx + 3);
+		indices.push(idx + 2);
+	}
+}
+
+fn create_tile(
+	pos: Vec3,
+	cell: Rect,
+	corners: &[Vec3; 6],
+	settings: &HexGridSettings,
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+) {
+	let idx = verts.len() as u32;
+	let center = Vec3::new(pos.x, 0., pos.z);
+	normals.push(Vec3::Y);
+	// every tile maps onto the same hexagon inscribed in its atlas cell
+	uvs.push(cell.center());
+	verts.push(pos);
+	for (i, corner) in corners.iter().enumerate() {
+		let p = pos + *corner;
+		verts.push(p);
+		let uv = Vec2::splat(0.5) + corner.xz() / (2. * settings.outer_radius);
+		uvs.push(cell.min + uv * cell.size());
+		normals.push((p - center).normalize());
+		// pointy-top corners run from +z towards +x, so (center, i, i + 1)
+		// has its face normal along +Y. Flat-top corners are mirrored and the
+		// winding is flipped back in `finish_mesh`.
+		indices.push(idx);
+		indices.push(idx + 1 + i as u32);
+		indices.push(idx + 1 + ((i as u32 + 1) % 6));
+	}
+}
+
+fn uv_debug_texture() -> Image {
+	const TEXTURE_SIZE: usize = 8;
+
+	let mut palette: [u8; 32] = [
+		255, 102, 159, 255, 255, 159, 102, 255, 236, 255, 102, 255, 121, 255, 102, 255, 102, 255,
+		198, 255, 102, 198, 255, 255, 121, 102, 255, 255, 236, 102, 255, 255,
+	];
+
+	let mut texture_data = [0; TEXTURE_SIZE * TEXTURE_SIZE * 4];
+	for y in 0..TEXTURE_SIZE {
+		let offset = TEXTURE_SIZE * y * 4;
+		texture_data[offset..(offset + TEXTURE_SIZE * 4)].copy_from_slice(&palette);
+		palette.rotate_right(4);
+	}
+
+	return Image::new_fill(
+		Extent3d {
+			width: TEXTURE_SIZE as u32,
+			height: TEXTURE_SIZE as u32,
+			depth_or_array_layers: 1,
+		},
 		TextureDimension::D2,
 		&texture_data,
 		TextureFormat::Rgba8UnormSrgb,
 		RenderAssetUsages::RENDER_WORLD,
-	)
+	);
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{ecs::system::RunSystemOnce, render::mesh::VertexAttributeValues, utils::HashSet};
+
+	use super::*;
+
+	/// A small map shaded smoothly, which keeps every vertex where
+	/// `create_chunk` put it, seven per tile top ahead of any wall.
+	fn small_map(map_size: UVec2) -> HexGridSettings {
+		return HexGridSettings {
+			map_size,
+			chunk_size: 4,
+			height_amplitude: 4.,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+	}
+
+	/// Vertices of a chunk mesh in world space.
+	fn world_vertices(index: UVec2, settings: &HexGridSettings) -> Vec<Vec3> {
+		let origin = to_hex_pos(
+			Vec3::new(index.x as f32, 0., index.y as f32) * settings.chunk_size as f32,
+			settings,
+		);
+		let noise = settings.noise();
+		let mesh = create_chunk(index.x, index.y, 0, &noise, settings);
+		return mesh
+			.attribute(Mesh::ATTRIBUTE_POSITION)
+			.and_then(|positions| positions.as_float3())
+			.unwrap()
+			.iter()
+			.map(|p| origin + Vec3::from(*p))
+			.collect();
+	}
+
+	/// Center and corners of the top of tile (`x`, `z`) within a chunk.
+	fn tile_top(vertices: &[Vec3], x: u32, z: u32, settings: &HexGridSettings) -> [Vec3; 7] {
+		let first = ((x + z * settings.chunk_size) * 7) as usize;
+		return std::array::from_fn(|i| vertices[first + i]);
+	}
+
+	fn close(a: Vec3, b: Vec3) -> bool {
+		return a.distance(b) < 1e-4;
+	}
+
+	/// Checks that chunk `first` walls its edge tiles up or down to those of
+	/// chunk `next`, ending on their corners wherever the two touch, and
+	/// returns how many corners of `next` do.
+	fn seam_corners(first: UVec2, next: UVec2, settings: &HexGridSettings) -> usize {
+		let (first, next) = (
+			world_vertices(first, settings),
+			world_vertices(next, settings),
+		);
+		let tops = |vertices: &[Vec3]| {
+			let size = settings.chunk_size;
+			return (0..size * size)
+				.flat_map(|i| tile_top(vertices, i % size, i / size, settings)[1..].to_vec())
+				.collect::<Vec<_>>();
+		};
+		let first_tops = tops(&first);
+		let mut seam = 0;
+		for corner in tops(&next) {
+			if first_tops
+				.iter()
+				.any(|c| c.xz().distance(corner.xz()) < 1e-4)
+			{
+				assert!(first.iter().any(|v| close(*v, corner)), "{corner}");
+				seam += 1;
+			}
+		}
+		return seam;
+	}
+
+	#[test]
+	fn seams_meet_the_next_chunk() {
+		for map_size in [UVec2::new(2, 1), UVec2::new(1, 2)] {
+			let settings = small_map(map_size);
+			let seam = seam_corners(UVec2::ZERO, map_size - 1, &settings);
+			// each tile along the seam shares two or three of its corners
+			assert!(seam >= 2 * settings.chunk_size as usize, "{seam}");
+		}
+	}
+
+	#[test]
+	fn chunk_sides_sample_the_next_chunk_heights() {
+		let settings = HexGridSettings {
+			seed: 7,
+			..small_map(UVec2::new(2, 1))
+		};
+		let first = world_vertices(UVec2::ZERO, &settings);
+		let next = world_vertices(UVec2::X, &settings);
+		let noise = settings.noise();
+		let size = settings.chunk_size;
+		for z in 0..size {
+			let height = sample_height(size as i32, z as i32, &noise, &settings);
+			assert!((tile_top(&next, 0, z, &settings)[0].y - height).abs() < 1e-5);
+			// the east edge of the last tile of the row, lowered or raised to
+			// the first tile of the same row in the next chunk
+			let edge = tile_top(&first, size - 1, z, &settings);
+			for corner in [edge[2], edge[3]] {
+				let bottom = Vec3::new(corner.x, height, corner.z);
+				let touching = (corner.y - height).abs() < 1e-4;
+				assert!(
+					touching || first.iter().any(|v| close(*v, bottom)),
+					"{bottom}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn height_at_matches_the_tile_centers() {
+		let settings = small_map(UVec2::new(2, 2));
+		let size = settings.chunk_size;
+		for index in [UVec2::ZERO, UVec2::ONE] {
+			let vertices = world_vertices(index, &settings);
+			for (x, z) in (0..size).flat_map(|z| (0..size).map(move |x| (x, z))) {
+				let offset = (index * size + UVec2::new(x, z)).as_ivec2();
+				let coord = HexCoord::from_offset(offset.x, offset.y);
+				let center = tile_top(&vertices, x, z, &settings)[0];
+				assert!((HexGrid::height_at(coord, &settings) - center.y).abs() < 1e-5);
+			}
+		}
+	}
+
+	#[test]
+	fn hex_corners_are_evenly_spaced() {
+		for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+			let settings = HexGridSettings {
+				outer_radius: 1.5,
+				orientation,
+				..default()
+			};
+			let corners = settings.hex_corners();
+			for (i, corner) in corners.iter().enumerate() {
+				assert_eq!(corner.y, 0.);
+				assert!((corner.length() - settings.outer_radius).abs() < 1e-5);
+				let next = corners[(i + 1) % 6];
+				let angle = corner.angle_between(next).to_degrees();
+				assert!((angle - 60.).abs() < 1e-3, "{angle}");
+			}
+			// the first corner points along z, or along x for flat tops
+			let first = match orientation {
+				HexOrientation::PointyTop => Vec3::Z,
+				HexOrientation::FlatTop => Vec3::X,
+			};
+			assert!(close(corners[0], first * settings.outer_radius));
+		}
+	}
+
+	#[test]
+	fn heights_scale_with_amplitude() {
+		let range = |vertices: &[Vec3]| {
+			let low = vertices.iter().map(|v| v.y).fold(f32::INFINITY, f32::min);
+			let high = vertices
+				.iter()
+				.map(|v| v.y)
+				.fold(f32::NEG_INFINITY, f32::max);
+			return high - low;
+		};
+		let base = world_vertices(UVec2::ZERO, &small_map(UVec2::ONE));
+		assert!(range(&base) > 0.);
+		for amplitude in [0.5, 2., 10.] {
+			let settings = HexGridSettings {
+				height_amplitude: 4. * amplitude,
+				..small_map(UVec2::ONE)
+			};
+			let scaled = world_vertices(UVec2::ZERO, &settings);
+			assert_eq!(scaled.len(), base.len());
+			for (a, b) in base.iter().zip(&scaled) {
+				assert!((a.y * amplitude - b.y).abs() < 1e-3 * amplitude, "{a} {b}");
+				assert_eq!(a.xz(), b.xz());
+			}
+			assert!((range(&base) * amplitude - range(&scaled)).abs() < 1e-3 * amplitude);
+		}
+	}
+
+	#[test]
+	fn tile_tops_wind_upwards() {
+		for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+			let settings = HexGridSettings {
+				orientation,
+				..small_map(UVec2::ONE)
+			};
+			let noise = settings.noise();
+			let mesh = create_chunk(0, 0, 0, &noise, &settings);
+			let positions = mesh
+				.attribute(Mesh::ATTRIBUTE_POSITION)
+				.and_then(|positions| positions.as_float3())
+				.unwrap();
+			let indices = mesh.indices().unwrap().iter().collect::<Vec<_>>();
+			// the fans of every tile top come ahead of the walls
+			let tops = 6 * 3 * (settings.chunk_size * settings.chunk_size) as usize;
+			for tri in indices[..tops].chunks_exact(3) {
+				let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vec3::from(positions[i]));
+				let face = (b - a).cross(c - a);
+				// a sixth of the hexagon's area, doubled
+				let area = settings.outer_radius * settings.inner_radius();
+				assert!(face.y > 0.99 * area && face.y < 1.01 * area, "{face}");
+			}
+		}
+	}
+
+	/// The small map with each of the options that add or move vertices.
+	fn variants() -> Vec<HexGridSettings> {
+		let base = small_map(UVec2::new(2, 2));
+		let atlas = AtlasConfig {
+			texture: Handle::default(),
+			columns: 3,
+			rows: 2,
+		};
+		return vec![
+			base.clone(),
+			HexGridSettings {
+				orientation: HexOrientation::FlatTop,
+				..base.clone()
+			},
+			HexGridSettings {
+				atlas: Some(atlas),
+				..base
+			},
+		];
+	}
+
+	#[test]
+	fn uvs_stay_within_the_texture() {
+		for settings in variants() {
+			for index in [UVec2::ZERO, UVec2::ONE] {
+				let noise = settings.noise();
+				let mesh = create_chunk(index.x, index.y, 0, &noise, &settings);
+				let Some(VertexAttributeValues::Float32x2(uvs)) =
+					mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+				else {
+					panic!("chunk meshes have UVs");
+				};
+				assert_eq!(uvs.len(), mesh.count_vertices());
+				for uv in uvs {
+					assert!(uv.iter().all(|c| (0. ..=1.).contains(c)), "{uv:?}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn tile_uvs_stay_within_their_atlas_cell() {
+		let mut settings = variants()
+			.into_iter()
+			.find(|settings| settings.atlas.is_some())
+			.unwrap();
+		// heights rising through several bands
+		settings.noise_scale = 4.;
+		settings.height_amplitude = 0.2;
+		let noise = settings.noise();
+		let mesh = create_chunk(1, 1, 0, &noise, &settings);
+		let positions = mesh
+			.attribute(Mesh::ATTRIBUTE_POSITION)
+			.and_then(|positions| positions.as_float3())
+			.unwrap();
+		let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+		else {
+			panic!("chunk meshes have UVs");
+		};
+		let mut cells = HashSet::new();
+		for tile in 0..(settings.chunk_size * settings.chunk_size) as usize {
+			let height = positions[tile * 7][1];
+			let cell = settings.atlas_rect(height);
+			let uvs = &uvs[tile * 7..tile * 7 + 7];
+			assert!(Vec2::from(uvs[0]).distance(cell.center()) < 1e-5);
+			for &uv in &uvs[1..] {
+				let uv = Vec2::from(uv);
+				assert!(cell.inflate(1e-5).contains(uv), "{uv} outside {cell:?}");
+				// the corners touch the sides of the cell the hexagon is
+				// inscribed in
+				assert!(uv.distance(cell.center()) > 0.4 * cell.size().min_element());
+			}
+			cells.insert(cell.min.to_array().map(f32::to_bits));
+		}
+		assert!(cells.len() > 1);
+	}
+
+	#[test]
+	fn seeds_reproduce_their_map() {
+		let seeded = |seed| {
+			let settings = HexGridSettings {
+				seed,
+				..small_map(UVec2::new(2, 2))
+			};
+			return [UVec2::ZERO, UVec2::ONE].map(|index| world_vertices(index, &settings));
+		};
+		let bytes = |vertices: &[Vec<Vec3>; 2]| {
+			return vertices
+				.iter()
+				.flatten()
+				.flat_map(|p| p.to_array().map(f32::to_le_bytes))
+				.flatten()
+				.collect::<Vec<_>>();
+		};
+		let (first, again, other) = (seeded(5), seeded(5), seeded(6));
+		assert_eq!(bytes(&first), bytes(&again));
+		assert_eq!(first, again);
+		assert_ne!(bytes(&first), bytes(&other));
+	}
+
+	#[test]
+	fn welding_shares_corners_and_stays_watertight() {
+		let unwelded = HexGridSettings {
+			chunk_size: 8,
+			..small_map(UVec2::ONE)
+		};
+		let welded = HexGridSettings {
+			weld_tolerance: Some(1e-3),
+			..unwelded.clone()
+		};
+		let noise = unwelded.noise();
+		let before = create_chunk(0, 0, 0, &noise, &unwelded);
+		let after = create_chunk(0, 0, 0, &noise, &welded);
+		let (before_count, after_count) = (before.count_vertices(), after.count_vertices());
+		// every tile top alone brings seven vertices before welding
+		assert!(before_count >= 7 * 64, "{before_count}");
+		assert!(
+			after_count * 2 < before_count,
+			"{before_count} vertices before welding, {after_count} after"
+		);
+
+		let indices = after.indices().unwrap().iter().collect::<Vec<_>>();
+		let mut edges = HashMap::<(usize, usize), u32>::new();
+		for tri in indices.chunks_exact(3) {
+			for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+				*edges.entry((a.min(b), a.max(b))).or_default() += 1;
+			}
+		}
+		for (edge, count) in edges {
+			assert!(count <= 2, "edge {edge:?} shared by {count} triangles");
+		}
+
+		// welded vertices take the chunk wide mapping instead of the UV
+		// square of whichever tile came first
+		let positions = after
+			.attribute(Mesh::ATTRIBUTE_POSITION)
+			.and_then(|positions| positions.as_float3())
+			.unwrap();
+		let Some(VertexAttributeValues::Float32x2(uvs)) = after.attribute(Mesh::ATTRIBUTE_UV_0)
+		else {
+			panic!("chunk meshes have UVs");
+		};
+		for (p, uv) in positions.iter().zip(uvs) {
+			assert_eq!(Vec2::from(*uv), chunk_uv(Vec3::from(*p), &welded));
+		}
+	}
+
+	#[test]
+	fn camera_frames_the_whole_map() {
+		let fov = PerspectiveProjection::default().fov;
+		for (map_size, chunk_size) in [
+			(UVec2::ONE, 4),
+			(UVec2::new(4, 2), 8),
+			(UVec2::splat(8), 32),
+		] {
+			let settings = HexGridSettings {
+				map_size,
+				chunk_size,
+				..default()
+			};
+			let (position, target) = settings.camera_framing();
+			let bounds = settings.map_bounds();
+			let ground = settings.sea_level.unwrap_or(0.);
+			assert!(close(target, bounds.center().extend(ground).xzy()));
+			assert!(position.y > target.y);
+			let forward = (target - position).normalize();
+			for corner in [
+				bounds.min,
+				bounds.max,
+				Vec2::new(bounds.min.x, bounds.max.y),
+				Vec2::new(bounds.max.x, bounds.min.y),
+			] {
+				let corner = corner.extend(ground).xzy();
+				let angle = forward.angle_between(corner - position);
+				assert!(angle <= fov * 0.5 + 1e-4, "{map_size} {corner}");
+			}
+		}
+	}
+
+	#[test]
+	fn camera_settings_override_the_framing() {
+		let mut settings = small_map(UVec2::new(2, 2));
+		settings.camera.position = Some(Vec3::new(-5., 10., -5.));
+		assert_eq!(settings.camera_framing().0, Vec3::new(-5., 10., -5.));
+		settings.camera.target = Some(Vec3::new(1., 0., 1.));
+		settings.camera.radius = Some(6.);
+		let (position, target) = settings.camera_framing();
+		assert_eq!(target, Vec3::new(1., 0., 1.));
+		assert!((position.distance(target) - 6.).abs() < 1e-4);
+		let direction = (Vec3::new(-5., 10., -5.) - target).normalize();
+		assert!(close((position - target).normalize(), direction));
+	}
+
+	/// The walls of a chunk, four vertices each after the tile tops.
+	fn walls(vertices: &[Vec3], settings: &HexGridSettings) -> Vec<[Vec3; 4]> {
+		let tops = (settings.chunk_size * settings.chunk_size * 7) as usize;
+		return vertices[tops..]
+			.chunks_exact(4)
+			.map(|quad| [quad[0], quad[1], quad[2], quad[3]])
+			.collect();
+	}
+
+	/// Walls of a chunk standing on the edge between corners `i` and `i + 1`
+	/// of any tile in `tiles`.
+	fn walls_on_edge(
+		index: UVec2,
+		tiles: impl Iterator<Item = (u32, u32)>,
+		i: usize,
+		settings: &HexGridSettings,
+	) -> usize {
+		let vertices = world_vertices(index, settings);
+		let edges = tiles
+			.map(|(x, z)| tile_top(&vertices, x, z, settings))
+			.map(|top| [top[1 + i].xz(), top[1 + (i + 1) % 6].xz()])
+			.collect::<Vec<_>>();
+		return walls(&vertices, settings)
+			.iter()
+			.filter(|quad| {
+				return edges.iter().any(|edge| {
+					let on = |v: &Vec3| edge.iter().any(|e| e.distance(v.xz()) < 1e-4);
+					return quad.iter().all(on);
+				});
+			})
+			.count();
+	}
+
+	#[test]
+	fn wide_maps_stitch_only_their_inner_seams() {
+		let settings = small_map(UVec2::new(3, 1));
+		let mut world = World::new();
+		world.insert_resource(settings.clone());
+		world.init_resource::<LoadedChunks>();
+		world.init_resource::<MapGeneration>();
+		AsyncComputeTaskPool::get_or_init(default);
+		world.run_system_once(
+			|mut commands: Commands,
+			 mut loaded: ResMut<LoadedChunks>,
+			 mut generation: ResMut<MapGeneration>,
+			 settings: Res<HexGridSettings>| {
+				spawn_map(&mut commands, &mut loaded, &mut generation, &settings);
+			},
+		);
+		let loaded = world.resource::<LoadedChunks>();
+		assert_eq!(loaded.len(), 3);
+		assert!((0..3).all(|x| loaded.contains_key(&IVec2::new(x, 0))));
+		assert_eq!(world.query::<&Chunk>().iter(&world).count(), 3);
+
+		let last = settings.chunk_size - 1;
+		let column = |x| (0..settings.chunk_size).map(move |z| (x, z));
+		// east: corners 1 and 2, west: corners 4 and 5
+		for x in 0..3 {
+			let index = UVec2::new(x, 0);
+			let east = walls_on_edge(index, column(last), 1, &settings);
+			let west = walls_on_edge(index, column(0), 4, &settings);
+			assert_eq!(east > 0, x < 2, "chunk {x} has {east} east walls");
+			assert_eq!(west, 0, "chunk {x} has {west} west walls");
+		}
+	}
+
+	#[test]
+	fn wrapped_edges_meet_the_far_side() {
+		for wrap in [WrapMode::Cylinder, WrapMode::Torus] {
+			let settings = HexGridSettings {
+				wrap,
+				..small_map(UVec2::new(3, 2))
+			};
+			let tiles = settings.map_tiles().as_ivec2();
+			for z in 0..tiles.y {
+				let (first, last) = (
+					HexCoord::from_offset(0, z),
+					HexCoord::from_offset(tiles.x - 1, z),
+				);
+				assert!(first.neighbors_on_map(&settings).contains(&last));
+				assert!(last.neighbors_on_map(&settings).contains(&first));
+				// looking past either edge finds the tile on the other side
+				let past = HexCoord::from_offset(-1, z);
+				assert_eq!(settings.wrap_coord(past), last);
+				assert_eq!(
+					HexGrid::height_at(past, &settings),
+					HexGrid::height_at(last, &settings)
+				);
+				let next = HexCoord::from_offset(tiles.x, z);
+				assert_eq!(
+					HexGrid::height_at(next, &settings),
+					HexGrid::height_at(first, &settings)
+				);
+			}
+			// the last chunk of each row walls its east edge to the first tiles
+			let size = settings.chunk_size;
+			let index = UVec2::new(2, 1);
+			let vertices = world_vertices(index, &settings);
+			for z in 0..size {
+				let first = HexCoord::from_offset(0, (size + z) as i32);
+				let height = HexGrid::height_at(first, &settings);
+				let edge = tile_top(&vertices, size - 1, z, &settings);
+				for corner in [edge[2], edge[3]] {
+					let bottom = Vec3::new(corner.x, height, corner.z);
+					let touching = (corner.y - height).abs() < 1e-4;
+					assert!(
+						touching || vertices.iter().any(|v| close(*v, bottom)),
+						"{bottom}"
+					);
+				}
+			}
+		}
+		let torus = HexGridSettings {
+			wrap: WrapMode::Torus,
+			..small_map(UVec2::new(3, 2))
+		};
+		let below = HexCoord::from_offset(5, -1);
+		assert_eq!(torus.wrap_coord(below), HexCoord::from_offset(5, 7));
+	}
+
+	#[test]
+	fn map_generation_reports_once() {
+		let mut world = World::new();
+		world.init_resource::<Events<MapGenerationComplete>>();
+		let chunk = world.spawn_empty().id();
+		let mut generation = MapGeneration::default();
+		generation.pending.insert(chunk);
+		world.insert_resource(generation);
+		let reported = |world: &World| world.resource::<Events<MapGenerationComplete>>().len();
+
+		world.run_system_once(report_map_generation);
+		assert_eq!(reported(&world), 0);
+		let mut generation = world.resource_mut::<MapGeneration>();
+		generation.pending.remove(&chunk);
+		for _ in 0..3 {
+			world.run_system_once(report_map_generation);
+		}
+		assert_eq!(reported(&world), 1);
+	}
+
+	#[test]
+	fn insets_open_seams_between_tiles() {
+		for inset in [0., 0.2] {
+			let settings = HexGridSettings {
+				tile_inset: inset,
+				..small_map(UVec2::ONE)
+			};
+			let vertices = world_vertices(UVec2::ZERO, &settings);
+			let (a, b) = (
+				tile_top(&vertices, 1, 1, &settings),
+				tile_top(&vertices, 2, 1, &settings),
+			);
+			for top in [a, b] {
+				for corner in &top[1..] {
+					let radius = corner.xz().distance(top[0].xz());
+					assert!((radius - settings.outer_radius * (1. - inset)).abs() < 1e-4);
+				}
+			}
+			// corners 1 and 2 of a tile meet corners 5 and 4 of its east
+			// neighbor, exactly without an inset
+			for (i, j) in [(2, 6), (3, 5)] {
+				let gap = a[i].xz().distance(b[j].xz());
+				let expected = 2. * inset * settings.inner_radius();
+				assert!((gap - expected).abs() < 1e-4, "{gap} not {expected}");
+			}
+		}
+	}
+
+	#[test]
+	fn every_chunk_boundary_agrees_on_its_heights() {
+		for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+			let settings = HexGridSettings {
+				orientation,
+				..small_map(UVec2::splat(3))
+			};
+			let size = settings.chunk_size;
+			for index in (0..9).map(|i| UVec2::new(i % 3, i / 3)) {
+				// every chunk builds its tiles from the same heights
+				let vertices = world_vertices(index, &settings);
+				for (x, z) in (0..size * size).map(|i| (i % size, i / size)) {
+					let offset = (index * size + UVec2::new(x, z)).as_ivec2();
+					let height =
+						HexGrid::height_at(HexCoord::from_offset(offset.x, offset.y), &settings);
+					assert_eq!(tile_top(&vertices, x, z, &settings)[0].y, height);
+				}
+				for next in [index + UVec2::X, index + UVec2::Y] {
+					if next.cmplt(UVec2::splat(3)).all() {
+						let seam = seam_corners(index, next, &settings);
+						assert!(seam >= 2 * size as usize, "{index} to {next}: {seam}");
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn flat_maps_have_no_walls() {
+		for map_size in [UVec2::new(2, 2), UVec2::new(3, 1)] {
+			let settings = HexGridSettings {
+				noise_type: NoiseType::Flat,
+				height_offset: 1.5,
+				..small_map(map_size)
+			};
+			let tiles = (settings.chunk_size * settings.chunk_size) as usize;
+			for index in [UVec2::ZERO, map_size - 1] {
+				let buffers = HexGrid::chunk_buffers(index, &settings);
+				assert_eq!(buffers.positions.len(), 7 * tiles);
+				assert_eq!(buffers.indices.len(), 18 * tiles);
+				assert!(buffers.positions.iter().all(|p| p[1] == 1.5));
+			}
+		}
+	}
+
+	#[test]
+	fn walls_stand_only_between_different_heights() {
+		// wide level terraces, a step down along x = 4 and a block raised on
+		// top of the upper one
+		let mut settings = HexGridSettings {
+			chunk_size: 8,
+			..small_map(UVec2::ONE)
+		};
+		let height = |offset: IVec2| match offset {
+			IVec2 { x: 5..=6, y: 2..=4 } => 7.,
+			IVec2 { x: 4.., .. } => 5.,
+			_ => 2.,
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = height(IVec2::new(i as i32 % 8, i as i32 / 8));
+		}
+		settings.map = Some(Arc::new(map));
+
+		let mut edges = 0;
+		for offset in (0..64).map(|i| IVec2::new(i % 8, i / 8)) {
+			let coord = HexCoord::from_offset(offset.x, offset.y);
+			for neighbor in coord.neighbors() {
+				let n = neighbor.to_offset();
+				// each pair once, both on the chunk
+				let inside = n.cmpge(IVec2::ZERO).all() && n.cmplt(IVec2::splat(8)).all();
+				if inside && (n.y, n.x) > (offset.y, offset.x) && height(n) != height(offset) {
+					edges += 1;
+				}
+			}
+		}
+		let vertices = world_vertices(UVec2::ZERO, &settings);
+		let walls = walls(&vertices, &settings);
+		assert_eq!(walls.len(), edges);
+		assert!(edges > 8);
+		for quad in walls {
+			let heights = quad.map(|v| v.y);
+			assert!(heights.iter().any(|&h| h != heights[0]));
+		}
+	}
+
+	#[test]
+	fn every_chunk_size_keeps_its_indices_in_bounds() {
+		for chunk_size in [1, 2, 3, 16] {
+			// odd sizes start every other chunk on an odd row
+			let settings = HexGridSettings {
+				chunk_size,
+				seed: 11,
+				height_amplitude: 6.,
+				..small_map(UVec2::new(2, 3))
+			};
+			let noise = settings.noise();
+			let tiles = (chunk_size * chunk_size) as usize;
+			for index in (0..6).map(|i| UVec2::new(i % 2, i / 2)) {
+				for lod in 0..3 {
+					let mesh = create_chunk(index.x, index.y, lod, &noise, &settings);
+					let buffers = ChunkBuffers::from_mesh(&mesh);
+					let len = buffers.positions.len();
+					assert!(len > 0, "{chunk_size} {index} {lod}");
+					assert!(
+						buffers.indices.iter().all(|&i| (i as usize) < len),
+						"{chunk_size} {index} {lod}"
+					);
+				}
+			}
+			// the walls of the first chunk hang down to the heights of both of
+			// its later neighbors
+			let first = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+			assert!(first.positions.len() > 7 * tiles, "{chunk_size}");
+		}
+	}
+
+	#[test]
+	fn material_swaps_reach_every_chunk() {
+		let mut world = World::new();
+		let mut materials = Assets::<StandardMaterial>::default();
+		let generated = materials.add(StandardMaterial::default());
+		let swapped = materials.add(StandardMaterial::from(Color::BLACK));
+		world.insert_resource(ChunkMaterial {
+			current: generated.clone(),
+			generated: generated.clone(),
+		});
+		world.init_resource::<Events<SetChunkMaterial>>();
+		let chunks = (0..4)
+			.map(|x| {
+				let index = UVec2::new(x, 0);
+				return world
+					.spawn((Chunk { index, lod: 0 }, generated.clone()))
+					.id();
+			})
+			.collect::<Vec<_>>();
+		// nothing to swap until asked
+		world.run_system_once(swap_chunk_material);
+		assert_eq!(
+			world.get::<Handle<StandardMaterial>>(chunks[0]),
+			Some(&generated)
+		);
+
+		world.send_event(SetChunkMaterial(swapped.clone()));
+		world.run_system_once(swap_chunk_material);
+		for chunk in chunks {
+			assert_eq!(world.get::<Handle<StandardMaterial>>(chunk), Some(&swapped));
+		}
+		let material = world.resource::<ChunkMaterial>();
+		assert_eq!(**material, swapped);
+		assert_eq!(material.generated(), &generated);
+	}
+
+	#[test]
+	fn textures_end_up_on_the_material() {
+		let mut images = Assets::<Image>::default();
+		let [normal_map, metallic_roughness, occlusion] =
+			[0, 1, 2].map(|_| images.add(Image::default()));
+		let settings = HexGridSettings {
+			textures: MaterialTextures {
+				normal_map: Some(normal_map.clone()),
+				metallic_roughness: Some(metallic_roughness.clone()),
+				occlusion: Some(occlusion.clone()),
+			},
+			..small_map(UVec2::ONE)
+		};
+		let mut material = StandardMaterial::default();
+		apply_textures(&mut material, &mut images, &settings);
+		assert_eq!(material.normal_map_texture, Some(normal_map));
+		assert_eq!(
+			material.metallic_roughness_texture,
+			Some(metallic_roughness)
+		);
+		assert_eq!(material.occlusion_texture, Some(occlusion));
+		// without an atlas the tiles show the UV debug texture
+		let debug = material.base_color_texture.clone().unwrap();
+		assert_eq!(images.get(&debug).unwrap().data, uv_debug_texture().data);
+		let texture = images.add(Image::default());
+		let atlas = HexGridSettings {
+			atlas: Some(AtlasConfig {
+				texture: texture.clone(),
+				columns: 3,
+				rows: 2,
+			}),
+			..settings.clone()
+		};
+		apply_textures(&mut material, &mut images, &atlas);
+		assert_eq!(material.base_color_texture, Some(texture));
+		// normal maps need tangents to shade with
+		let mesh = HexGrid::chunk_mesh(UVec2::ZERO, &settings);
+		assert!(mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_some());
+		let plain = HexGrid::chunk_mesh(UVec2::ZERO, &small_map(UVec2::ONE));
+		assert!(plain.attribute(Mesh::ATTRIBUTE_TANGENT).is_none());
+	}
+
+	#[test]
+	fn full_chunks_keep_their_indices_in_bounds() {
+		// chunks with a wall on nearly every edge, built with the debug
+		// assertions on the wall and mesh indices
+		for chunk_size in [3, 16, 32] {
+			let mut settings = HexGridSettings {
+				chunk_size,
+				height_amplitude: 6.,
+				..small_map(UVec2::ONE)
+			};
+			let size = chunk_size as i32;
+			let height = |offset: IVec2| ((offset.x + 2 * offset.y) % 3) as f32 * 2.;
+			let mut map = HexMap::from_settings(&settings);
+			for (i, tile) in map.tiles.iter_mut().enumerate() {
+				tile.height = height(IVec2::new(i as i32 % size, i as i32 / size));
+			}
+			settings.map = Some(Arc::new(map));
+
+			// one four cornered wall for each pair of tiles of unequal height
+			let mut walls = 0;
+			for offset in (0..size * size).map(|i| IVec2::new(i % size, i / size)) {
+				for neighbor in HexCoord::from_offset(offset.x, offset.y).neighbors() {
+					let n = neighbor.to_offset();
+					let inside = n.cmpge(IVec2::ZERO).all() && n.cmplt(IVec2::splat(size)).all();
+					if inside && (n.y, n.x) > (offset.y, offset.x) && height(n) != height(offset) {
+						walls += 1;
+					}
+				}
+			}
+			let tiles = (size * size) as usize;
+			let buffers = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+			assert_eq!(
+				buffers.positions.len(),
+				7 * tiles + 4 * walls,
+				"{chunk_size}"
+			);
+			assert!(walls > tiles, "{chunk_size}");
+			assert!(buffers
+				.indices
+				.iter()
+				.all(|&i| (i as usize) < buffers.positions.len()));
+		}
+	}
+
+	#[test]
+	fn cliff_walls_face_the_lower_tile() {
+		// a single tile raised above the others, then sunk below them
+		for (height, outwards) in [(7., 1.), (-3., -1.)] {
+			let mut settings = small_map(UVec2::ONE);
+			let mut map = HexMap::from_settings(&settings);
+			for (i, tile) in map.tiles.iter_mut().enumerate() {
+				tile.height = if i == 2 * 4 + 1 { height } else { 2. };
+			}
+			settings.map = Some(Arc::new(map));
+
+			let center = HexCoord::from_offset(1, 2).to_world(&settings);
+			let buffers = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+			let walls = &buffers.indices[18 * 16..];
+			assert_eq!(walls.len(), 6 * 6);
+			for tri in walls.chunks_exact(3) {
+				let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(buffers.positions[tri[i] as usize]));
+				let normal = (b - a).cross(c - a).normalize();
+				let away = ((a + b + c) / 3. - center).xz().normalize();
+				assert!(normal.xz().dot(away) * outwards > 0.9);
+			}
+		}
+	}
+
+	#[test]
+	fn skirts_hang_from_the_chunk_edges() {
+		let plain = small_map(UVec2::new(2, 2));
+		let skirted = HexGridSettings {
+			chunk_skirt: Some(2.5),
+			..plain.clone()
+		};
+		for index in [UVec2::ZERO, UVec2::X, UVec2::ONE] {
+			let without = world_vertices(index, &plain);
+			let vertices = world_vertices(index, &skirted);
+			assert_eq!(vertices[..without.len()], without[..]);
+
+			// one wall per tile edge facing another chunk or off the map
+			let chunk = index.as_ivec2();
+			let mut edges = Vec::new();
+			for (x, z) in (0..16).map(|i| (i % 4, i / 4)) {
+				let top = tile_top(&vertices, x, z, &skirted);
+				let offset = chunk * 4 + IVec2::new(x as i32, z as i32);
+				let coord = HexCoord::from_offset(offset.x, offset.y);
+				for dir in HexDirection::ALL {
+					let n = coord.neighbor(dir).to_offset();
+					if n.div_euclid(IVec2::splat(4)) != chunk {
+						let i = dir.index();
+						edges.push((top[1 + i], top[1 + (i + 1) % 6]));
+					}
+				}
+			}
+			let skirt = vertices[without.len()..]
+				.chunks_exact(4)
+				.collect::<Vec<_>>();
+			assert_eq!(skirt.len(), edges.len());
+			for quad in skirt {
+				assert!(edges
+					.iter()
+					.any(|&(a, b)| close(quad[0], a) && close(quad[1], b)));
+				assert!(close(quad[2], quad[1] - Vec3::Y * 2.5));
+				assert!(close(quad[3], quad[0] - Vec3::Y * 2.5));
+			}
+		}
+	}
+
+	#[test]
+	fn centered_maps_surround_their_origin() {
+		for (map_size, chunk_size) in [
+			(UVec2::new(2, 2), 4),
+			(UVec2::new(3, 1), 5),
+			(UVec2::ONE, 3),
+		] {
+			for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+				for origin in [Vec3::ZERO, Vec3::new(12., 3., -7.)] {
+					let settings = HexGridSettings {
+						map_size,
+						chunk_size,
+						orientation,
+						origin,
+						center_origin: true,
+						..default()
+					};
+					let tiles = settings.map_tiles().as_ivec2();
+					let centroid = (0..tiles.x * tiles.y)
+						.map(|i| {
+							HexCoord::from_offset(i % tiles.x, i / tiles.x).to_world(&settings)
+						})
+						.sum::<Vec3>() / (tiles.x * tiles.y) as f32;
+					assert!(
+						(centroid - origin).length() < 1e-3,
+						"{centroid} off {origin}"
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn tiny_maps_build() {
+		for size in 0..=2 {
+			for chunk_size in [0, 1, 2, 3, 16] {
+				let settings = HexGridSettings {
+					map_size: UVec2::splat(size),
+					chunk_size,
+					chunk_skirt: Some(1.),
+					..small_map(UVec2::ONE)
+				};
+				let tiles = (size * chunk_size).pow(2) as usize;
+				assert_eq!(settings.is_empty(), tiles == 0);
+				let buffers = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+				let top = (chunk_size * chunk_size * 7) as usize;
+				assert!(buffers.positions.len() >= top);
+				let len = buffers.positions.len();
+				assert!(buffers.indices.iter().all(|&i| (i as usize) < len));
+				if tiles > 0 {
+					// a lone chunk hangs a skirt from every outer edge
+					assert!(buffers.positions.len() > top);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn faceted_triangles_share_one_normal() {
+		for smooth in variants() {
+			let flat = HexGridSettings {
+				normal_mode: NormalMode::Flat,
+				..smooth.clone()
+			};
+			for index in [UVec2::ZERO, UVec2::ONE] {
+				let triangles = HexGrid::chunk_buffers(index, &smooth).indices.len() / 3;
+				assert!(HexGrid::chunk_mesh(index, &flat).indices().is_none());
+				let buffers = HexGrid::chunk_buffers(index, &flat);
+				assert_eq!(buffers.positions.len(), 3 * triangles);
+				for (tri, normals) in buffers
+					.positions
+					.chunks_exact(3)
+					.zip(buffers.normals.chunks_exact(3))
+				{
+					assert!(normals[1] == normals[0] && normals[2] == normals[0]);
+					let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(tri[i]));
+					let face = (b - a).cross(c - a).normalize_or_zero();
+					if face != Vec3::ZERO {
+						assert!(face.dot(Vec3::from(normals[0])) > 0.999);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn camera_focus_stays_over_the_map() {
+		let settings = HexGridSettings {
+			camera: CameraSettings {
+				focus_margin: Some(2.),
+				..default()
+			},
+			..small_map(UVec2::new(3, 2))
+		};
+		let bounds = settings.map_bounds();
+		let (min, max) = (bounds.min - 2., bounds.max + 2.);
+		let inside = bounds.center().extend(3.).xzy();
+		assert_eq!(settings.clamp_camera_focus(inside), inside);
+		for far in [
+			Vec3::new(-500., 1., 4.),
+			Vec3::new(300., -2., 900.),
+			Vec3::new(bounds.center().x, 0., -80.),
+		] {
+			let clamped = settings.clamp_camera_focus(far);
+			assert!(clamped.xz().cmpge(min).all() && clamped.xz().cmple(max).all());
+			assert_eq!(clamped.y, far.y);
+		}
+
+		let mut world = World::new();
+		world.insert_resource(settings.clone());
+		let camera = world
+			.spawn(PanOrbitCamera {
+				focus: Vec3::new(-500., 1., 4.),
+				target_focus: Vec3::new(300., 0., 900.),
+				..default()
+			})
+			.id();
+		world.run_system_once(bound_camera_focus);
+		let camera = world.get::<PanOrbitCamera>(camera).unwrap();
+		assert_eq!(camera.focus, Vec3::new(min.x, 1., 4.));
+		assert_eq!(camera.target_focus, Vec3::new(max.x, 0., max.y));
+		assert!(camera.force_update);
+
+		let unbounded = HexGridSettings {
+			camera: default(),
+			..settings
+		};
+		assert_eq!(
+			unbounded.clamp_camera_focus(Vec3::splat(-500.)),
+			Vec3::splat(-500.)
+		);
+	}
 }
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+/// A texture split into a grid of equally sized cells, one per biome.
+#[derive(Reflect, Clone, Debug)]
+pub struct AtlasConfig {
+	pub texture: Handle<Image>,
+	pub columns: u32,
+	pub rows: u32,
+}
+
+impl AtlasConfig {
+	/// UV bounds of `cell`, counted row by row from the top left. Indices past
+	/// the last cell use the last one.
+	pub fn cell_rect(&self, cell: u32) -> Rect {
+		let columns = self.columns.max(1);
+		let rows = self.rows.max(1);
+		let cell = cell.min(columns * rows - 1);
+		let size = Vec2::new(1. / columns as f32, 1. / rows as f32);
+		let min = UVec2::new(cell % columns, cell / columns).as_vec2() * size;
+		return Rect::from_corners(min, min + size);
+	}
+}
@@ -0,0 +1,715 @@
+use bevy::prelude::*;
+
+use super::{height::sample_height, HexCoord, HexGridSettings, TerrainNoise};
+
+/// A kind of terrain, colored uniformly, covering the tiles within its
+/// height and slope ranges.
+#[derive(Reflect, Clone, Debug)]
+pub struct Biome {
+	pub name: String,
+	/// Lowest height of the tiles of this biome.
+	pub min_height: f32,
+	/// Tiles from `min_height` up to below this height belong to this biome,
+	/// unless an earlier biome claims them.
+	pub max_height: f32,
+	/// Smallest height difference to a neighbor of the tiles of this biome.
+	pub min_slope: f32,
+	/// Largest height difference to a neighbor of the tiles of this biome.
+	pub max_slope: f32,
+	pub color: Color,
+	/// Cell of `HexGridSettings::atlas` textured onto tiles of this biome.
+	pub atlas_cell: u32,
+	/// Whether units can enter the tiles, the starting `TileData::passable`.
+	pub passable: bool,
+	/// Price of stepping onto a tile, at least 1, the starting
+	/// `TileData::move_cost`.
+	pub move_cost: f32,
+}
+
+impl Biome {
+	pub fn new(name: &str, max_height: f32, color: Color) -> Self {
+		return Self {
+			name: name.into(),
+			min_height: f32::NEG_INFINITY,
+			max_height,
+			min_slope: 0.,
+			max_slope: f32::INFINITY,
+			color,
+			atlas_cell: 0,
+			passable: true,
+			move_cost: 1.,
+		};
+	}
+
+	pub fn with_atlas_cell(mut self, cell: u32) -> Self {
+		self.atlas_cell = cell;
+		return self;
+	}
+
+	pub fn with_heights(mut self, min: f32, max: f32) -> Self {
+		self.min_height = min;
+		self.max_height = max;
+		return self;
+	}
+
+	pub fn with_slopes(mut self, min: f32, max: f32) -> Self {
+		self.min_slope = min;
+		self.max_slope = max;
+		return self;
+	}
+
+	pub fn with_movement(mut self, passable: bool, move_cost: f32) -> Self {
+		self.passable = passable;
+		self.move_cost = move_cost;
+		return self;
+	}
+
+	/// Whether tiles at `height` with neighbors up to `slope` above or below
+	/// belong to this biome.
+	pub fn matches(&self, height: f32, slope: f32) -> bool {
+		return height >= self.min_height
+			&& height < self.max_height
+			&& slope >= self.min_slope
+			&& slope <= self.max_slope;
+	}
+
+	fn narrows_slope(&self) -> bool {
+		return self.min_slope > 0. || self.max_slope < f32::INFINITY;
+	}
+
+	pub fn default_bands() -> Vec<Biome> {
+		return vec![
+			Biome::new("water", 3.2, Color::srgb(0.16, 0.33, 0.6))
+				.with_atlas_cell(0)
+				.with_movement(false, 1.),
+			Biome::new("sand", 3.6, Color::srgb(0.84, 0.78, 0.55)).with_atlas_cell(1),
+			Biome::new("grass", 8., Color::srgb(0.33, 0.56, 0.25)).with_atlas_cell(2),
+			Biome::new("rock", 18., Color::srgb(0.45, 0.42, 0.4))
+				.with_atlas_cell(3)
+				.with_movement(true, 2.),
+			Biome::new("snow", f32::INFINITY, Color::srgb(0.95, 0.96, 0.98))
+				.with_atlas_cell(4)
+				.with_movement(true, 3.),
+		];
+	}
+}
+
+/// The biomes tiles are classified into, tried in the order they were
+/// registered, so where their ranges overlap the earlier one wins.
+#[derive(Reflect, Clone, Debug, Default, Deref, DerefMut)]
+pub struct BiomeRegistry(Vec<Biome>);
+
+impl BiomeRegistry {
+	/// Adds `biome` after the registered ones, or in place of the one with
+	/// the same name, keeping its priority.
+	pub fn register(&mut self, biome: Biome) -> &mut Self {
+		match self.0.iter_mut().find(|b| b.name == biome.name) {
+			Some(existing) => *existing = biome,
+			None => self.0.push(biome),
+		}
+		return self;
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Biome> {
+		return self.0.iter().find(|biome| biome.name == name);
+	}
+
+	/// The first registered biome matching a tile at `height` with neighbors
+	/// up to `slope` above or below. Tiles matching none use the last.
+	pub fn classify(&self, height: f32, slope: f32) -> Option<&Biome> {
+		return self
+			.0
+			.iter()
+			.find(|biome| biome.matches(height, slope))
+			.or(self.0.last());
+	}
+
+	/// Color at `height` on level ground, fading into the neighboring biome
+	/// with a smoothstep over `width` around the nearest boundary between
+	/// two biomes, half way at the boundary itself.
+	pub fn blended_color(&self, height: f32, width: f32) -> Option<Color> {
+		let biome = self.classify(height, 0.)?;
+		let half = width * 0.5;
+		let changes = |p: f32| {
+			let (below, above) = (self.classify(p.next_down(), 0.), self.classify(p, 0.));
+			return !std::ptr::eq(below.unwrap(), above.unwrap());
+		};
+		let boundary = self
+			.0
+			.iter()
+			.flat_map(|biome| [biome.min_height, biome.max_height])
+			.filter(|p| p.is_finite() && (height - p).abs() < half && changes(*p))
+			.min_by(|a, b| (height - a).abs().total_cmp(&(height - b).abs()));
+		let Some(boundary) = boundary else {
+			return Some(biome.color);
+		};
+		let below = self
+			.classify(boundary.next_down(), 0.)?
+			.color
+			.to_srgba()
+			.to_f32_array();
+		let above = self.classify(boundary, 0.)?.color.to_srgba().to_f32_array();
+		let t = ((height - boundary) / width + 0.5).clamp(0., 1.);
+		let t = t * t * (3. - 2. * t);
+		let mix = |k: usize| below[k] + (above[k] - below[k]) * t;
+		return Some(Color::srgba(mix(0), mix(1), mix(2), mix(3)));
+	}
+
+	/// Whether any biome needs the slope of a tile to classify it.
+	pub(super) fn uses_slope(&self) -> bool {
+		return self.0.iter().any(Biome::narrows_slope);
+	}
+}
+
+impl From<Vec<Biome>> for BiomeRegistry {
+	fn from(biomes: Vec<Biome>) -> Self {
+		let mut registry = Self::default();
+		for biome in biomes {
+			registry.register(biome);
+		}
+		return registry;
+	}
+}
+
+/// Tiles rising or falling steeply to a neighbor use a cliff biome whatever
+/// their height.
+#[derive(Reflect, Clone, Debug)]
+pub struct Cliffs {
+	/// Height difference to a neighbor that makes a tile a cliff.
+	pub min_slope: f32,
+	/// Name of the biome cliffs use, one of `HexGridSettings::biomes`.
+	pub biome: String,
+}
+
+impl Default for Cliffs {
+	fn default() -> Self {
+		return Self {
+			min_slope: 2.,
+			biome: "rock".into(),
+		};
+	}
+}
+
+/// Land tiles next to water below `HexGridSettings::sea_level` use a shore
+/// biome whatever their height, so coastlines get a band of their own.
+#[derive(Reflect, Clone, Debug)]
+pub struct Coasts {
+	/// Name of the biome coasts use, one of `HexGridSettings::biomes`.
+	pub biome: String,
+	/// Biome of land tiles with water on every side, or the coast biome.
+	pub islet_biome: Option<String>,
+}
+
+impl Default for Coasts {
+	fn default() -> Self {
+		return Self {
+			biome: "sand".into(),
+			islet_biome: None,
+		};
+	}
+}
+
+/// Darkens tiles the steeper they rise or fall to a neighbor, through their
+/// vertex colors, so slopes read from above under any lighting.
+#[derive(Reflect, Clone, Debug)]
+pub struct SlopeShading {
+	/// Height difference to a neighbor at which tiles reach `darkest`.
+	pub max_slope: f32,
+	/// Brightness of tiles at `max_slope` and steeper, between 0 and 1.
+	pub darkest: f32,
+	/// Shades white in place of the biome color, leaving only the slope.
+	pub replace_biomes: bool,
+}
+
+impl Default for SlopeShading {
+	fn default() -> Self {
+		return Self {
+			max_slope: 2.,
+			darkest: 0.3,
+			replace_biomes: false,
+		};
+	}
+}
+
+impl SlopeShading {
+	/// Brightness of a tile with neighbors up to `slope` above or below, from
+	/// 1 on level ground down to `darkest`.
+	pub fn brightness(&self, slope: f32) -> f32 {
+		let steepness = (slope / self.max_slope.max(f32::EPSILON)).clamp(0., 1.);
+		return 1. - (1. - self.darkest.clamp(0., 1.)) * steepness;
+	}
+
+	/// `color` of a tile darkened by its `slope`.
+	pub fn shade(&self, color: Color, slope: f32) -> Color {
+		let light = self.brightness(slope);
+		let rgba = match self.replace_biomes {
+			true => Srgba::WHITE,
+			false => color.to_srgba(),
+		};
+		return Color::srgba(
+			rgba.red * light,
+			rgba.green * light,
+			rgba.blue * light,
+			rgba.alpha,
+		);
+	}
+}
+
+/// Biome of the tile at offset coordinates (`x`, `z`): the one stored in
+/// `HexGridSettings::map`, then the cliff biome, then the coast biome, then
+/// the first registered biome matching its height and slope.
+pub(super) fn tile_biome<'a>(
+	x: i32,
+	z: i32,
+	height: f32,
+	neighbors: &[f32],
+	settings: &'a HexGridSettings,
+) -> Option<&'a Biome> {
+	let biomes = &settings.biomes;
+	if let Some(stored) = settings
+		.map
+		.as_ref()
+		.and_then(|map| biomes.get(&map.tile(x, z)?.biome))
+	{
+		return Some(stored);
+	}
+	if !reads_neighbors(settings) {
+		return biome_at(biomes, height);
+	}
+	let slope = tile_slope(height, neighbors);
+	if let Some(cliffs) = &settings.cliffs {
+		if let Some(cliff) = biomes
+			.get(&cliffs.biome)
+			.filter(|_| slope >= cliffs.min_slope)
+		{
+			return Some(cliff);
+		}
+	}
+	if let (Some(coasts), Some(sea_level)) = (&settings.coasts, settings.sea_level) {
+		let wet = neighbors.iter().filter(|&&n| n < sea_level).count();
+		if height >= sea_level && wet > 0 {
+			let name = match &coasts.islet_biome {
+				Some(islet) if wet == neighbors.len() => islet,
+				_ => &coasts.biome,
+			};
+			if let Some(coast) = biomes.get(name) {
+				return Some(coast);
+			}
+		}
+	}
+	return biomes.classify(height, slope);
+}
+
+/// Whether `tile_biome` reads the heights of the neighbors of a tile, for
+/// cliffs, coasts or biomes ranged by slope.
+pub(super) fn reads_neighbors(settings: &HexGridSettings) -> bool {
+	let coasts = settings.coasts.is_some() && settings.sea_level.is_some();
+	return settings.cliffs.is_some() || coasts || settings.biomes.uses_slope();
+}
+
+/// `neighbor_heights` of the tile at offset coordinates (`x`, `z`) if
+/// `tile_biome` reads them, or none.
+pub(super) fn biome_neighbors(
+	x: i32,
+	z: i32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Vec<f32> {
+	if !reads_neighbors(settings) {
+		return Vec::new();
+	}
+	return neighbor_heights(x, z, noise, settings);
+}
+
+/// Heights of the neighbors on the map of the tile at offset coordinates
+/// (`x`, `z`), sampled once per tile and shared by `tile_biome`,
+/// `tile_slope` and `ambient_light`.
+pub(super) fn neighbor_heights(
+	x: i32,
+	z: i32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Vec<f32> {
+	return HexCoord::from_offset(x, z)
+		.neighbors_on_map(settings)
+		.into_iter()
+		.map(|n| {
+			let offset = n.to_offset();
+			return sample_height(offset.x, offset.y, noise, settings);
+		})
+		.collect();
+}
+
+/// Largest height difference between a tile at `height` and its
+/// `neighbors`.
+pub(super) fn tile_slope(height: f32, neighbors: &[f32]) -> f32 {
+	return neighbors
+		.iter()
+		.map(|neighbor| (neighbor - height).abs())
+		.fold(0., f32::max);
+}
+
+/// The first biome `height` falls in on level ground. Heights outside every
+/// biome use the last.
+pub(super) fn biome_at(biomes: &BiomeRegistry, height: f32) -> Option<&Biome> {
+	return biomes.classify(height, 0.);
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bevy::render::mesh::VertexAttributeValues;
+
+	use super::*;
+	use crate::hex_grid::{
+		create_chunk, AtlasConfig, ColorRamp, HexGridSettings, HexMap, NormalMode,
+	};
+
+	/// Vertex colors of the tops of a single flat chunk at `height`.
+	fn top_colors(height: f32) -> Vec<[f32; 4]> {
+		let settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 6,
+			height_amplitude: 0.,
+			height_offset: height,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+		let noise = settings.noise();
+		let mesh = create_chunk(0, 0, 0, &noise, &settings);
+		let Some(VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+		else {
+			panic!("chunk meshes have vertex colors");
+		};
+		return colors[..6 * 6 * 7].to_vec();
+	}
+
+	#[test]
+	fn heights_pick_their_band() {
+		let bands = BiomeRegistry::from(Biome::default_bands());
+		let water = bands[0].color.to_linear().to_f32_array();
+		assert!(top_colors(1.).iter().all(|&c| c == water));
+		let snow = bands[4].color.to_linear().to_f32_array();
+		assert!(top_colors(30.).iter().all(|&c| c == snow));
+		// the bands meet at their max_height
+		assert_eq!(biome_at(&bands, 3.2).map(|b| b.name.as_str()), Some("sand"));
+		assert_eq!(
+			biome_at(&bands, 3.19).map(|b| b.name.as_str()),
+			Some("water")
+		);
+		assert_eq!(
+			biome_at(&BiomeRegistry::default(), 3.).map(|b| b.name.as_str()),
+			None
+		);
+	}
+
+	/// A single stored chunk at `height`, except for `tiles`, with no biome
+	/// names so tiles fall back to their band.
+	fn stored(height: f32, tiles: &[((i32, i32), f32)]) -> HexGridSettings {
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 6,
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for tile in &mut map.tiles {
+			tile.height = height;
+			tile.biome.clear();
+		}
+		for &((x, z), height) in tiles {
+			map.tiles[(z * 6 + x) as usize].height = height;
+		}
+		settings.map = Some(Arc::new(map));
+		return settings;
+	}
+
+	fn color(settings: &HexGridSettings, x: i32, z: i32) -> Color {
+		let noise = settings.noise();
+		let height = sample_height(x, z, &noise, settings);
+		let neighbors = settings.color_neighbors(x, z, &noise);
+		return settings.tile_color(x, z, height, &neighbors);
+	}
+
+	fn biome(settings: &HexGridSettings, x: i32, z: i32) -> &str {
+		let noise = settings.noise();
+		let height = sample_height(x, z, &noise, settings);
+		let neighbors = biome_neighbors(x, z, &noise, settings);
+		return &tile_biome(x, z, height, &neighbors, settings).unwrap().name;
+	}
+
+	#[test]
+	fn steep_grass_becomes_cliff() {
+		// both tiles lie in the grass band, 2.5 apart
+		let mut settings = stored(5., &[((3, 2), 7.5)]);
+		assert_eq!(biome(&settings, 2, 2), "grass");
+		settings.cliffs = Some(Cliffs::default());
+		assert_eq!(biome(&settings, 2, 2), "rock");
+		assert_eq!(biome(&settings, 3, 2), "rock");
+		assert_eq!(biome(&settings, 5, 5), "grass");
+		assert_eq!(
+			color(&settings, 2, 2),
+			settings
+				.biomes
+				.iter()
+				.find(|b| b.name == "rock")
+				.unwrap()
+				.color
+		);
+		// gentler than the cliff slope
+		settings.cliffs = Some(Cliffs {
+			min_slope: 3.,
+			..default()
+		});
+		assert_eq!(biome(&settings, 2, 2), "grass");
+	}
+
+	#[test]
+	fn cliffs_take_the_atlas_cell_of_their_biome() {
+		let atlas = AtlasConfig {
+			texture: Handle::default(),
+			columns: 3,
+			rows: 2,
+		};
+		let settings = HexGridSettings {
+			cliffs: Some(Cliffs::default()),
+			atlas: Some(atlas.clone()),
+			normal_mode: NormalMode::Smooth,
+			..stored(5., &[((3, 2), 7.5)])
+		};
+		let noise = settings.noise();
+		let mesh = create_chunk(0, 0, 0, &noise, &settings);
+		let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+		else {
+			panic!("chunk meshes have UVs");
+		};
+		let cell = |name: &str| {
+			let biome = settings.biomes.get(name).unwrap();
+			return atlas.cell_rect(biome.atlas_cell).center();
+		};
+		// the center vertex of each tile sits in the middle of its cell
+		let center = |x: usize, z: usize| Vec2::from(uvs[(z * 6 + x) * 7]);
+		assert!(center(2, 2).distance(cell("rock")) < 1e-5);
+		assert!(center(5, 5).distance(cell("grass")) < 1e-5);
+		assert_ne!(cell("rock"), cell("grass"));
+	}
+
+	#[test]
+	fn land_beside_water_is_coast() {
+		// a pond at (2, 2) in grass well above the sea
+		let mut settings = stored(5., &[((2, 2), 1.)]);
+		settings.sea_level = Some(3.2);
+		let pond = HexCoord::from_offset(2, 2);
+		assert_eq!(biome(&settings, 3, 3), "grass");
+		settings.coasts = Some(Coasts::default());
+		assert_eq!(biome(&settings, 2, 2), "water");
+		for neighbor in pond.neighbors() {
+			let n = neighbor.to_offset();
+			assert_eq!(biome(&settings, n.x, n.y), "sand");
+		}
+		assert_eq!(biome(&settings, 5, 5), "grass");
+	}
+
+	#[test]
+	fn land_surrounded_by_water_is_an_islet() {
+		let island = HexCoord::from_offset(2, 2);
+		let mut settings = stored(1., &[((2, 2), 5.)]);
+		settings.sea_level = Some(3.2);
+		settings.coasts = Some(Coasts {
+			islet_biome: Some("rock".into()),
+			..default()
+		});
+		assert_eq!(biome(&settings, 2, 2), "rock");
+		for neighbor in island.neighbors() {
+			let n = neighbor.to_offset();
+			assert_eq!(biome(&settings, n.x, n.y), "water");
+		}
+	}
+
+	/// Marsh and meadow overlapping from 4 to 6, and scree on any slope of
+	/// at least 2 up to 10, over rock.
+	fn overlapping() -> Vec<Biome> {
+		return vec![
+			Biome::new("scree", 10., Color::srgb(0.5, 0.5, 0.5)).with_slopes(2., f32::INFINITY),
+			Biome::new("marsh", 6., Color::srgb(0.3, 0.4, 0.2)).with_heights(2., 6.),
+			Biome::new("meadow", 10., Color::srgb(0.4, 0.6, 0.2)).with_heights(4., 10.),
+			Biome::new("rock", f32::INFINITY, Color::srgb(0.45, 0.42, 0.4)),
+		];
+	}
+
+	fn classified(registry: &BiomeRegistry, height: f32, slope: f32) -> &str {
+		return &registry.classify(height, slope).unwrap().name;
+	}
+
+	#[test]
+	fn overlaps_go_to_the_earlier_biome() {
+		let registry = BiomeRegistry::from(overlapping());
+		for _ in 0..3 {
+			assert_eq!(classified(&registry, 5., 0.), "marsh");
+		}
+		assert_eq!(classified(&registry, 3., 0.), "marsh");
+		assert_eq!(classified(&registry, 7., 0.), "meadow");
+		assert_eq!(classified(&registry, 5., 3.), "scree");
+		assert_eq!(classified(&registry, 12., 3.), "rock");
+		// registered the other way round, the meadow wins the overlap
+		let mut biomes = overlapping();
+		biomes.swap(1, 2);
+		let swapped = BiomeRegistry::from(biomes);
+		assert_eq!(classified(&swapped, 5., 0.), "meadow");
+		assert_eq!(classified(&swapped, 3., 0.), "marsh");
+		// replacing a biome keeps its place in line
+		let mut registry = registry;
+		registry.register(overlapping().remove(1).with_atlas_cell(7));
+		assert_eq!(registry.len(), 4);
+		assert_eq!(registry.classify(5., 0.).unwrap().atlas_cell, 7);
+		// stored heights classify the same way
+		let mut settings = stored(5., &[((3, 3), 7.)]);
+		settings.biomes = overlapping().into();
+		assert_eq!(biome(&settings, 0, 0), "marsh");
+		assert_eq!(biome(&settings, 3, 3), "scree");
+		assert_eq!(biome(&settings, 5, 5), "marsh");
+	}
+
+	fn close(a: Color, b: Color) -> bool {
+		let (a, b) = (a.to_srgba().to_f32_array(), b.to_srgba().to_f32_array());
+		return a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5);
+	}
+
+	#[test]
+	fn boundaries_blend_half_way() {
+		let registry = BiomeRegistry::from(Biome::default_bands());
+		let grass = registry.get("grass").unwrap().color;
+		let rock = registry.get("rock").unwrap().color;
+		let half = Color::from(grass.to_srgba().mix(&rock.to_srgba(), 0.5));
+		// grass gives way to rock at 8
+		assert!(close(registry.blended_color(8., 2.).unwrap(), half));
+		// well inside a band, and past the blend width, the color is pure
+		for height in [5., 6.99, 9.01, 12.] {
+			let band = registry.classify(height, 0.).unwrap().color;
+			assert_eq!(registry.blended_color(height, 2.), Some(band));
+		}
+		// on either side of the boundary the color leans to the own band
+		let below = registry.blended_color(7.5, 2.).unwrap();
+		let above = registry.blended_color(8.5, 2.).unwrap();
+		assert!(!close(below, half) && !close(below, grass));
+		assert!(!close(above, half) && !close(above, rock));
+		// tiles on the boundary take the blend too
+		let mut settings = stored(8., &[]);
+		settings.biome_blend = Some(2.);
+		assert!(close(color(&settings, 2, 2), half));
+	}
+
+	/// Level grass tiles around a single grass tile raised 3 above them at
+	/// (2, 2).
+	fn plateau(shading: SlopeShading) -> HexGridSettings {
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 6,
+			slope_shading: Some(shading),
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = if i == 2 * 6 + 2 { 8. } else { 5. };
+			tile.biome = "grass".into();
+		}
+		settings.map = Some(Arc::new(map));
+		return settings;
+	}
+
+	fn brightness(settings: &HexGridSettings, x: i32, z: i32) -> f32 {
+		let rgba = color(settings, x, z).to_srgba();
+		return rgba.red + rgba.green + rgba.blue;
+	}
+
+	#[test]
+	fn steep_tiles_shade_darker() {
+		for replace_biomes in [false, true] {
+			let settings = plateau(SlopeShading {
+				replace_biomes,
+				..default()
+			});
+			// (5, 5) borders nothing but level ground
+			let (flat, steep) = (brightness(&settings, 5, 5), brightness(&settings, 2, 2));
+			assert!(steep < flat, "{steep} is not darker than {flat}");
+		}
+	}
+
+	#[test]
+	fn level_tiles_keep_their_biome_color() {
+		let settings = plateau(SlopeShading::default());
+		let unshaded = HexGridSettings {
+			slope_shading: None,
+			..settings.clone()
+		};
+		assert_eq!(brightness(&settings, 5, 5), brightness(&unshaded, 5, 5));
+		let grass = settings.biomes.get("grass").unwrap().color.to_srgba();
+		let steep = settings.slope_shading.as_ref().unwrap().brightness(3.);
+		let shaded = brightness(&settings, 2, 2);
+		assert!((shaded - (grass.red + grass.green + grass.blue) * steep).abs() < 1e-5);
+	}
+
+	/// Sum of the color channels of the top of the tile at (`x`, `z`) in the
+	/// chunk mesh.
+	fn vertex_brightness(settings: &HexGridSettings, x: usize, z: usize) -> f32 {
+		let noise = settings.noise();
+		let mesh = create_chunk(0, 0, 0, &noise, settings);
+		let Some(VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+		else {
+			panic!("chunk meshes have vertex colors");
+		};
+		let [r, g, b, _] = colors[(z * 6 + x) * 7];
+		return r + g + b;
+	}
+
+	#[test]
+	fn atlas_and_ramp_tiles_are_shaded_too() {
+		let atlas = AtlasConfig {
+			texture: Handle::default(),
+			columns: 2,
+			rows: 2,
+		};
+		let ramp = Arc::new(ColorRamp {
+			colors: vec![Color::WHITE, Color::WHITE],
+		});
+		let sloped = HexGridSettings {
+			normal_mode: NormalMode::Smooth,
+			..plateau(SlopeShading::default())
+		};
+		let occluded = HexGridSettings {
+			slope_shading: None,
+			ambient_occlusion: Some(0.8),
+			..sloped.clone()
+		};
+		for settings in [sloped, occluded] {
+			let textured = [
+				HexGridSettings {
+					atlas: Some(atlas.clone()),
+					..settings.clone()
+				},
+				HexGridSettings {
+					ramp: Some(ramp.clone()),
+					..settings
+				},
+			];
+			for settings in textured {
+				// (3, 2) sits at the foot of the raised tile, (5, 5) far from it
+				let (flat, below) = (
+					vertex_brightness(&settings, 5, 5),
+					vertex_brightness(&settings, 3, 2),
+				);
+				assert!((flat - 3.).abs() < 1e-4, "{flat}");
+				assert!(below < flat, "{below} is not darker than {flat}");
+			}
+		}
+	}
+
+	#[test]
+	fn brightness_falls_to_darkest() {
+		let shading = SlopeShading::default();
+		assert_eq!(shading.brightness(0.), 1.);
+		assert_eq!(shading.brightness(shading.max_slope), shading.darkest);
+		assert_eq!(shading.brightness(100.), shading.darkest);
+		assert!(shading.brightness(0.5) > shading.brightness(1.));
+	}
+}
@@ -0,0 +1,52 @@
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+
+use super::{HexGrid, HexGridSettings};
+
+/// Vertex and index data of one chunk as plain buffers, for generating maps
+/// without rendering, e.g. on a server or in tools.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkBuffers {
+	pub positions: Vec<[f32; 3]>,
+	pub normals: Vec<[f32; 3]>,
+	pub uvs: Vec<[f32; 2]>,
+	/// Linear RGBA.
+	pub colors: Vec<[f32; 4]>,
+	pub indices: Vec<u32>,
+}
+
+impl ChunkBuffers {
+	pub fn from_mesh(mesh: &Mesh) -> Self {
+		let mut buffers = Self::default();
+		for (id, values) in mesh.attributes() {
+			match values {
+				VertexAttributeValues::Float32x3(values) if id == Mesh::ATTRIBUTE_POSITION.id => {
+					buffers.positions.clone_from(values);
+				}
+				VertexAttributeValues::Float32x3(values) if id == Mesh::ATTRIBUTE_NORMAL.id => {
+					buffers.normals.clone_from(values);
+				}
+				VertexAttributeValues::Float32x2(values) if id == Mesh::ATTRIBUTE_UV_0.id => {
+					buffers.uvs.clone_from(values);
+				}
+				VertexAttributeValues::Float32x4(values) if id == Mesh::ATTRIBUTE_COLOR.id => {
+					buffers.colors.clone_from(values);
+				}
+				_ => {}
+			}
+		}
+		// flat shaded meshes have their vertices unrolled and no indices
+		buffers.indices = match mesh.indices() {
+			Some(indices) => indices.iter().map(|i| i as u32).collect(),
+			None => (0..buffers.positions.len() as u32).collect(),
+		};
+		return buffers;
+	}
+}
+
+impl HexGrid {
+	/// Builds the full detail buffers of one chunk. Needs no `App`, so it
+	/// works with `MinimalPlugins` or outside of Bevy entirely.
+	pub fn chunk_buffers(index: UVec2, settings: &HexGridSettings) -> ChunkBuffers {
+		return ChunkBuffers::from_mesh(&Self::chunk_mesh(index, settings));
+	}
+}
@@ -0,0 +1,179 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use super::{labels::label_node, spawn_chunk, Chunk, HexGridSettings, LoadedChunks, MapGeneration};
+
+/// Despawns one chunk and spawns it again at the same LOD with a mesh built
+/// from the current settings, leaving every other chunk alone. Meant for
+/// iterating on the walls stitched between tiles and chunks.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RegenerateChunk {
+	pub index: UVec2,
+	/// Labels each vertex of the new mesh with its index in place of the
+	/// labels of any chunk regenerated before.
+	pub show_indices: bool,
+}
+
+/// The chunk whose vertices are labeled with their indices, set by
+/// `RegenerateChunk`.
+#[derive(Resource, Default, Debug)]
+pub struct VertexIndexLabels(pub Option<UVec2>);
+
+/// A text node showing the indices of the vertices at one position.
+#[derive(Component)]
+pub(super) struct VertexLabel;
+
+pub(super) fn regenerate_chunks(
+	mut commands: Commands,
+	mut events: EventReader<RegenerateChunk>,
+	chunks: Query<(&Chunk, Option<&Handle<Mesh>>)>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut loaded: ResMut<LoadedChunks>,
+	mut generation: ResMut<MapGeneration>,
+	mut labeled: ResMut<VertexIndexLabels>,
+	settings: Res<HexGridSettings>,
+) {
+	let noise = settings.noise();
+	for event in events.read() {
+		if event.index.cmpge(settings.map_size).any() {
+			warn!("chunk {} is off the map", event.index);
+			continue;
+		}
+		let mut lod = 0;
+		let mut pending = false;
+		if let Some(entity) = loaded.remove(&event.index.as_ivec2()) {
+			if let Ok((chunk, mesh)) = chunks.get(entity) {
+				lod = chunk.lod;
+				if let Some(mesh) = mesh {
+					meshes.remove(mesh);
+				}
+			}
+			pending = generation.pending.remove(&entity);
+			commands.entity(entity).despawn_recursive();
+		}
+		let entity = spawn_chunk(&mut commands, event.index, lod, &noise, &settings);
+		loaded.insert(event.index.as_ivec2(), entity);
+		// a map still generating waits for the replacement instead
+		if pending {
+			generation.pending.insert(entity);
+		}
+		labeled.0 = event.show_indices.then_some(event.index);
+	}
+}
+
+/// Labels the vertices of the chunk in `VertexIndexLabels` that are in view,
+/// joining the indices of vertices sharing a position, and reusing the text
+/// nodes from the frame before.
+pub(super) fn draw_vertex_indices(
+	mut commands: Commands,
+	cameras: Query<(&Camera, &GlobalTransform), With<PanOrbitCamera>>,
+	chunks: Query<(&GlobalTransform, &Handle<Mesh>), With<Chunk>>,
+	mut labels: Query<(&mut Text, &mut Style, &mut Visibility), With<VertexLabel>>,
+	meshes: Res<Assets<Mesh>>,
+	loaded: Res<LoadedChunks>,
+	labeled: Res<VertexIndexLabels>,
+) {
+	let positions = labeled
+		.0
+		.and_then(|index| loaded.get(&index.as_ivec2()))
+		.and_then(|&entity| chunks.get(entity).ok())
+		.and_then(|(transform, mesh)| {
+			let mesh = meshes.get(mesh)?;
+			let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+			return Some((transform, positions));
+		});
+	let (Some((chunk, positions)), Ok((camera, transform))) = (positions, cameras.get_single())
+	else {
+		for (_, _, mut visibility) in &mut labels {
+			visibility.set_if_neq(Visibility::Hidden);
+		}
+		return;
+	};
+
+	let mut grouped = Vec::<(Vec3, String)>::new();
+	let mut groups = HashMap::new();
+	for (i, pos) in positions.iter().enumerate() {
+		let key = pos.map(f32::to_bits);
+		let group = *groups.entry(key).or_insert_with(|| {
+			grouped.push((Vec3::from(*pos), String::new()));
+			return grouped.len() - 1;
+		});
+		let label = &mut grouped[group].1;
+		if !label.is_empty() {
+			label.push('/');
+		}
+		label.push_str(&i.to_string());
+	}
+	let mut shown = grouped.into_iter().filter_map(|(pos, label)| {
+		let screen = camera.world_to_viewport(transform, chunk.transform_point(pos))?;
+		return Some((label, screen));
+	});
+
+	for (mut text, mut style, mut visibility) in &mut labels {
+		let Some((label, screen)) = shown.next() else {
+			visibility.set_if_neq(Visibility::Hidden);
+			continue;
+		};
+		if text.sections[0].value != label {
+			text.sections[0].value = label;
+		}
+		style.left = Val::Px(screen.x);
+		style.top = Val::Px(screen.y);
+		visibility.set_if_neq(Visibility::Inherited);
+	}
+	for _ in shown {
+		commands.spawn((VertexLabel, label_node()));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{ecs::system::RunSystemOnce, tasks::AsyncComputeTaskPool};
+
+	use super::*;
+	use crate::hex_grid::HexGrid;
+
+	#[test]
+	fn regenerating_a_chunk_leaves_the_others_alone() {
+		AsyncComputeTaskPool::get_or_init(default);
+		let mut world = World::new();
+		let settings = HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 4,
+			..default()
+		};
+		let mut meshes = Assets::<Mesh>::default();
+		let mut loaded = LoadedChunks::default();
+		let mut chunks = Vec::new();
+		for index in [UVec2::ZERO, UVec2::X, UVec2::Y, UVec2::ONE] {
+			let mesh = meshes.add(HexGrid::chunk_mesh(index, &settings));
+			let entity = world.spawn((Chunk { index, lod: 0 }, mesh.clone())).id();
+			loaded.0.insert(index.as_ivec2(), entity);
+			chunks.push((entity, mesh));
+		}
+		world.insert_resource(settings);
+		world.insert_resource(meshes);
+		world.insert_resource(loaded);
+		world.init_resource::<MapGeneration>();
+		world.init_resource::<VertexIndexLabels>();
+		world.init_resource::<Events<RegenerateChunk>>();
+		world.send_event(RegenerateChunk {
+			index: UVec2::ZERO,
+			show_indices: true,
+		});
+		world.run_system_once(regenerate_chunks);
+
+		let (first, first_mesh) = &chunks[0];
+		assert!(world.get_entity(*first).is_none());
+		assert!(!world.resource::<Assets<Mesh>>().contains(first_mesh));
+		let replaced = world.resource::<LoadedChunks>().0[&IVec2::ZERO];
+		assert_ne!(replaced, *first);
+		assert_eq!(world.get::<Chunk>(replaced).unwrap().index, UVec2::ZERO);
+		for (entity, mesh) in &chunks[1..] {
+			assert_eq!(world.get::<Handle<Mesh>>(*entity), Some(mesh));
+			assert!(world.resource::<Assets<Mesh>>().contains(mesh));
+		}
+		assert_eq!(world.resource::<LoadedChunks>().0.len(), 4);
+		assert_eq!(world.resource::<VertexIndexLabels>().0, Some(UVec2::ZERO));
+	}
+}
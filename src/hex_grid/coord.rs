@@ -0,0 +1,445 @@
+use std::ops::{Add, Mul, Sub};
+
+use bevy::prelude::*;
+
+use super::{sample_height, HexGridSettings, TerrainNoise};
+
+/// Direction across a tile edge, with +z as north. Direction `i` crosses the
+/// edge between corners `i` and `i + 1` of `HexGridSettings::hex_corners`.
+/// The compass names describe the pointy-top layout; flat-top grids mirror it
+/// across the x = z diagonal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum HexDirection {
+	NorthEast,
+	East,
+	SouthEast,
+	SouthWest,
+	West,
+	NorthWest,
+}
+
+impl HexDirection {
+	pub const ALL: [HexDirection; 6] = [
+		HexDirection::NorthEast,
+		HexDirection::East,
+		HexDirection::SouthEast,
+		HexDirection::SouthWest,
+		HexDirection::West,
+		HexDirection::NorthWest,
+	];
+
+	pub fn from_index(index: usize) -> Self {
+		return Self::ALL[index % 6];
+	}
+
+	pub fn index(&self) -> usize {
+		return *self as usize;
+	}
+
+	pub fn opposite(&self) -> Self {
+		return Self::from_index(self.index() + 3);
+	}
+
+	/// Axial offset to the neighbor in this direction.
+	pub fn offset(&self) -> HexCoord {
+		return match self {
+			HexDirection::NorthEast => HexCoord::new(0, 1),
+			HexDirection::East => HexCoord::new(1, 0),
+			HexDirection::SouthEast => HexCoord::new(1, -1),
+			HexDirection::SouthWest => HexCoord::new(0, -1),
+			HexDirection::West => HexCoord::new(-1, 0),
+			HexDirection::NorthWest => HexCoord::new(-1, 1),
+		};
+	}
+}
+
+/// Axial hex coordinate. `r` is the row along z, `q` the column along x.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct HexCoord {
+	pub q: i32,
+	pub r: i32,
+}
+
+impl HexCoord {
+	pub const ZERO: HexCoord = HexCoord { q: 0, r: 0 };
+
+	pub const fn new(q: i32, r: i32) -> Self {
+		return Self { q, r };
+	}
+
+	/// Converts from the odd-row offset layout used by the chunk generator.
+	pub fn from_offset(x: i32, z: i32) -> Self {
+		return Self::new(x - (z - (z & 1)) / 2, z);
+	}
+
+	pub fn to_offset(&self) -> IVec2 {
+		return IVec2::new(self.q + (self.r - (self.r & 1)) / 2, self.r);
+	}
+
+	/// Index of the chunk generating this tile. Chunks cover square blocks
+	/// of offset coordinates, so odd rows shifted half a tile along x still
+	/// belong to the chunk of the rows above and below them.
+	pub fn chunk(&self, chunk_size: u32) -> IVec2 {
+		return self
+			.to_offset()
+			.div_euclid(IVec2::splat(chunk_size.max(1) as i32));
+	}
+
+	pub fn from_cube(cube: IVec3) -> Self {
+		return Self::new(cube.x, cube.y);
+	}
+
+	/// Cube coordinate as (q, r, s), with q + r + s == 0.
+	pub fn to_cube(&self) -> IVec3 {
+		return IVec3::new(self.q, self.r, -self.q - self.r);
+	}
+
+	/// Rounds fractional cube coordinates to the nearest hex.
+	pub fn round_cube(cube: Vec3) -> Self {
+		let mut rounded = cube.round();
+		let diff = (rounded - cube).abs();
+		if diff.x > diff.y && diff.x > diff.z {
+			rounded.x = -rounded.y - rounded.z;
+		} else if diff.y > diff.z {
+			rounded.y = -rounded.x - rounded.z;
+		}
+		return Self::new(rounded.x as i32, rounded.y as i32);
+	}
+
+	pub fn neighbor(&self, dir: HexDirection) -> HexCoord {
+		return *self + dir.offset();
+	}
+
+	/// Neighbors in `HexDirection::ALL` order.
+	pub fn neighbors(&self) -> [HexCoord; 6] {
+		return HexDirection::ALL.map(|dir| self.neighbor(dir));
+	}
+
+	/// Neighbors that are tiles of the map, following wrapped edges.
+	pub fn neighbors_on_map(&self, settings: &HexGridSettings) -> Vec<HexCoord> {
+		return self
+			.neighbors()
+			.into_iter()
+			.map(|n| settings.wrap_coord(n))
+			.filter(|&n| settings.contains(n))
+			.collect();
+	}
+
+	pub fn distance(&self, other: &HexCoord) -> u32 {
+		let diff = (self.to_cube() - other.to_cube()).abs();
+		return ((diff.x + diff.y + diff.z) / 2) as u32;
+	}
+
+	/// Tiles at exactly `radius` from `center`, walking clockwise from the
+	/// south-west corner of the ring.
+	pub fn ring(center: HexCoord, radius: u32) -> Vec<HexCoord> {
+		if radius == 0 {
+			return vec![center];
+		}
+		let start = HexDirection::SouthWest;
+		let mut tiles = Vec::with_capacity(6 * radius as usize);
+		let mut tile = center + start.offset() * radius as i32;
+		for i in 0..6 {
+			let dir = HexDirection::from_index(start.index() + 2 + i);
+			for _ in 0..radius {
+				tiles.push(tile);
+				tile = tile.neighbor(dir);
+			}
+		}
+		return tiles;
+	}
+
+	/// Tiles within `radius` of `center`, ordered ring by ring outwards.
+	pub fn spiral(center: HexCoord, radius: u32) -> Vec<HexCoord> {
+		let mut tiles = Vec::with_capacity((3 * radius * (radius + 1) + 1) as usize);
+		for r in 0..=radius {
+			tiles.extend(Self::ring(center, r));
+		}
+		return tiles;
+	}
+
+	/// Tiles a straight line from `self` to `other` passes through, both ends
+	/// included.
+	pub fn line_to(&self, other: &HexCoord) -> Vec<HexCoord> {
+		let steps = self.distance(other);
+		let (a, b) = (self.to_cube().as_vec3(), other.to_cube().as_vec3());
+		// nudge the line off tile corners so ties always round the same way,
+		// by a few times the rounding error of points this far out
+		let magnitude = a.abs().max(b.abs()).max_element().max(1.);
+		let nudge = Vec3::new(1., 2., -3.) * magnitude * f32::EPSILON * 8.;
+		let (a, b) = (a + nudge, b + nudge);
+		return (0..=steps)
+			.map(|i| {
+				let t = if steps == 0 {
+					0.
+				} else {
+					i as f32 / steps as f32
+				};
+				return Self::round_cube(a.lerp(b, t));
+			})
+			.collect();
+	}
+
+	/// World position of the tile center at y = 0.
+	pub fn to_world(&self, settings: &HexGridSettings) -> Vec3 {
+		let x = (self.q as f32 + self.r as f32 * 0.5) * settings.inner_radius() * 2.;
+		let z = self.r as f32 * settings.outer_radius * 1.5;
+		return settings.world_origin() + settings.orient(Vec3::new(x, 0., z));
+	}
+
+	/// World positions of the corners of the tile top, in the order of
+	/// `HexGridSettings::hex_corners`. These are the corners of the full
+	/// hexagon, before any `tile_inset` shrinks it.
+	pub fn corners(&self, settings: &HexGridSettings) -> [Vec3; 6] {
+		return self.corners_with(&settings.noise(), settings);
+	}
+
+	/// `corners`, sampling the height of the tile from `noise`.
+	pub(super) fn corners_with(
+		&self,
+		noise: &TerrainNoise,
+		settings: &HexGridSettings,
+	) -> [Vec3; 6] {
+		let offset = self.to_offset();
+		let height = sample_height(offset.x, offset.y, noise, settings);
+		let center = self.to_world(settings) + Vec3::Y * height;
+		return settings.hex_corners().map(|corner| center + corner);
+	}
+
+	/// Middle of each edge of the tile top like `corners`, edge `i` facing
+	/// `HexDirection::from_index(i)`.
+	pub fn edge_midpoints(&self, settings: &HexGridSettings) -> [Vec3; 6] {
+		let corners = self.corners(settings);
+		return std::array::from_fn(|i| corners[i].lerp(corners[(i + 1) % 6], 0.5));
+	}
+
+	/// The tile containing `pos`, ignoring its height.
+	pub fn from_world(pos: Vec3, settings: &HexGridSettings) -> Self {
+		// orienting is its own inverse
+		let pos = settings.orient(pos - settings.world_origin());
+		let r = pos.z / (settings.outer_radius * 1.5);
+		let q = pos.x / (settings.inner_radius() * 2.) - r * 0.5;
+		return Self::round_cube(Vec3::new(q, r, -q - r));
+	}
+}
+
+impl Add for HexCoord {
+	type Output = HexCoord;
+
+	fn add(self, rhs: HexCoord) -> HexCoord {
+		return HexCoord::new(self.q + rhs.q, self.r + rhs.r);
+	}
+}
+
+impl Sub for HexCoord {
+	type Output = HexCoord;
+
+	fn sub(self, rhs: HexCoord) -> HexCoord {
+		return HexCoord::new(self.q - rhs.q, self.r - rhs.r);
+	}
+}
+
+impl Mul<i32> for HexCoord {
+	type Output = HexCoord;
+
+	fn mul(self, rhs: i32) -> HexCoord {
+		return HexCoord::new(self.q * rhs, self.r * rhs);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::utils::HashSet;
+
+	use super::*;
+	use crate::hex_grid::{chunk_origin, HexGrid, HexOrientation, NormalMode};
+
+	#[test]
+	fn tile_centers_round_trip() {
+		for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop] {
+			let settings = HexGridSettings {
+				orientation,
+				..default()
+			};
+			let size = settings.chunk_size as i32;
+			for (x, z) in (0..size * size).map(|i| (i % size, i / size)) {
+				let coord = HexCoord::from_offset(x, z);
+				let center = coord.to_world(&settings);
+				assert_eq!(HexCoord::from_world(center, &settings), coord);
+				// anywhere within the inner circle rounds to the same tile
+				let nudge = Vec3::new(0.4, 3., -0.6) * settings.inner_radius();
+				assert_eq!(HexCoord::from_world(center + nudge, &settings), coord);
+			}
+		}
+	}
+
+	#[test]
+	fn neighbors_point_back() {
+		let coords = [
+			HexCoord::from_offset(3, 4),
+			HexCoord::from_offset(3, 5),
+			HexCoord::ZERO,
+			HexCoord::new(-7, 2),
+		];
+		for coord in coords {
+			for dir in HexDirection::ALL {
+				let neighbor = coord.neighbor(dir);
+				assert_eq!(neighbor.neighbor(dir.opposite()), coord);
+				assert_eq!(coord.distance(&neighbor), 1);
+			}
+		}
+	}
+
+	#[test]
+	fn rings_and_spirals_count_their_tiles() {
+		let center = HexCoord::new(2, -3);
+		for radius in 0..6 {
+			let ring = HexCoord::ring(center, radius);
+			assert_eq!(ring.len(), (6 * radius as usize).max(1));
+			assert!(ring.iter().all(|tile| tile.distance(&center) == radius));
+			assert_eq!(ring.iter().collect::<HashSet<_>>().len(), ring.len());
+			if radius > 0 {
+				for (i, tile) in ring.iter().enumerate() {
+					assert_eq!(tile.distance(&ring[(i + 1) % ring.len()]), 1);
+				}
+			}
+
+			let spiral = HexCoord::spiral(center, radius);
+			assert_eq!(spiral.len(), (3 * radius * (radius + 1) + 1) as usize);
+			assert_eq!(spiral.iter().collect::<HashSet<_>>().len(), spiral.len());
+		}
+	}
+
+	#[test]
+	fn lines_step_between_neighbors() {
+		let ends = [
+			(HexCoord::ZERO, HexCoord::ZERO),
+			(HexCoord::ZERO, HexCoord::new(5, -2)),
+			(HexCoord::new(-3, 4), HexCoord::new(6, -7)),
+			// exactly between two tiles half way along
+			(HexCoord::ZERO, HexCoord::new(2, -1)),
+			(HexCoord::new(200, -150), HexCoord::new(202, -151)),
+		];
+		for (a, b) in ends {
+			let line = a.line_to(&b);
+			assert_eq!(line.len() as u32, a.distance(&b) + 1);
+			assert_eq!((line[0], line[line.len() - 1]), (a, b));
+			for pair in line.windows(2) {
+				assert_eq!(pair[0].distance(&pair[1]), 1, "{a:?} to {b:?}");
+			}
+			// both ways through the same tiles
+			let mut back = b.line_to(&a);
+			back.reverse();
+			assert_eq!(back, line);
+		}
+	}
+
+	#[test]
+	fn long_lines_far_out_step_between_neighbors() {
+		// tiles a couple hundred steps out, where a fixed nudge rounds away
+		for i in -20..20 {
+			for j in -20..20 {
+				let a = HexCoord::new(i * 3, j * 2 - 120);
+				let b = HexCoord::new(7 - i * 2, j * 3 + 100);
+				let line = a.line_to(&b);
+				assert_eq!(line.len() as u32, a.distance(&b) + 1);
+				assert!(line.windows(2).all(|pair| pair[0].distance(&pair[1]) == 1));
+				let mut back = b.line_to(&a);
+				back.reverse();
+				assert_eq!(back, line, "{a:?} to {b:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn grids_side_by_side_keep_their_own_coords() {
+		let here = HexGridSettings::default();
+		let there = HexGridSettings {
+			origin: Vec3::new(100., 5., -40.),
+			..default()
+		};
+		for coord in [HexCoord::ZERO, HexCoord::new(3, -1), HexCoord::new(-4, 6)] {
+			let near = coord.to_world(&here);
+			let far = coord.to_world(&there);
+			assert!((far - near - there.origin).length() < 1e-4);
+			// a little off the center still lands on the same tile
+			let nudge = Vec3::new(0.3, 2., -0.2);
+			assert_eq!(HexCoord::from_world(near + nudge, &here), coord);
+			assert_eq!(HexCoord::from_world(far + nudge, &there), coord);
+			assert_ne!(HexCoord::from_world(far, &here), coord);
+		}
+	}
+
+	#[test]
+	fn corners_match_the_tile_top_vertices() {
+		let base = HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 4,
+			height_amplitude: 4.,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+		let variants = [
+			base.clone(),
+			HexGridSettings {
+				orientation: HexOrientation::FlatTop,
+				origin: Vec3::new(7., -1., 3.),
+				..base
+			},
+		];
+		for settings in variants {
+			for index in [UVec2::ZERO, UVec2::ONE] {
+				let origin = chunk_origin(index, &settings);
+				let positions = HexGrid::chunk_buffers(index, &settings).positions;
+				let noise = settings.noise();
+				for (i, top) in positions[..16 * 7].chunks_exact(7).enumerate() {
+					let offset = (index * 4).as_ivec2() + IVec2::new(i as i32 % 4, i as i32 / 4);
+					let coord = HexCoord::from_offset(offset.x, offset.y);
+					let corners = coord.corners_with(&noise, &settings);
+					assert_eq!(corners, coord.corners(&settings));
+					for (corner, vertex) in corners.iter().zip(&top[1..]) {
+						assert!((*corner - origin - Vec3::from(*vertex)).length() < 1e-4);
+					}
+					let middles = coord.edge_midpoints(&settings);
+					assert!((middles[0] - corners[0].lerp(corners[1], 0.5)).length() < 1e-5);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn tiles_belong_to_the_chunk_that_generates_them() {
+		let base = HexGridSettings {
+			map_size: UVec2::new(3, 2),
+			chunk_size: 4,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+		let variants = [
+			base.clone(),
+			HexGridSettings {
+				orientation: HexOrientation::FlatTop,
+				..base
+			},
+		];
+		for settings in variants {
+			let mut seen = HashSet::new();
+			for index in (0..6).map(|i| UVec2::new(i % 3, i / 3)) {
+				let origin = chunk_origin(index, &settings);
+				let positions = HexGrid::chunk_buffers(index, &settings).positions;
+				let tiles = HexGrid::tiles_in_chunk(index.as_ivec2(), &settings);
+				for (coord, top) in tiles.zip(positions[..16 * 7].chunks_exact(7)) {
+					assert_eq!(coord.chunk(settings.chunk_size), index.as_ivec2());
+					// the chunk's own tile sits at the center of its fan
+					let center = origin + Vec3::from(top[0]);
+					assert!((center - coord.to_world(&settings)).xz().length() < 1e-4);
+					assert!(seen.insert(coord));
+				}
+			}
+			assert_eq!(seen.len(), 12 * 8);
+		}
+		// blocks left of and above the map count down from -1
+		assert_eq!(HexCoord::from_offset(-1, -1).chunk(4), IVec2::NEG_ONE);
+		assert_eq!(HexCoord::from_offset(3, 4).chunk(4), IVec2::new(0, 1));
+	}
+}
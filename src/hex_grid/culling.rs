@@ -0,0 +1,134 @@
+use bevy::{math::Affine3A, prelude::*, render::primitives::Frustum};
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use super::{Chunk, HexGridSettings};
+
+pub(super) fn cull_chunks(
+	cameras: Query<&Frustum, With<PanOrbitCamera>>,
+	mut chunks: Query<(&Chunk, &mut Visibility)>,
+	mut culled: Local<bool>,
+	settings: Res<HexGridSettings>,
+) {
+	// without culling chunk visibility belongs to the game, once the chunks
+	// hidden while culling was on are shown again
+	if !settings.chunk_culling {
+		if std::mem::take(&mut *culled) {
+			for (_, mut visibility) in &mut chunks {
+				if *visibility == Visibility::Hidden {
+					*visibility = Visibility::Inherited;
+				}
+			}
+		}
+		return;
+	}
+	let Ok(frustum) = cameras.get_single() else {
+		return;
+	};
+	*culled = true;
+	for (chunk, mut visibility) in &mut chunks {
+		let visible = frustum.intersects_obb(
+			&settings.chunk_aabb(chunk.index),
+			&Affine3A::IDENTITY,
+			true,
+			true,
+		);
+		// only touch visibility on change so it isn't flagged every frame
+		visibility.set_if_neq(match visible {
+			true => Visibility::Inherited,
+			false => Visibility::Hidden,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{ecs::system::RunSystemOnce, render::camera::CameraProjection};
+
+	use super::*;
+
+	fn map() -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::new(8, 1),
+			chunk_size: 4,
+			height_amplitude: 0.,
+			height_offset: 2.,
+			chunk_culling: true,
+			..default()
+		};
+	}
+
+	/// The frustum of a camera looking straight down at the middle of the
+	/// first chunk, close enough to see little else.
+	fn frustum(settings: &HexGridSettings) -> Frustum {
+		let aabb = settings.chunk_aabb(UVec2::ZERO);
+		let target = Vec3::from(aabb.center);
+		let view = Transform::from_translation(target + Vec3::Y * 12.)
+			.looking_at(target, Vec3::Z)
+			.compute_matrix();
+		let mut projection = PerspectiveProjection::default();
+		projection.update(1280., 720.);
+		return Frustum::from_clip_from_world(&(projection.get_clip_from_view() * view.inverse()));
+	}
+
+	fn spawn_chunks(world: &mut World, settings: &HexGridSettings) -> Vec<Entity> {
+		world.insert_resource(settings.clone());
+		world.spawn((frustum(settings), PanOrbitCamera::default()));
+		return (0..settings.map_size.x)
+			.map(|x| {
+				let chunk = Chunk {
+					index: UVec2::new(x, 0),
+					lod: 0,
+				};
+				return world.spawn((chunk, Visibility::Inherited)).id();
+			})
+			.collect();
+	}
+
+	#[test]
+	fn chunk_aabbs_meet_the_frustum_in_view() {
+		let settings = map();
+		let frustum = frustum(&settings);
+		let visible = |x| {
+			let aabb = settings.chunk_aabb(UVec2::new(x, 0));
+			return frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, true, true);
+		};
+		assert!(visible(0) && visible(1));
+		assert!(!visible(7));
+		// neighboring chunks overlap where LOD meshes reach into the next one
+		let (first, next) = (
+			settings.chunk_aabb(UVec2::ZERO),
+			settings.chunk_aabb(UVec2::X),
+		);
+		assert!(first.max().x > next.min().x);
+	}
+
+	#[test]
+	fn far_chunks_hide_while_culling() {
+		let mut world = World::new();
+		let chunks = spawn_chunks(&mut world, &map());
+		world.run_system_once(cull_chunks);
+		let visibility = |world: &World, entity| *world.get::<Visibility>(entity).unwrap();
+		assert_eq!(visibility(&world, chunks[0]), Visibility::Inherited);
+		assert_eq!(visibility(&world, chunks[7]), Visibility::Hidden);
+	}
+
+	#[test]
+	fn chunks_keep_their_visibility_without_culling() {
+		let mut world = World::new();
+		let settings = HexGridSettings {
+			chunk_culling: false,
+			..map()
+		};
+		let chunks = spawn_chunks(&mut world, &settings);
+		world.entity_mut(chunks[0]).insert(Visibility::Hidden);
+		world.run_system_once(cull_chunks);
+		assert_eq!(
+			world.get::<Visibility>(chunks[0]),
+			Some(&Visibility::Hidden)
+		);
+		assert_eq!(
+			world.get::<Visibility>(chunks[7]),
+			Some(&Visibility::Inherited)
+		);
+	}
+}
@@ -0,0 +1,148 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+	biome::{biome_neighbors, tile_biome},
+	height::sample_height,
+	Chunk, HexCoord, HexGridSettings,
+};
+
+/// Gameplay state of a single tile.
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct TileData {
+	/// Height of the tile top when it was generated.
+	pub height: f32,
+	pub biome: String,
+	/// Starts out false for tiles below `HexGridSettings::sea_level` and of
+	/// impassable biomes.
+	pub passable: bool,
+	/// Price of stepping onto the tile, starting out as its biome's.
+	pub move_cost: f32,
+	pub owner: Option<u32>,
+}
+
+/// Per-tile state for every tile of a generated chunk. Entries are added as
+/// chunks are first generated and kept when they are streamed out, so edits
+/// survive, but are rebuilt from scratch on `RegenerateMap`.
+#[derive(Resource, Default, Debug, Deref)]
+pub struct HexData(HashMap<HexCoord, TileData>);
+
+impl HexData {
+	pub fn get(&self, coord: HexCoord) -> Option<&TileData> {
+		return self.0.get(&coord);
+	}
+
+	pub fn get_mut(&mut self, coord: HexCoord) -> Option<&mut TileData> {
+		return self.0.get_mut(&coord);
+	}
+
+	pub(super) fn clear(&mut self) {
+		self.0.clear();
+	}
+}
+
+pub(super) fn populate_hex_data(
+	chunks: Query<&Chunk, Added<Chunk>>,
+	mut data: ResMut<HexData>,
+	settings: Res<HexGridSettings>,
+) {
+	if chunks.is_empty() {
+		return;
+	}
+	let noise = settings.noise();
+	let size = settings.chunk_size;
+	for chunk in &chunks {
+		let origin = (chunk.index * size).as_ivec2();
+		for z in 0..size as i32 {
+			for x in 0..size as i32 {
+				let (x, z) = (origin.x + x, origin.y + z);
+				let coord = HexCoord::from_offset(x, z);
+				if !settings.contains(coord) {
+					continue;
+				}
+				data.0.entry(coord).or_insert_with(|| {
+					let height = sample_height(x, z, &noise, &settings);
+					let neighbors = biome_neighbors(x, z, &noise, &settings);
+					let biome = tile_biome(x, z, height, &neighbors, &settings);
+					let above_sea = settings.sea_level.is_none_or(|sea| height >= sea);
+					return TileData {
+						height,
+						biome: match settings.map.as_ref().and_then(|map| map.tile(x, z)) {
+							Some(tile) => tile.biome.clone(),
+							None => biome.map_or_else(String::new, |biome| biome.name.clone()),
+						},
+						passable: above_sea && biome.is_none_or(|biome| biome.passable),
+						move_cost: biome.map_or(1., |biome| biome.move_cost),
+						owner: None,
+					};
+				});
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::ecs::system::RunSystemOnce;
+
+	use super::*;
+	use crate::hex_grid::{HexGrid, MapShape};
+
+	/// `HexData` once every chunk of the map is generated.
+	fn populated(settings: &HexGridSettings) -> HexData {
+		let mut world = World::new();
+		world.insert_resource(settings.clone());
+		world.init_resource::<HexData>();
+		for z in 0..settings.map_size.y {
+			for x in 0..settings.map_size.x {
+				world.spawn(Chunk {
+					index: UVec2::new(x, z),
+					lod: 0,
+				});
+			}
+		}
+		world.run_system_once(populate_hex_data);
+		return world.remove_resource::<HexData>().unwrap();
+	}
+
+	#[test]
+	fn every_tile_gets_data() {
+		let settings = HexGridSettings {
+			map_size: UVec2::new(3, 2),
+			chunk_size: 4,
+			height_amplitude: 4.,
+			..default()
+		};
+		let data = populated(&settings);
+		let tiles = settings.map_tiles();
+		assert_eq!(data.len(), (tiles.x * tiles.y) as usize);
+		for z in 0..tiles.y as i32 {
+			for x in 0..tiles.x as i32 {
+				let coord = HexCoord::from_offset(x, z);
+				// looked up from anywhere on the tile top
+				let center = coord.to_world(&settings);
+				for corner in settings.hex_corners() {
+					let pos = center + corner * 0.9 + Vec3::Y * 3.;
+					assert_eq!(HexCoord::from_world(pos, &settings), coord);
+				}
+				let tile = data.get(coord).unwrap();
+				assert_eq!(tile.height, HexGrid::height_at(coord, &settings));
+				assert!(!tile.biome.is_empty());
+			}
+		}
+	}
+
+	#[test]
+	fn hexagon_maps_hold_only_their_tiles() {
+		for radius in 0..=4 {
+			let settings = HexGridSettings {
+				map_size: UVec2::splat(2),
+				chunk_size: 5,
+				shape: MapShape::Hexagon { radius },
+				..default()
+			};
+			let data = populated(&settings);
+			assert_eq!(data.len(), (3 * radius * (radius + 1) + 1) as usize);
+			assert!(data.keys().all(|&coord| settings.contains(coord)));
+		}
+	}
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+
+use bevy::{
+	prelude::*,
+	render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+
+use super::{sample_height, HexCoord, HexDirection, HexGridSettings, WALL_EPSILON};
+
+/// Lifts roads off the tile tops so they don't z-fight with them.
+const ROAD_LIFT: f32 = 0.01;
+
+/// Geometry placed along the edge two neighboring tiles share.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub enum EdgeKind {
+	/// A slab standing on the edge, reaching from the lower tile top to
+	/// `height` above the higher one.
+	Wall { height: f32, thickness: f32 },
+	/// A strip from one tile center to the other, stepping down the edge
+	/// between their heights.
+	Road { width: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeError {
+	/// The two tiles don't share an edge.
+	NotAdjacent,
+	/// One of the tiles is off the map.
+	OffMap,
+}
+
+impl fmt::Display for EdgeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			EdgeError::NotAdjacent => write!(f, "tiles don't share an edge"),
+			EdgeError::OffMap => write!(f, "tile is off the map"),
+		};
+	}
+}
+
+impl std::error::Error for EdgeError {}
+
+/// `v` at y = 0.
+fn flat(v: Vec3) -> Vec3 {
+	return Vec3::new(v.x, 0., v.z);
+}
+
+/// Walls and roads collected into one mesh in world space, to draw a whole
+/// network of them with a single entity.
+#[derive(Default, Debug, Clone)]
+pub struct EdgeStructures {
+	verts: Vec<Vec3>,
+	normals: Vec<Vec3>,
+	indices: Vec<u32>,
+}
+
+impl EdgeStructures {
+	/// Adds a structure of `kind` on the edge between `a` and `b`, at the
+	/// height of their tile tops.
+	pub fn add_edge_structure(
+		&mut self,
+		a: HexCoord,
+		b: HexCoord,
+		kind: EdgeKind,
+		settings: &HexGridSettings,
+	) -> Result<(), EdgeError> {
+		let (a, b) = (settings.wrap_coord(a), settings.wrap_coord(b));
+		if !settings.contains(a) || !settings.contains(b) {
+			return Err(EdgeError::OffMap);
+		}
+		let dir = HexDirection::ALL
+			.into_iter()
+			.find(|&dir| settings.wrap_coord(a.neighbor(dir)) == b)
+			.ok_or(EdgeError::NotAdjacent)?;
+		let i = dir.index();
+		// like `HexCoord::corners`, with one noise for both tiles
+		let noise = settings.noise();
+		let height = |coord: HexCoord| {
+			let n = coord.to_offset();
+			return sample_height(n.x, n.y, &noise, settings);
+		};
+		let top = a.to_world(settings) + Vec3::Y * height(a);
+		let corners = settings.hex_corners().map(|corner| top + corner);
+		let (start, end) = (corners[i], corners[(i + 1) % 6]);
+		let height_a = start.y;
+		let height_b = settings.origin.y + height(b);
+		let (low, high) = (height_a.min(height_b), height_a.max(height_b));
+		// measured from `a`, whose neighbor may lie across a wrapped seam
+		let center = flat(a.to_world(settings));
+		let middle = flat(start.lerp(end, 0.5));
+		let (start, end) = (flat(start), flat(end));
+		let across = (middle - center).normalize();
+		let along = (end - start).normalize();
+
+		match kind {
+			EdgeKind::Wall { height, thickness } => {
+				let (top, half) = (high + height, across * thickness * 0.5);
+				for side in [half, -half] {
+					self.push_quad(
+						[
+							start + side + Vec3::Y * low,
+							end + side + Vec3::Y * low,
+							end + side + Vec3::Y * top,
+							start + side + Vec3::Y * top,
+						],
+						side.normalize_or_zero(),
+					);
+				}
+				self.push_quad(
+					[
+						start - half + Vec3::Y * top,
+						end - half + Vec3::Y * top,
+						end + half + Vec3::Y * top,
+						start + half + Vec3::Y * top,
+					],
+					Vec3::Y,
+				);
+			}
+			EdgeKind::Road { width } => {
+				let side = along * width * 0.5;
+				let halves = [
+					(center, middle, height_a),
+					(middle, middle * 2. - center, height_b),
+				];
+				for (from, to, height) in halves {
+					let lift = Vec3::Y * (height + ROAD_LIFT);
+					self.push_quad(
+						[
+							from - side + lift,
+							to - side + lift,
+							to + side + lift,
+							from + side + lift,
+						],
+						Vec3::Y,
+					);
+				}
+				// the riser faces the lower tile
+				if high - low > WALL_EPSILON {
+					let facing = if height_a < height_b { -across } else { across };
+					let (low, high) = (low + ROAD_LIFT, high + ROAD_LIFT);
+					self.push_quad(
+						[
+							middle - side + Vec3::Y * low,
+							middle + side + Vec3::Y * low,
+							middle + side + Vec3::Y * high,
+							middle - side + Vec3::Y * high,
+						],
+						facing,
+					);
+				}
+			}
+		}
+		return Ok(());
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.indices.is_empty();
+	}
+
+	pub fn into_mesh(self) -> Mesh {
+		return Mesh::new(
+			PrimitiveTopology::TriangleList,
+			RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+		)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.verts)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+		.with_inserted_indices(Indices::U32(self.indices));
+	}
+
+	/// Adds the quad through `corners` in order, wound to face `normal`
+	/// whichever way they turn.
+	fn push_quad(&mut self, corners: [Vec3; 4], normal: Vec3) {
+		let idx = self.verts.len() as u32;
+		self.verts.extend(corners);
+		self.normals.extend([normal; 4]);
+		let turn = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+		let order = if turn.dot(normal) >= 0. {
+			[0, 1, 2, 0, 2, 3]
+		} else {
+			[0, 2, 1, 0, 3, 2]
+		};
+		self.indices.extend(order.map(|i| idx + i));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn structures_need_a_shared_edge() {
+		let settings = HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 4,
+			..default()
+		};
+		let wall = EdgeKind::Wall {
+			height: 1.,
+			thickness: 0.2,
+		};
+		let road = EdgeKind::Road { width: 0.5 };
+		let a = HexCoord::from_offset(2, 2);
+		let mut edges = EdgeStructures::default();
+		for b in [a, a + HexCoord::new(2, 0), a + HexCoord::new(1, 1)] {
+			for kind in [wall, road] {
+				let added = edges.add_edge_structure(a, b, kind, &settings);
+				assert_eq!(added, Err(EdgeError::NotAdjacent));
+			}
+		}
+		assert!(edges.is_empty());
+		let off_map = edges.add_edge_structure(
+			HexCoord::from_offset(0, 0),
+			HexCoord::new(-1, 0),
+			wall,
+			&settings,
+		);
+		assert_eq!(off_map, Err(EdgeError::OffMap));
+		assert!(edges.is_empty());
+
+		for neighbor in a.neighbors() {
+			assert_eq!(
+				edges.add_edge_structure(a, neighbor, road, &settings),
+				Ok(())
+			);
+		}
+		assert!(!edges.is_empty());
+	}
+}
@@ -0,0 +1,392 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+	biome::biome_at, chunk_task, Chunk, HexCoord, HexData, HexGrid, HexGridSettings, HexMap,
+	HexTile, LoadedChunks, TileSpan,
+};
+
+/// Chunks whose tiles were edited, rebuilt on the next update.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct DirtyChunks(pub HashSet<UVec2>);
+
+/// Sent for every loaded chunk `DirtyChunks` rebuilds, so whatever stands on
+/// its tiles can follow the edit.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChunkRebuilt {
+	pub entity: Entity,
+	pub index: UVec2,
+}
+
+impl HexGrid {
+	/// Sets the stored height of `coord`, capturing the chunk of the tile
+	/// from the generated map into `HexGridSettings::map` first. Maps made
+	/// this way fill in lazily, one edited chunk at a time. The tile and its
+	/// `HexData` entry take the biome of its new height.
+	///
+	/// The settings aren't flagged as changed, so instead of the whole map
+	/// only the chunks showing the tile or its walls are marked dirty.
+	pub fn set_height(
+		coord: HexCoord,
+		height: f32,
+		settings: &mut ResMut<HexGridSettings>,
+		data: &mut HexData,
+		dirty: &mut DirtyChunks,
+	) {
+		let settings = settings.bypass_change_detection();
+		let coord = settings.wrap_coord(coord);
+		let biome = biome_at(&settings.biomes, height).map_or_else(String::new, |b| b.name.clone());
+		let Some(tile) = stored_tile(coord, settings) else {
+			return;
+		};
+		tile.height = height;
+		tile.biome.clone_from(&biome);
+		if let Some(tile) = data.get_mut(coord) {
+			tile.height = height;
+			tile.biome = biome;
+			tile.passable = settings.sea_level.is_none_or(|sea| height >= sea);
+		}
+
+		// neighboring chunks hang walls down to the tile, and may shade or
+		// class their own tiles by it
+		for tile in std::iter::once(coord).chain(coord.neighbors_on_map(settings)) {
+			dirty.insert(tile.chunk(settings.chunk_size).as_uvec2());
+		}
+	}
+
+	/// Replaces the solid spans floating above `coord`, marking its chunk and
+	/// those of its neighbors dirty like `set_height`.
+	pub fn set_spans(
+		coord: HexCoord,
+		spans: Vec<TileSpan>,
+		settings: &mut ResMut<HexGridSettings>,
+		dirty: &mut DirtyChunks,
+	) {
+		let settings = settings.bypass_change_detection();
+		let coord = settings.wrap_coord(coord);
+		let Some(tile) = stored_tile(coord, settings) else {
+			return;
+		};
+		tile.spans = spans;
+		// neighbors wall off their spans where this tile's no longer cover them
+		for tile in std::iter::once(coord).chain(coord.neighbors_on_map(settings)) {
+			dirty.insert(tile.chunk(settings.chunk_size).as_uvec2());
+		}
+	}
+
+	/// Hides or shows `coord`, adding it to or removing it from
+	/// `HexGridSettings::hidden_tiles`. Its vertices stay in the mesh, only
+	/// the triangles of its top and of the walls it shares with its
+	/// neighbors are left out, at every LOD, along with its decorations.
+	/// The chunks of the tile and its neighbors are marked dirty.
+	pub fn set_tile_visible(
+		coord: HexCoord,
+		visible: bool,
+		settings: &mut ResMut<HexGridSettings>,
+		dirty: &mut DirtyChunks,
+	) {
+		let settings = settings.bypass_change_detection();
+		let coord = settings.wrap_coord(coord);
+		if !settings.contains(coord) || settings.hidden_tiles.contains(&coord) != visible {
+			return;
+		}
+		let hidden = Arc::make_mut(&mut settings.hidden_tiles);
+		match visible {
+			true => hidden.remove(&coord),
+			false => hidden.insert(coord),
+		};
+		// walls to the neighbors may belong to their chunks
+		for tile in std::iter::once(coord).chain(coord.neighbors_on_map(settings)) {
+			dirty.insert(tile.chunk(settings.chunk_size).as_uvec2());
+		}
+	}
+
+	/// Sets the stored biome of every tile within `radius` of `center`, and
+	/// of their `HexData` entries, marking the chunks they are in dirty like
+	/// `set_height`. Entries also take the passability and move cost of a
+	/// registered biome.
+	pub fn paint(
+		center: HexCoord,
+		radius: u32,
+		biome: &str,
+		settings: &mut ResMut<HexGridSettings>,
+		data: &mut HexData,
+		dirty: &mut DirtyChunks,
+	) {
+		let settings = settings.bypass_change_detection();
+		// brushes wider than a wrapped map reach some tiles twice
+		let tiles = HexCoord::spiral(center, radius)
+			.into_iter()
+			.map(|coord| settings.wrap_coord(coord))
+			.filter(|&coord| settings.contains(coord))
+			.collect::<HashSet<_>>();
+		for coord in tiles {
+			let Some(tile) = stored_tile(coord, settings) else {
+				continue;
+			};
+			tile.biome = biome.into();
+			if let Some(tile) = data.get_mut(coord) {
+				tile.biome = biome.into();
+				if let Some(biome) = settings.biomes.get(biome) {
+					tile.passable = biome.passable;
+					tile.move_cost = biome.move_cost;
+				}
+			}
+			dirty.insert(coord.chunk(settings.chunk_size).as_uvec2());
+		}
+	}
+}
+
+/// The stored tile at `coord`, capturing its chunk from the generated map
+/// into `HexGridSettings::map` first.
+fn stored_tile(coord: HexCoord, settings: &mut HexGridSettings) -> Option<&mut HexTile> {
+	if !settings.contains(coord) {
+		return None;
+	}
+	let offset = coord.to_offset().as_uvec2();
+	// captured from the noise alone, without the map being edited
+	let mut map = settings
+		.map
+		.take()
+		.unwrap_or_else(|| Arc::new(HexMap::lazy(settings)));
+	Arc::make_mut(&mut map).capture(offset / settings.chunk_size, settings);
+	let map = Arc::make_mut(settings.map.insert(map));
+	// loaded maps smaller than the chunk grid repeat their edge tiles
+	if offset.cmpge(map.size).any() {
+		return None;
+	}
+	return map
+		.tiles
+		.get_mut((offset.y * map.size.x + offset.x) as usize);
+}
+
+pub(super) fn rebuild_dirty_chunks(
+	mut commands: Commands,
+	mut dirty: ResMut<DirtyChunks>,
+	mut rebuilt: EventWriter<ChunkRebuilt>,
+	chunks: Query<&Chunk>,
+	loaded: Res<LoadedChunks>,
+	settings: Res<HexGridSettings>,
+) {
+	if dirty.is_empty() {
+		return;
+	}
+	let noise = settings.noise();
+	for index in dirty.drain() {
+		// unloaded chunks pick the edit up when they stream back in
+		let Some(&entity) = loaded.get(&index.as_ivec2()) else {
+			continue;
+		};
+		let Ok(chunk) = chunks.get(entity) else {
+			continue;
+		};
+		// the old mesh stays up until the new one is ready
+		let task = chunk_task(index, chunk.lod, &noise, &settings);
+		commands.entity(entity).insert(task);
+		rebuilt.send(ChunkRebuilt { entity, index });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::ecs::system::RunSystemOnce;
+
+	use super::*;
+	use crate::hex_grid::{chunk_origin, data::populate_hex_data, NormalMode};
+
+	/// A 2×2 map of 4 tile chunks with every chunk's `HexData`.
+	fn world() -> World {
+		let mut world = World::new();
+		world.insert_resource(HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 4,
+			sea_level: Some(3.2),
+			..default()
+		});
+		world.init_resource::<HexData>();
+		world.init_resource::<DirtyChunks>();
+		for index in [UVec2::ZERO, UVec2::X, UVec2::Y, UVec2::ONE] {
+			world.spawn(Chunk { index, lod: 0 });
+		}
+		world.run_system_once(populate_hex_data);
+		return world;
+	}
+
+	fn set_height(world: &mut World, coord: HexCoord, height: f32) {
+		world.run_system_once(
+			move |mut settings: ResMut<HexGridSettings>,
+			      mut data: ResMut<HexData>,
+			      mut dirty: ResMut<DirtyChunks>| {
+				HexGrid::set_height(coord, height, &mut settings, &mut data, &mut dirty);
+			},
+		);
+	}
+
+	#[test]
+	fn edits_on_a_seam_dirty_both_chunks() {
+		let dirtied = |x, z| {
+			let mut world = world();
+			set_height(&mut world, HexCoord::from_offset(x, z), 9.);
+			return world.resource::<DirtyChunks>().0.clone();
+		};
+		// in the middle of the seam between the first two chunks
+		assert_eq!(dirtied(3, 1), HashSet::from([UVec2::ZERO, UVec2::X]));
+		// with all its neighbors in the second chunk
+		assert_eq!(dirtied(5, 2), HashSet::from([UVec2::X]));
+	}
+
+	#[test]
+	fn edits_update_the_tile_data() {
+		let mut world = world();
+		let coord = HexCoord::from_offset(3, 1);
+		let sea = world.resource::<HexGridSettings>().sea_level.unwrap();
+		for (height, biome, passable) in [
+			(sea - 1., "water", false),
+			(12., "rock", true),
+			(5., "grass", true),
+		] {
+			set_height(&mut world, coord, height);
+			let tile = world.resource::<HexData>().get(coord).unwrap().clone();
+			assert_eq!(tile.height, height);
+			assert_eq!(tile.biome, biome);
+			assert_eq!(tile.passable, passable);
+			let settings = world.resource::<HexGridSettings>();
+			assert_eq!(HexGrid::height_at(coord, settings), height);
+			let stored = settings.map.as_ref().unwrap().tile(3, 1).unwrap();
+			assert_eq!(stored.biome, biome);
+		}
+	}
+
+	#[test]
+	fn edits_capture_only_their_chunk() {
+		let mut world = world();
+		let generated = world.resource::<HexGridSettings>().clone();
+		set_height(&mut world, HexCoord::from_offset(5, 2), 9.);
+		let settings = world.resource::<HexGridSettings>();
+		let map = settings.map.as_ref().unwrap();
+		let captured = (0..8 * 8)
+			.filter(|i| map.is_captured(i % 8, i / 8))
+			.map(|i| IVec2::new(i % 8, i / 8).as_uvec2() / 4)
+			.collect::<Vec<_>>();
+		assert_eq!(captured, vec![UVec2::X; 16]);
+		// the other tiles still follow the noise
+		for i in 0..8 * 8 {
+			let coord = HexCoord::from_offset(i % 8, i / 8);
+			if coord != HexCoord::from_offset(5, 2) {
+				let height = HexGrid::height_at(coord, settings);
+				assert_eq!(height, HexGrid::height_at(coord, &generated));
+			}
+		}
+
+		assert!(map
+			.save(std::env::temp_dir().join("hex-grid-lazy.ron"))
+			.is_err());
+
+		// filled up, the map matches one captured in full
+		let mut filled = (**map).clone();
+		filled.fill(&generated);
+		let mut full = HexMap::from_settings(&generated);
+		full.tiles[2 * 8 + 5] = filled.tiles[2 * 8 + 5].clone();
+		assert!(filled.is_filled());
+		assert_eq!(filled, full);
+	}
+
+	#[test]
+	fn paint_covers_the_brush() {
+		let mut world = world();
+		let center = HexCoord::from_offset(4, 4);
+		world.run_system_once(
+			move |mut settings: ResMut<HexGridSettings>,
+			      mut data: ResMut<HexData>,
+			      mut dirty: ResMut<DirtyChunks>| {
+				HexGrid::paint(center, 2, "snow", &mut settings, &mut data, &mut dirty);
+			},
+		);
+		let settings = world.resource::<HexGridSettings>();
+		let map = settings.map.as_ref().unwrap();
+		let painted = map.tiles.iter().filter(|tile| tile.biome == "snow").count();
+		assert_eq!(painted, 19);
+		let data = world.resource::<HexData>();
+		assert_eq!(
+			data.values().filter(|tile| tile.biome == "snow").count(),
+			19
+		);
+		for coord in HexCoord::spiral(center, 2) {
+			let offset = coord.to_offset();
+			assert_eq!(map.tile(offset.x, offset.y).unwrap().biome, "snow");
+			assert!(data.get(coord).unwrap().passable);
+		}
+		// the brush reaches into every chunk around the center
+		assert_eq!(world.resource::<DirtyChunks>().len(), 4);
+	}
+
+	fn set_visible(world: &mut World, coord: HexCoord, visible: bool) {
+		world.run_system_once(
+			move |mut settings: ResMut<HexGridSettings>, mut dirty: ResMut<DirtyChunks>| {
+				HexGrid::set_tile_visible(coord, visible, &mut settings, &mut dirty);
+			},
+		);
+	}
+
+	#[test]
+	fn shown_tiles_restore_the_mesh() {
+		let mut world = world();
+		let chunk = |world: &World| {
+			return HexGrid::chunk_buffers(UVec2::X, world.resource::<HexGridSettings>());
+		};
+		let before = chunk(&world);
+		let before_mode = world.resource::<HexGridSettings>().normal_mode;
+		let coord = HexCoord::from_offset(4, 2);
+		set_visible(&mut world, coord, false);
+		set_visible(&mut world, coord, false);
+		assert_eq!(world.resource::<HexGridSettings>().hidden_tiles.len(), 1);
+		assert_eq!(
+			**world.resource::<DirtyChunks>(),
+			HashSet::from([UVec2::ZERO, UVec2::X])
+		);
+		// flat shading unrolls the six triangles of the top, and those of the
+		// walls around it
+		let hidden = chunk(&world);
+		assert!(hidden.positions.len() < before.positions.len() - 18);
+
+		// with smooth normals the vertices stay shared, so nothing may index
+		// the top, nor may a wall run along an edge of it
+		world.resource_mut::<HexGridSettings>().normal_mode = NormalMode::Smooth;
+		let settings = world.resource::<HexGridSettings>().clone();
+		// on the seam with the first chunk
+		let top = (2 * 4) * 7;
+		let buffers = HexGrid::chunk_buffers(UVec2::X, &settings);
+		let origin = chunk_origin(UVec2::X, &settings);
+		let corners = buffers.positions[top + 1..top + 7]
+			.iter()
+			.map(|&p| (origin + Vec3::from(p)).xz())
+			.collect::<Vec<_>>();
+		for index in [UVec2::ZERO, UVec2::X] {
+			let buffers = HexGrid::chunk_buffers(index, &settings);
+			let origin = chunk_origin(index, &settings);
+			for tri in buffers.indices.chunks_exact(3) {
+				if index == UVec2::X {
+					assert!(tri.iter().all(|&i| !(top..top + 7).contains(&(i as usize))));
+				}
+				// walls follow the 16 tile tops
+				if tri.iter().any(|&i| i < 16 * 7) {
+					continue;
+				}
+				let touched = corners.iter().filter(|&&corner| {
+					return tri.iter().any(|&i| {
+						let p = origin + Vec3::from(buffers.positions[i as usize]);
+						return p.xz().distance(corner) < 1e-4;
+					});
+				});
+				// walls between two neighbors reach one corner at most
+				assert!(touched.count() < 2, "{index} {tri:?}");
+			}
+		}
+		world.resource_mut::<HexGridSettings>().normal_mode = before_mode;
+
+		set_visible(&mut world, coord, true);
+		assert!(world.resource::<HexGridSettings>().hidden_tiles.is_empty());
+		assert_eq!(chunk(&world), before);
+	}
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+
+use super::{biome::biome_at, HexCoord, HexGridSettings, HexMap};
+
+/// Thermal erosion: material slides from each tile onto neighbors lower than
+/// it by more than `talus`, wearing cliffs down into slopes.
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct ThermalErosion {
+	pub iterations: u32,
+	/// Height difference between neighbors that stays stable.
+	pub talus: f32,
+	/// Fraction of the excess height moved each iteration, in 0..=0.5.
+	pub rate: f32,
+}
+
+impl Default for ThermalErosion {
+	fn default() -> Self {
+		return Self {
+			iterations: 20,
+			talus: 0.5,
+			rate: 0.25,
+		};
+	}
+}
+
+impl HexMap {
+	/// Runs thermal erosion over the whole map, across chunk boundaries, and
+	/// updates each tile's biome to its new height. Fills the map first.
+	pub fn erode(&mut self, erosion: &ThermalErosion, settings: &HexGridSettings) {
+		self.fill(settings);
+		let neighbors = self.neighbor_indices();
+		let rate = erosion.rate.clamp(0., 0.5);
+		let mut heights = self
+			.tiles
+			.iter()
+			.map(|tile| tile.height)
+			.collect::<Vec<_>>();
+		let mut deltas = vec![0.; heights.len()];
+		for _ in 0..erosion.iterations {
+			deltas.fill(0.);
+			for (i, tile_neighbors) in neighbors.iter().enumerate() {
+				let drops = tile_neighbors
+					.iter()
+					.map(|&n| heights[i] - heights[n] - erosion.talus)
+					.collect::<Vec<_>>();
+				let total = drops.iter().filter(|&&d| d > 0.).sum::<f32>();
+				let steepest = drops.iter().copied().fold(0f32, f32::max);
+				if total <= 0. {
+					continue;
+				}
+				let moved = rate * steepest;
+				deltas[i] -= moved;
+				for (&n, &drop) in tile_neighbors.iter().zip(&drops) {
+					if drop > 0. {
+						deltas[n] += moved * drop / total;
+					}
+				}
+			}
+			for (height, delta) in heights.iter_mut().zip(&deltas) {
+				*height += delta;
+			}
+		}
+
+		for (tile, height) in self.tiles.iter_mut().zip(heights) {
+			tile.height = height;
+			tile.biome = biome_at(&settings.biomes, height)
+				.map_or_else(String::new, |biome| biome.name.clone());
+		}
+	}
+
+	/// Largest height difference between any two neighboring tiles.
+	pub fn max_slope(&self) -> f32 {
+		let neighbors = self.neighbor_indices();
+		return neighbors
+			.iter()
+			.enumerate()
+			.flat_map(|(i, tile_neighbors)| {
+				tile_neighbors
+					.iter()
+					.map(move |&n| (self.tiles[i].height - self.tiles[n].height).abs())
+			})
+			.fold(0., f32::max);
+	}
+
+	/// Indices of the neighbors inside the map for every tile.
+	pub(super) fn neighbor_indices(&self) -> Vec<Vec<usize>> {
+		let size = self.size.as_ivec2();
+		let mut neighbors = Vec::with_capacity(self.tiles.len());
+		for z in 0..size.y {
+			for x in 0..size.x {
+				let tile_neighbors = HexCoord::from_offset(x, z)
+					.neighbors()
+					.iter()
+					.map(|n| n.to_offset())
+					.filter(|n| n.cmpge(IVec2::ZERO).all() && n.cmplt(size).all())
+					.map(|n| (n.y * size.x + n.x) as usize)
+					.collect();
+				neighbors.push(tile_neighbors);
+			}
+		}
+		return neighbors;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::HexTile;
+
+	/// Terraces 4 apart, each two columns wide.
+	fn cliffs() -> HexMap {
+		let size = UVec2::new(12, 8);
+		let tiles = (0..size.x * size.y)
+			.map(|i| HexTile {
+				height: ((i % size.x) / 2) as f32 * 4.,
+				biome: String::new(),
+				spans: Vec::new(),
+			})
+			.collect();
+		return HexMap {
+			size,
+			tiles,
+			captured: Vec::new(),
+		};
+	}
+
+	fn eroded(iterations: u32) -> HexMap {
+		let mut map = cliffs();
+		let erosion = ThermalErosion {
+			iterations,
+			..default()
+		};
+		map.erode(&erosion, &HexGridSettings::default());
+		return map;
+	}
+
+	#[test]
+	fn erosion_wears_cliffs_down() {
+		let slopes = [0, 5, 20, 80].map(|iterations| eroded(iterations).max_slope());
+		assert_eq!(slopes[0], 4.);
+		assert!(
+			slopes.windows(2).all(|pair| pair[1] < pair[0]),
+			"{slopes:?}"
+		);
+	}
+
+	#[test]
+	fn erosion_moves_material_without_losing_any() {
+		let total = |map: &HexMap| map.tiles.iter().map(|tile| tile.height).sum::<f32>();
+		let map = eroded(20);
+		assert!((total(&map) - total(&cliffs())).abs() < 1e-2);
+		// biomes follow the new heights
+		let settings = HexGridSettings::default();
+		for tile in &map.tiles {
+			assert_eq!(
+				Some(tile.biome.as_str()),
+				biome_at(&settings.biomes, tile.height).map(|b| b.name.as_str())
+			);
+		}
+	}
+}
@@ -0,0 +1,261 @@
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::Path,
+};
+
+use bevy::{
+	prelude::*,
+	render::{
+		mesh::{Indices, VertexAttributeValues},
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat},
+	},
+};
+
+use super::{
+	chunk_origin, create_chunk, height::sample_height, HexCoord, HexGrid, HexGridSettings,
+};
+
+impl HexGrid {
+	/// Writes every chunk of the map at full detail to a Wavefront OBJ file.
+	pub fn export_obj(path: impl AsRef<Path>, settings: &HexGridSettings) -> io::Result<()> {
+		let mut file = BufWriter::new(File::create(path)?);
+		write_obj(&mut file, settings)?;
+		return file.flush();
+	}
+
+	/// One gray pixel per tile, black at the low end of `range` and white at
+	/// the high end, with +z at the top of the image like `minimap`. Without
+	/// a range the lowest and highest tiles of the map are used. Tiles
+	/// outside the map shape are black.
+	pub fn heightmap(range: Option<(f32, f32)>, settings: &HexGridSettings) -> Image {
+		let noise = settings.noise();
+		let tiles = settings.map_tiles();
+		let heights = (0..tiles.y as i32)
+			.rev()
+			.flat_map(|z| (0..tiles.x as i32).map(move |x| (x, z)))
+			.map(|(x, z)| {
+				let coord = HexCoord::from_offset(x, z);
+				return settings
+					.contains(coord)
+					.then(|| sample_height(x, z, &noise, settings));
+			})
+			.collect::<Vec<_>>();
+		let (low, high) = range.unwrap_or_else(|| {
+			return heights
+				.iter()
+				.flatten()
+				.fold((f32::MAX, f32::MIN), |(low, high), &h| {
+					(low.min(h), high.max(h))
+				});
+		});
+		let data = heights
+			.iter()
+			.map(|height| {
+				let Some(height) = height else {
+					return 0;
+				};
+				let t = (height - low) / (high - low).max(f32::EPSILON);
+				return (t.clamp(0., 1.) * 255.).round() as u8;
+			})
+			.collect();
+		return Image::new(
+			Extent3d {
+				width: tiles.x,
+				height: tiles.y,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			data,
+			TextureFormat::R8Unorm,
+			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+		);
+	}
+
+	/// Writes `heightmap` to a grayscale PNG, picked by the `.png` extension
+	/// `path` needs to have.
+	pub fn export_heightmap(
+		path: impl AsRef<Path>,
+		range: Option<(f32, f32)>,
+		settings: &HexGridSettings,
+	) -> io::Result<()> {
+		let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+		let image = Self::heightmap(range, settings)
+			.try_into_dynamic()
+			.map_err(|err| invalid(err.to_string()))?;
+		return image.save(path).map_err(|err| invalid(err.to_string()));
+	}
+}
+
+/// Writes the combined chunk meshes as OBJ, with each chunk moved to its
+/// place in the map.
+fn write_obj(out: &mut impl Write, settings: &HexGridSettings) -> io::Result<()> {
+	let noise = settings.noise();
+	// OBJ indices are 1-based and count across the whole file
+	let mut offset = 1;
+	for z in 0..settings.map_size.y {
+		for x in 0..settings.map_size.x {
+			let index = UVec2::new(x, z);
+			let mesh = create_chunk(x, z, 0, &noise, settings);
+			let origin = chunk_origin(index, settings);
+			let (
+				Some(VertexAttributeValues::Float32x3(positions)),
+				Some(VertexAttributeValues::Float32x3(normals)),
+				Some(VertexAttributeValues::Float32x2(uvs)),
+			) = (
+				mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+				mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+				mesh.attribute(Mesh::ATTRIBUTE_UV_0),
+			)
+			else {
+				continue;
+			};
+
+			writeln!(out, "o chunk_{x}_{z}")?;
+			for p in positions {
+				let p = Vec3::from(*p) + origin;
+				writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+			}
+			for [u, v] in uvs {
+				// OBJ puts v = 0 at the bottom of the texture
+				writeln!(out, "vt {} {}", u, 1. - v)?;
+			}
+			for [x, y, z] in normals {
+				writeln!(out, "vn {x} {y} {z}")?;
+			}
+
+			// flat shaded meshes have their vertices unrolled and no indices
+			let indices = match mesh.indices() {
+				Some(Indices::U32(indices)) => indices.clone(),
+				Some(Indices::U16(indices)) => indices.iter().map(|&i| i as u32).collect(),
+				None => (0..positions.len() as u32).collect(),
+			};
+			for tri in indices.chunks_exact(3) {
+				let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| i + offset);
+				writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+			}
+			offset += positions.len() as u32;
+		}
+	}
+	return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::hex_grid::{HexMap, NormalMode};
+
+	#[test]
+	fn obj_keeps_every_triangle() {
+		for normal_mode in [NormalMode::Flat, NormalMode::Smooth] {
+			let settings = HexGridSettings {
+				map_size: UVec2::new(2, 2),
+				chunk_size: 4,
+				height_amplitude: 4.,
+				normal_mode,
+				..default()
+			};
+			let mut obj = Vec::new();
+			write_obj(&mut obj, &settings).unwrap();
+			let obj = String::from_utf8(obj).unwrap();
+
+			let vertices = obj
+				.lines()
+				.filter_map(|line| line.strip_prefix("v "))
+				.map(|v| {
+					let v = v.split(' ').map(|c| c.parse::<f32>().unwrap());
+					return Vec3::from_slice(&v.collect::<Vec<_>>());
+				})
+				.collect::<Vec<_>>();
+			let faces = obj
+				.lines()
+				.filter_map(|line| line.strip_prefix("f "))
+				.map(|f| {
+					return f
+						.split(' ')
+						.map(|corner| corner.split('/').next().unwrap().parse::<usize>().unwrap())
+						.collect::<Vec<_>>();
+				})
+				.collect::<Vec<_>>();
+
+			let mut triangles = Vec::new();
+			for index in [UVec2::ZERO, UVec2::X, UVec2::Y, UVec2::ONE] {
+				let mesh = HexGrid::chunk_mesh(index, &settings);
+				let positions = mesh
+					.attribute(Mesh::ATTRIBUTE_POSITION)
+					.and_then(|positions| positions.as_float3())
+					.unwrap();
+				let indices = match mesh.indices() {
+					Some(indices) => indices.iter().collect(),
+					None => (0..positions.len()).collect::<Vec<_>>(),
+				};
+				let origin = chunk_origin(index, &settings);
+				triangles.extend(indices.chunks_exact(3).map(|tri| {
+					return [tri[0], tri[1], tri[2]].map(|i| Vec3::from(positions[i]) + origin);
+				}));
+			}
+			assert_eq!(faces.len(), triangles.len());
+			for (face, triangle) in faces.iter().zip(&triangles) {
+				assert_eq!(face.len(), 3);
+				for (&i, corner) in face.iter().zip(triangle) {
+					assert!(vertices[i - 1].distance(*corner) < 1e-4);
+				}
+			}
+		}
+	}
+
+	/// Two chunks side by side with stored heights from -3 at (0, 0) up to
+	/// 9 at (2, 3), and 1 everywhere else.
+	fn slope() -> HexGridSettings {
+		let mut settings = HexGridSettings {
+			map_size: UVec2::new(2, 1),
+			chunk_size: 4,
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = match (i % 8, i / 8) {
+				(0, 0) => -3.,
+				(2, 3) => 9.,
+				_ => 1.,
+			};
+		}
+		settings.map = Some(Arc::new(map));
+		return settings;
+	}
+
+	#[test]
+	fn heightmaps_span_black_to_white() {
+		let settings = slope();
+		let image = HexGrid::heightmap(None, &settings);
+		assert_eq!(image.size(), UVec2::new(8, 4));
+		// +z is at the top, so the first row holds the last tiles
+		let pixel = |x: usize, z: usize| image.data[(3 - z) * 8 + x];
+		assert_eq!(pixel(0, 0), 0);
+		assert_eq!(pixel(2, 3), 255);
+		assert_eq!(pixel(5, 1), (4. / 12. * 255_f32).round() as u8);
+
+		let clipped = HexGrid::heightmap(Some((1., 2.)), &settings);
+		assert_eq!(clipped.data[3 * 8], 0);
+		assert_eq!(clipped.data[2], 255);
+		assert_eq!(clipped.data[8 + 5], 0);
+	}
+
+	#[test]
+	fn heightmap_pngs_have_one_pixel_per_tile() {
+		let path =
+			std::env::temp_dir().join(format!("hex-grid-{}-heightmap.png", std::process::id()));
+		HexGrid::export_heightmap(&path, None, &slope()).unwrap();
+		let png = std::fs::read(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		// the header chunk comes first, after the 8 byte signature
+		assert_eq!(&png[12..16], b"IHDR");
+		let size = |at: usize| u32::from_be_bytes([png[at], png[at + 1], png[at + 2], png[at + 3]]);
+		assert_eq!((size(16), size(20)), (8, 4));
+		// 8 bit grayscale
+		assert_eq!((png[24], png[25]), (8, 0));
+	}
+}
@@ -0,0 +1,299 @@
+use bevy::{
+	prelude::*,
+	render::{
+		mesh::Indices, primitives::Aabb, render_asset::RenderAssetUsages,
+		render_resource::PrimitiveTopology,
+	},
+	utils::{HashMap, HashSet},
+};
+
+use super::{
+	sample_height, to_chunk_pos, Chunk, ChunkRebuilt, HexCoord, HexGridSettings, HexOrientation,
+	TerrainNoise,
+};
+
+/// Covers tiles no `FogRevealer` can see with a translucent overlay, and
+/// tiles never seen with an opaque one.
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct Fog {
+	pub color: Color,
+	/// Opacity of the overlay over tiles seen before but not now.
+	pub seen_alpha: f32,
+	/// Height of the overlay above the tile tops.
+	pub lift: f32,
+}
+
+impl Default for Fog {
+	fn default() -> Self {
+		return Self {
+			color: Color::BLACK,
+			seen_alpha: 0.6,
+			lift: 0.05,
+		};
+	}
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TileVisibility {
+	#[default]
+	Unseen,
+	Seen,
+	Visible,
+}
+
+/// Reveals the tiles within `radius` of the entity every frame.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+pub struct FogRevealer {
+	pub radius: u32,
+}
+
+/// What has been seen of every tile. Tiles missing from it are unseen.
+#[derive(Resource, Default, Debug)]
+pub struct FogOfWar {
+	tiles: HashMap<HexCoord, TileVisibility>,
+	/// Chunks with a tile whose visibility changed since their overlay was
+	/// last built.
+	changed: HashSet<UVec2>,
+}
+
+impl FogOfWar {
+	pub fn state(&self, coord: HexCoord) -> TileVisibility {
+		return self.tiles.get(&coord).copied().unwrap_or_default();
+	}
+
+	/// Makes every tile within `radius` of `center` visible.
+	pub fn reveal(&mut self, center: HexCoord, radius: u32, settings: &HexGridSettings) {
+		for coord in in_sight(center, radius, settings) {
+			self.set(coord, TileVisibility::Visible, settings);
+		}
+	}
+
+	/// Makes exactly the tiles of `visible` visible, turning the other
+	/// visible tiles back into seen ones.
+	pub fn set_visible(&mut self, visible: &HashSet<HexCoord>, settings: &HexGridSettings) {
+		let hidden = self
+			.tiles
+			.iter()
+			.filter(|(coord, &state)| state == TileVisibility::Visible && !visible.contains(*coord))
+			.map(|(&coord, _)| coord)
+			.collect::<Vec<_>>();
+		for coord in hidden {
+			self.set(coord, TileVisibility::Seen, settings);
+		}
+		for &coord in visible {
+			self.set(coord, TileVisibility::Visible, settings);
+		}
+	}
+
+	/// Forgets every tile, leaving the whole map unseen.
+	pub fn clear(&mut self, settings: &HexGridSettings) {
+		for coord in self.tiles.drain().map(|(coord, _)| coord) {
+			self.changed
+				.insert(coord.chunk(settings.chunk_size).as_uvec2());
+		}
+	}
+
+	fn set(&mut self, coord: HexCoord, state: TileVisibility, settings: &HexGridSettings) {
+		if self.tiles.insert(coord, state) != Some(state) {
+			self.changed
+				.insert(coord.chunk(settings.chunk_size).as_uvec2());
+		}
+	}
+}
+
+/// The tiles on the map within `radius` of `center`, ring by ring.
+fn in_sight(
+	center: HexCoord,
+	radius: u32,
+	settings: &HexGridSettings,
+) -> impl Iterator<Item = HexCoord> + '_ {
+	return (0..=radius)
+		.flat_map(move |r| HexCoord::ring(center, r))
+		.map(|coord| settings.wrap_coord(coord))
+		.filter(|&coord| settings.contains(coord));
+}
+
+/// The fog covering the tiles of the chunk it is a child of.
+#[derive(Component)]
+pub(super) struct FogOverlay(UVec2);
+
+pub(super) fn update_fog(
+	mut fog: ResMut<FogOfWar>,
+	revealers: Query<(&GlobalTransform, &FogRevealer)>,
+	settings: Res<HexGridSettings>,
+) {
+	if settings.fog.is_none() || revealers.is_empty() {
+		return;
+	}
+	let visible = revealers
+		.iter()
+		.flat_map(|(transform, revealer)| {
+			let center = HexCoord::from_world(transform.translation(), &settings);
+			return in_sight(center, revealer.radius, &settings);
+		})
+		.collect::<HashSet<_>>();
+	// only tiles that changed state mark their overlays for rebuilding
+	fog.set_visible(&visible, &settings);
+}
+
+pub(super) fn draw_fog(
+	mut commands: Commands,
+	mut fog: ResMut<FogOfWar>,
+	mut material: Local<Option<Handle<StandardMaterial>>>,
+	chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+	mut rebuilt: EventReader<ChunkRebuilt>,
+	overlays: Query<(Entity, &FogOverlay, &Handle<Mesh>)>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	settings: Res<HexGridSettings>,
+) {
+	let Some(fog_settings) = settings.fog else {
+		rebuilt.clear();
+		return;
+	};
+	// edits move the tile tops the overlay floats over
+	let mut changed = std::mem::take(&mut fog.bypass_change_detection().changed);
+	changed.extend(rebuilt.read().map(|event| event.index));
+	if chunks.is_empty() && changed.is_empty() {
+		return;
+	}
+	let noise = settings.noise();
+	let material = material
+		.get_or_insert_with(|| {
+			return materials.add(StandardMaterial {
+				unlit: true,
+				alpha_mode: AlphaMode::Blend,
+				..default()
+			});
+		})
+		.clone();
+
+	for (entity, chunk) in &chunks {
+		let mesh = fog_mesh(chunk.index, &fog, fog_settings, &noise, &settings);
+		commands.entity(entity).with_children(|parent| {
+			parent.spawn((
+				FogOverlay(chunk.index),
+				PbrBundle {
+					mesh: meshes.add(mesh),
+					material: material.clone(),
+					..default()
+				},
+			));
+		});
+	}
+	for (entity, overlay, handle) in &overlays {
+		if changed.contains(&overlay.0) {
+			let mesh = fog_mesh(overlay.0, &fog, fog_settings, &noise, &settings);
+			// the bounds Bevy computed for the first mesh would go stale
+			match mesh.compute_aabb() {
+				Some(aabb) => commands.entity(entity).insert(aabb),
+				None => commands.entity(entity).remove::<Aabb>(),
+			};
+			meshes.insert(handle, mesh);
+		}
+	}
+}
+
+/// A hexagon over every tile of the chunk at `index` that isn't visible,
+/// relative to the chunk.
+fn fog_mesh(
+	index: UVec2,
+	fog: &FogOfWar,
+	fog_settings: Fog,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Mesh {
+	let size = settings.chunk_size;
+	let origin = (index * size).as_ivec2();
+	let corners = settings.hex_corners();
+	let mut verts = Vec::new();
+	let mut colors = Vec::new();
+	let mut indices = Vec::new();
+	for z in 0..size {
+		for x in 0..size {
+			let (g_x, g_z) = (origin.x + x as i32, origin.y + z as i32);
+			let coord = HexCoord::from_offset(g_x, g_z);
+			if !settings.contains(coord) {
+				continue;
+			}
+			let alpha = match fog.state(coord) {
+				TileVisibility::Visible => continue,
+				TileVisibility::Seen => fog_settings.seen_alpha,
+				TileVisibility::Unseen => 1.,
+			};
+			let height = sample_height(g_x, g_z, noise, settings) + fog_settings.lift;
+			let center = to_chunk_pos(Vec3::new(x as f32, height, z as f32), origin.y, settings);
+			let idx = verts.len() as u32;
+			verts.push(center);
+			verts.extend(corners.map(|corner| center + corner));
+			let color = fog_settings
+				.color
+				.with_alpha(alpha)
+				.to_linear()
+				.to_f32_array();
+			colors.extend([color; 7]);
+			for i in 0..6 {
+				indices.extend([idx, idx + 1 + i, idx + 1 + (i + 1) % 6]);
+			}
+		}
+	}
+	// flat-top corners are mirrored, like in `finish_mesh`
+	if settings.orientation == HexOrientation::FlatTop {
+		for tri in indices.chunks_exact_mut(3) {
+			tri.swap(1, 2);
+		}
+	}
+	let normals = vec![Vec3::Y; verts.len()];
+	return Mesh::new(
+		PrimitiveTopology::TriangleList,
+		RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+	)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, verts)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+	.with_inserted_indices(Indices::U32(indices));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tiles(settings: &HexGridSettings) -> impl Iterator<Item = HexCoord> {
+		let tiles = settings.map_size * settings.chunk_size;
+		return (0..tiles.x * tiles.y)
+			.map(move |i| HexCoord::from_offset((i % tiles.x) as i32, (i / tiles.x) as i32));
+	}
+
+	#[test]
+	fn reveals_flip_only_the_tiles_in_sight() {
+		let settings = HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 8,
+			..default()
+		};
+		let center = HexCoord::from_offset(8, 8);
+		let mut fog = FogOfWar::default();
+		fog.reveal(center, 3, &settings);
+		let visible = tiles(&settings)
+			.filter(|&coord| fog.state(coord) == TileVisibility::Visible)
+			.collect::<Vec<_>>();
+		assert_eq!(visible.len(), 37);
+		assert!(visible.iter().all(|coord| coord.distance(&center) <= 3));
+		assert!(tiles(&settings).all(|coord| fog.state(coord) != TileVisibility::Seen));
+
+		// the overlays cover every tile but those
+		let noise = settings.noise();
+		let covered = [UVec2::ZERO, UVec2::X, UVec2::Y, UVec2::ONE]
+			.map(|index| fog_mesh(index, &fog, Fog::default(), &noise, &settings).count_vertices())
+			.iter()
+			.sum::<usize>();
+		assert_eq!(covered, (16 * 16 - 37) * 7);
+
+		// looking away leaves them seen
+		fog.set_visible(&HashSet::new(), &settings);
+		let seen = tiles(&settings)
+			.filter(|&coord| fog.state(coord) == TileVisibility::Seen)
+			.count();
+		assert_eq!(seen, 37);
+	}
+}
@@ -0,0 +1,382 @@
+use std::sync::{mpsc, Arc};
+
+use bevy::render::{
+	render_resource::{
+		binding_types::{
+			storage_buffer_read_only_sized, storage_buffer_sized, uniform_buffer_sized,
+		},
+		BindGroupEntries, BindGroupLayoutEntries, BufferDescriptor, BufferInitDescriptor,
+		BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor, Maintain, MapMode,
+		PipelineLayoutDescriptor, RawComputePipelineDescriptor, ShaderModuleDescriptor,
+		ShaderSource, ShaderStages,
+	},
+	renderer::{RenderDevice, RenderQueue},
+};
+
+use noise::permutationtable::{NoiseHasher, PermutationTable};
+
+use super::{
+	biome::{biome_neighbors, tile_biome},
+	height::elevation_bounds,
+	HexGrid, HexGridSettings, HexMap, HexTile, NoiseBlend, NoiseLayerKind, NoiseType, WrapMode,
+};
+
+/// Tiles along each side of a workgroup, as in `heightmap.wgsl`.
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Bytes of 32 bit words, the only fields of the structs in `heightmap.wgsl`.
+fn bytes(words: &[u32]) -> Vec<u8> {
+	return words.iter().flat_map(|word| word.to_le_bytes()).collect();
+}
+
+impl HexGrid {
+	/// The generated height of every tile of the map, row by row along x,
+	/// sampled by a compute shader instead of `sample_height`. Blocks until
+	/// the heights are read back. The shader works in `f32`, so heights only
+	/// match the CPU within float tolerance, and `height_steps` may put a
+	/// tile right on a step one level off.
+	///
+	/// Returns `None` for wrapped maps, layers with a `speed` and heightmap
+	/// images, which the shader doesn't port, and if the readback fails.
+	/// `HexMap::from_gpu` falls back to the CPU then.
+	pub fn gpu_heights(
+		device: &RenderDevice,
+		queue: &RenderQueue,
+		settings: &HexGridSettings,
+	) -> Option<Vec<f32>> {
+		let moving = settings.noise_layers.iter().any(|layer| layer.speed != 0.);
+		if settings.wrap_mode() != WrapMode::None || moving || settings.heightmap.is_some() {
+			return None;
+		}
+		let tiles = settings.map_tiles();
+		let count = (tiles.x * tiles.y) as u64;
+		if count == 0 {
+			return Some(Vec::new());
+		}
+
+		let (low, high) = elevation_bounds(&settings.noise_layers);
+		// the fields of `Params` in order, padded to the 16 byte alignment
+		// of uniforms
+		let params = [
+			tiles.x,
+			tiles.y,
+			settings.noise_layers.len() as u32,
+			match settings.noise_type {
+				NoiseType::Simplex => 0,
+				NoiseType::Ridged => 1,
+				NoiseType::Billow => 2,
+				NoiseType::Flat => 3,
+			},
+			(settings.noise_scale as f32).to_bits(),
+			settings.height_amplitude.to_bits(),
+			settings.height_offset.to_bits(),
+			settings.island_falloff.is_some() as u32,
+			settings.island_falloff.unwrap_or(0.).max(0.).to_bits(),
+			settings.height_steps.is_some() as u32,
+			settings.height_steps.unwrap_or(0),
+			(low as f32).to_bits(),
+			(high as f32).to_bits(),
+			0,
+			0,
+			0,
+		];
+		let mut layers = settings
+			.noise_layers
+			.iter()
+			.flat_map(|layer| {
+				let (rigid, weight_multi) = match layer.kind {
+					NoiseLayerKind::Simple => (0, 0.),
+					NoiseLayerKind::Rigid { weight_multi } => (1, weight_multi),
+				};
+				let blend = match layer.blend {
+					NoiseBlend::Add => 0,
+					NoiseBlend::Multiply => 1,
+					NoiseBlend::Max => 2,
+				};
+				// the fields of `Layer` in order
+				return [
+					rigid,
+					(weight_multi as f32).to_bits(),
+					(layer.base_roughness as f32).to_bits(),
+					(layer.lacunarity as f32).to_bits(),
+					(layer.persistence as f32).to_bits(),
+					(layer.min_value as f32).to_bits(),
+					(layer.strength as f32).to_bits(),
+					layer.octaves as u32,
+					layer.masked as u32,
+					(layer.weight as f32).to_bits(),
+					blend,
+				];
+			})
+			.collect::<Vec<_>>();
+		// the same seeds as `TerrainNoise`, read back out of the table one
+		// entry at a time
+		let mut permutations = settings
+			.noise_layers
+			.iter()
+			.flat_map(|layer| {
+				let table = PermutationTable::new(settings.seed.wrapping_add(layer.seed_offset));
+				return (0..256).map(move |i| table.hash(&[i]) as u32);
+			})
+			.collect::<Vec<_>>();
+		// bindings can't be empty, so maps without layers bind unused ones
+		if layers.is_empty() {
+			layers.resize(11, 0);
+			permutations.push(0);
+		}
+
+		let layout = device.create_bind_group_layout(
+			"heightmap",
+			&BindGroupLayoutEntries::sequential(
+				ShaderStages::COMPUTE,
+				(
+					uniform_buffer_sized(false, None),
+					storage_buffer_read_only_sized(false, None),
+					storage_buffer_read_only_sized(false, None),
+					storage_buffer_sized(false, None),
+				),
+			),
+		);
+		let module = device.create_shader_module(ShaderModuleDescriptor {
+			label: Some("heightmap"),
+			source: ShaderSource::Wgsl(include_str!("heightmap.wgsl").into()),
+		});
+		let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("heightmap"),
+			bind_group_layouts: &[&layout],
+			push_constant_ranges: &[],
+		});
+		let pipeline = device.create_compute_pipeline(&RawComputePipelineDescriptor {
+			label: Some("heightmap"),
+			layout: Some(&pipeline_layout),
+			module: &module,
+			entry_point: "main",
+			compilation_options: Default::default(),
+		});
+
+		let buffer = |label, contents: &[u8], usage| {
+			return device.create_buffer_with_data(&BufferInitDescriptor {
+				label: Some(label),
+				contents,
+				usage,
+			});
+		};
+		let params = buffer("heightmap params", &bytes(&params), BufferUsages::UNIFORM);
+		let layers = buffer("heightmap layers", &bytes(&layers), BufferUsages::STORAGE);
+		let permutations = buffer(
+			"heightmap permutations",
+			&bytes(&permutations),
+			BufferUsages::STORAGE,
+		);
+		let size = count * 4;
+		let heights = device.create_buffer(&BufferDescriptor {
+			label: Some("heightmap heights"),
+			size,
+			usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+		let readback = device.create_buffer(&BufferDescriptor {
+			label: Some("heightmap readback"),
+			size,
+			usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		let bind_group = device.create_bind_group(
+			"heightmap",
+			&layout,
+			&BindGroupEntries::sequential((
+				params.as_entire_binding(),
+				layers.as_entire_binding(),
+				permutations.as_entire_binding(),
+				heights.as_entire_binding(),
+			)),
+		);
+
+		let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+			label: Some("heightmap"),
+		});
+		{
+			let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+				label: Some("heightmap"),
+				timestamp_writes: None,
+			});
+			pass.set_pipeline(&pipeline);
+			pass.set_bind_group(0, &bind_group, &[]);
+			pass.dispatch_workgroups(
+				tiles.x.div_ceil(WORKGROUP_SIZE),
+				tiles.y.div_ceil(WORKGROUP_SIZE),
+				1,
+			);
+		}
+		encoder.copy_buffer_to_buffer(&heights, 0, &readback, 0, size);
+		queue.submit([encoder.finish()]);
+
+		let slice = readback.slice(..);
+		let (sender, receiver) = mpsc::channel();
+		device.map_buffer(&slice, MapMode::Read, move |result| {
+			let _ = sender.send(result);
+		});
+		device.poll(Maintain::Wait);
+		receiver.recv().ok()?.ok()?;
+		let heights = slice
+			.get_mapped_range()
+			.chunks_exact(4)
+			.map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+			.collect();
+		readback.unmap();
+		return Some(heights);
+	}
+}
+
+impl HexMap {
+	/// Like `from_settings` for settings without a map, with the heights
+	/// sampled on the GPU by `HexGrid::gpu_heights`. Biomes are still classed
+	/// on the CPU, from the heights read back. Maps the shader can't
+	/// generate are captured by `from_settings` instead.
+	pub fn from_gpu(
+		device: &RenderDevice,
+		queue: &RenderQueue,
+		settings: &HexGridSettings,
+	) -> Self {
+		let Some(heights) = HexGrid::gpu_heights(device, queue, settings) else {
+			return Self::from_settings(settings);
+		};
+		let size = settings.map_tiles();
+		let heights = Self {
+			size,
+			tiles: heights
+				.into_iter()
+				.map(|height| HexTile {
+					height,
+					biome: String::new(),
+					spans: Vec::new(),
+				})
+				.collect(),
+			captured: Vec::new(),
+		};
+		// cliffs compare the tiles to their neighbors read back, not the noise
+		let baked = HexGridSettings {
+			map: Some(Arc::new(heights.clone())),
+			..settings.clone()
+		};
+		let noise = baked.noise();
+		let mut map = heights;
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			let (x, z) = ((i as u32 % size.x) as i32, (i as u32 / size.x) as i32);
+			let neighbors = biome_neighbors(x, z, &noise, &baked);
+			let biome = tile_biome(x, z, tile.height, &neighbors, &baked);
+			tile.biome = biome.map_or_else(String::new, |biome| biome.name.clone());
+		}
+		return map;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{prelude::*, render::renderer::WgpuWrapper, tasks::block_on};
+
+	use super::*;
+	use crate::hex_grid::{height::sample_height, Heightmap, NoiseLayer};
+
+	/// A device on the first adapter there is, software ones included. Tests
+	/// needing one are ignored, so machines without any report them skipped.
+	fn device() -> (RenderDevice, RenderQueue) {
+		let instance = wgpu::Instance::default();
+		let adapter = block_on(instance.request_adapter(&default())).expect("no GPU adapter");
+		let descriptor = wgpu::DeviceDescriptor {
+			required_limits: adapter.limits(),
+			..default()
+		};
+		let (device, queue) = block_on(adapter.request_device(&descriptor, None)).unwrap();
+		return (
+			RenderDevice::from(device),
+			RenderQueue(Arc::new(WgpuWrapper::new(queue))),
+		);
+	}
+
+	fn small_map() -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::new(3, 2),
+			chunk_size: 8,
+			noise_scale: 40.,
+			height_amplitude: 6.,
+			..default()
+		};
+	}
+
+	#[test]
+	#[ignore = "needs a GPU adapter, run with --ignored"]
+	fn gpu_heights_match_sample_height() {
+		let (device, queue) = device();
+		let mut layers = NoiseLayer::default_terrain();
+		layers[1].seed_offset = 3;
+		layers[2].blend = NoiseBlend::Max;
+		layers[3].blend = NoiseBlend::Multiply;
+		let variants = [
+			small_map(),
+			HexGridSettings {
+				seed: 9,
+				noise_type: NoiseType::Ridged,
+				noise_layers: layers,
+				island_falloff: Some(2.),
+				..small_map()
+			},
+			HexGridSettings {
+				noise_type: NoiseType::Billow,
+				height_offset: -2.,
+				..small_map()
+			},
+			HexGridSettings {
+				noise_type: NoiseType::Flat,
+				height_offset: 1.5,
+				..small_map()
+			},
+		];
+		for settings in variants {
+			let heights = HexGrid::gpu_heights(&device, &queue, &settings).unwrap();
+			let tiles = settings.map_tiles();
+			assert_eq!(heights.len(), (tiles.x * tiles.y) as usize);
+			let noise = settings.noise();
+			for (i, &height) in heights.iter().enumerate() {
+				let (x, z) = ((i as u32 % tiles.x) as i32, (i as u32 / tiles.x) as i32);
+				let expected = sample_height(x, z, &noise, &settings);
+				let tolerance = 1e-3 * (expected.abs() + 1.);
+				assert!(
+					(height - expected).abs() < tolerance,
+					"{x} {z}: {height} {expected}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	#[ignore = "needs a GPU adapter, run with --ignored"]
+	fn unported_settings_fall_back_to_the_cpu() {
+		let (device, queue) = device();
+		let wrapped = HexGridSettings {
+			wrap: WrapMode::Cylinder,
+			..small_map()
+		};
+		assert!(HexGrid::gpu_heights(&device, &queue, &wrapped).is_none());
+		let map = HexMap::from_gpu(&device, &queue, &wrapped);
+		assert_eq!(map, HexMap::from_settings(&wrapped));
+		let image = HexGridSettings {
+			heightmap: Some(Arc::new(Heightmap {
+				size: UVec2::splat(2),
+				values: vec![0., 0.25, 0.5, 1.],
+			})),
+			..small_map()
+		};
+		assert!(HexGrid::gpu_heights(&device, &queue, &image).is_none());
+		let map = HexMap::from_gpu(&device, &queue, &image);
+		assert_eq!(map, HexMap::from_settings(&image));
+
+		let settings = small_map();
+		let map = HexMap::from_gpu(&device, &queue, &settings);
+		assert!(map.is_filled());
+		let cpu = HexMap::from_settings(&settings);
+		for (gpu, cpu) in map.tiles.iter().zip(&cpu.tiles) {
+			assert!((gpu.height - cpu.height).abs() < 1e-3 * (cpu.height.abs() + 1.));
+		}
+	}
+}
@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+
+/// Replaces the fans of the tile tops of a chunk, the only triangles in
+/// `indices` so far, with larger triangles over the runs of neighboring
+/// tiles sharing a height and color, leaving out the tiles not `present`.
+/// The tile vertices stay where walls and skirts expect them, only fewer
+/// triangles reference them.
+///
+/// A row of tiles is a band of rectangles through their middles, with
+/// triangular teeth above and below that interlock with the teeth of the
+/// next row. Each run of equal tiles along a row becomes one rectangle, and
+/// each run of equal teeth between two rows one trapezoid.
+pub(super) fn merge_tile_tops(
+	first_row: i32,
+	chunk_size: u32,
+	verts: &[Vec3],
+	colors: &[Color],
+	present: &[bool],
+	indices: &mut Vec<u32>,
+) {
+	let size = chunk_size as usize;
+	let corner = |(x, z): (usize, usize), i: u32| ((z * size + x) * 7) as u32 + 1 + i;
+	let present = |(x, z): (usize, usize)| present[z * size + x];
+	// runs are either all present or all left out
+	let same = |a: (usize, usize), b: (usize, usize)| {
+		let both = present(a) == present(b);
+		let (a, b) = ((a.1 * size + a.0) * 7, (b.1 * size + b.0) * 7);
+		return both && verts[a].y == verts[b].y && colors[a] == colors[b];
+	};
+	indices.clear();
+
+	for z in 0..size {
+		let mut start = 0;
+		for x in 1..=size {
+			if x < size && same((start, z), (x, z)) {
+				continue;
+			}
+			let (first, last) = ((start, z), (x - 1, z));
+			start = x;
+			if !present(first) {
+				continue;
+			}
+			push_quad(
+				[
+					corner(first, 4),
+					corner(first, 5),
+					corner(last, 1),
+					corner(last, 2),
+				],
+				indices,
+			);
+		}
+	}
+	// the outer teeth have no neighboring row in the chunk to merge with
+	for x in 0..size {
+		let (bottom, top) = ((x, 0), (x, size - 1));
+		if present(bottom) {
+			indices.extend([corner(bottom, 2), corner(bottom, 3), corner(bottom, 4)]);
+		}
+		if present(top) {
+			indices.extend([corner(top, 5), corner(top, 0), corner(top, 1)]);
+		}
+	}
+
+	for z in 0..size - 1 {
+		// odd rows are shifted half a tile along x, so the teeth above row
+		// `z` and below row `z + 1` alternate
+		let shift = |z: usize| (first_row + z as i32).rem_euclid(2) as usize;
+		let mut teeth = (0..size)
+			.flat_map(|x| {
+				[
+					(2 * x + shift(z), true, x),
+					(2 * x + shift(z + 1), false, x),
+				]
+			})
+			.collect::<Vec<_>>();
+		teeth.sort_by_key(|tooth| tooth.0);
+		let tile = |(_, up, x): (usize, bool, usize)| if up { (x, z) } else { (x, z + 1) };
+
+		let mut start = 0;
+		for i in 1..=teeth.len() {
+			if i < teeth.len() && same(tile(teeth[start]), tile(teeth[i])) {
+				continue;
+			}
+			let (first, last) = (teeth[start], teeth[i - 1]);
+			let (first_up, last_up) = (first.1, last.1);
+			let (first, last) = (tile(first), tile(last));
+			let single = start == i - 1;
+			start = i;
+			if !present(first) {
+				continue;
+			}
+			if single {
+				match first_up {
+					true => indices.extend([corner(first, 5), corner(first, 0), corner(first, 1)]),
+					false => indices.extend([corner(first, 2), corner(first, 3), corner(first, 4)]),
+				}
+			} else {
+				let (bottom_left, top_left) = match first_up {
+					true => (corner(first, 5), corner(first, 0)),
+					false => (corner(first, 3), corner(first, 4)),
+				};
+				let (top_right, bottom_right) = match last_up {
+					true => (corner(last, 0), corner(last, 1)),
+					false => (corner(last, 2), corner(last, 3)),
+				};
+				push_quad([bottom_left, top_left, top_right, bottom_right], indices);
+			}
+		}
+	}
+}
+
+/// Two triangles through `quad`, its corners in the order of a tile's.
+fn push_quad(quad: [u32; 4], indices: &mut Vec<u32>) {
+	indices.extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::{ChunkBuffers, HexGrid, HexGridSettings, NoiseType, NormalMode};
+
+	/// Area of every triangle seen from above, none of them facing down.
+	fn top_area(buffers: &ChunkBuffers) -> f32 {
+		return buffers
+			.indices
+			.chunks_exact(3)
+			.map(|tri| {
+				let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(buffers.positions[tri[i] as usize]));
+				let area = (b - a).cross(c - a).y * 0.5;
+				assert!(area > 0.);
+				return area;
+			})
+			.sum();
+	}
+
+	#[test]
+	fn flat_chunks_merge_into_few_triangles() {
+		let plain = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 8,
+			noise_type: NoiseType::Flat,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+		let merged = HexGridSettings {
+			merge_flat_tops: true,
+			..plain.clone()
+		};
+		let fans = HexGrid::chunk_buffers(UVec2::ZERO, &plain);
+		let buffers = HexGrid::chunk_buffers(UVec2::ZERO, &merged);
+		assert_eq!(fans.indices.len(), 64 * 18);
+		assert!(buffers.indices.len() * 8 < fans.indices.len());
+		assert_eq!(buffers.positions, fans.positions);
+		let area = top_area(&fans);
+		assert!((top_area(&buffers) - area).abs() < area * 1e-5);
+	}
+}
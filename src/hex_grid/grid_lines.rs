@@ -0,0 +1,125 @@
+use bevy::{
+	prelude::*,
+	render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat},
+	},
+};
+
+use super::HexGridSettings;
+
+/// Outlines every tile top by texturing it with a hexagon whose border is
+/// drawn in `color`. The chunks keep their geometry, the lines only tint the
+/// vertex colors through the material's base color texture.
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct GridLines {
+	pub color: Color,
+	/// Width of the lines on each side of a border, as a fraction of the
+	/// distance from a tile's center to its edges.
+	pub width: f32,
+	/// Width and height of the generated texture in pixels.
+	pub resolution: u32,
+}
+
+impl Default for GridLines {
+	fn default() -> Self {
+		return Self {
+			color: Color::srgba(0., 0., 0., 0.6),
+			width: 0.04,
+			resolution: 256,
+		};
+	}
+}
+
+impl GridLines {
+	/// The hexagon every tile top maps its UVs onto, white inside and
+	/// fading into `color` over a pixel at the border.
+	pub fn image(&self, settings: &HexGridSettings) -> Image {
+		let size = self.resolution.max(1);
+		// tile tops map their corners half a texture away from its center
+		let scale = 0.5 / settings.outer_radius;
+		let corners = settings.hex_corners().map(|corner| corner.xz() * scale);
+		let edges = (0..6)
+			.map(|i| (corners[i] + corners[(i + 1) % 6]) * 0.5)
+			.collect::<Vec<_>>();
+		let apothem = edges[0].length();
+		let line = self.width * apothem;
+		let pixel = 1. / size as f32;
+		let mut data = Vec::with_capacity((size * size * 4) as usize);
+		for row in 0..size {
+			for column in 0..size {
+				let uv = (Vec2::new(column as f32, row as f32) + 0.5) * pixel - 0.5;
+				// distance to the nearest edge, negative outside the hexagon
+				let inside = edges
+					.iter()
+					.map(|edge| apothem - uv.dot(*edge / apothem))
+					.fold(f32::INFINITY, f32::min);
+				let coverage = ((line - inside) / pixel + 0.5).clamp(0., 1.) * self.color.alpha();
+				let color = Srgba::WHITE
+					.to_vec4()
+					.lerp(self.color.to_srgba().to_vec4(), coverage);
+				data.extend(Srgba::rgb(color.x, color.y, color.z).to_u8_array());
+			}
+		}
+		return Image::new(
+			Extent3d {
+				width: size,
+				height: size,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			data,
+			TextureFormat::Rgba8UnormSrgb,
+			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::{HexGrid, NormalMode};
+
+	#[test]
+	fn grid_lines_keep_the_geometry() {
+		for normal_mode in [NormalMode::Flat, NormalMode::Smooth] {
+			let plain = HexGridSettings {
+				map_size: UVec2::splat(2),
+				chunk_size: 4,
+				height_amplitude: 4.,
+				normal_mode,
+				..default()
+			};
+			let lined = HexGridSettings {
+				grid_lines: Some(GridLines::default()),
+				..plain.clone()
+			};
+			for index in [UVec2::ZERO, UVec2::ONE] {
+				let before = HexGrid::chunk_buffers(index, &plain);
+				let after = HexGrid::chunk_buffers(index, &lined);
+				assert_eq!(after.positions, before.positions);
+				assert_eq!(after.indices, before.indices);
+				assert_eq!(after.colors, before.colors);
+				assert_eq!(after.uvs.len(), before.uvs.len());
+			}
+		}
+	}
+
+	#[test]
+	fn tile_tops_map_onto_the_hexagon() {
+		let settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 4,
+			normal_mode: NormalMode::Smooth,
+			grid_lines: Some(GridLines::default()),
+			..default()
+		};
+		let uvs = HexGrid::chunk_buffers(UVec2::ZERO, &settings).uvs;
+		for tile in uvs[..16 * 7].chunks_exact(7) {
+			assert_eq!(tile[0], [0.5, 0.5]);
+			assert!(tile[1..]
+				.iter()
+				.all(|uv| ((Vec2::from(*uv) - 0.5).length() - 0.5).abs() < 1e-5));
+		}
+	}
+}
@@ -0,0 +1,978 @@
+use std::{f64::consts::TAU, sync::Arc};
+
+use bevy::{math::DVec2, prelude::*};
+
+use noise::{NoiseFn, OpenSimplex, SuperSimplex};
+
+use super::{Chunk, ChunkTask, DirtyChunks, Heightmap, HexCoord, HexGridSettings, WrapMode};
+
+const SEA_LEVEL: f64 = 5.;
+
+/// One fractal noise layer, summed over `octaves` samples of increasing
+/// frequency and decreasing amplitude.
+#[derive(Reflect, Clone, Debug)]
+pub struct NoiseLayer {
+	pub kind: NoiseLayerKind,
+	/// Frequency of the first octave.
+	pub base_roughness: f64,
+	/// Frequency multiplier between octaves.
+	pub lacunarity: f64,
+	/// Amplitude multiplier between octaves.
+	pub persistence: f64,
+	pub min_value: f64,
+	pub strength: f64,
+	pub octaves: usize,
+	/// Only applies where the first layer rises above sea level.
+	pub masked: bool,
+	/// Scales the layer before it is blended.
+	pub weight: f64,
+	/// Added to the map seed to seed this layer's own noise. Layers sharing
+	/// an offset sample the same noise.
+	pub seed_offset: u32,
+	/// How the layer combines with the layers before it. The first layer
+	/// always starts the elevation off as is.
+	pub blend: NoiseBlend,
+	/// Moves the layer through another noise dimension at this rate per
+	/// second of `HexGridSettings::terrain_time`. 0 keeps it still.
+	pub speed: f64,
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Debug, Default)]
+pub enum NoiseBlend {
+	#[default]
+	Add,
+	/// Scales everything below, so a low frequency layer near zero flattens
+	/// the terrain into oceans between continents.
+	Multiply,
+	Max,
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Debug)]
+pub enum NoiseLayerKind {
+	Simple,
+	/// Ridged noise, each octave weighted by the one before it.
+	Rigid {
+		weight_multi: f64,
+	},
+}
+
+/// How each octave of a `NoiseLayerKind::Simple` layer reshapes the raw noise,
+/// mapped into 0 to 1 either way.
+#[derive(Reflect, Clone, Copy, PartialEq, Debug, Default)]
+pub enum NoiseType {
+	#[default]
+	Simplex,
+	/// Folded around zero and inverted, peaking in sharp ridges where the noise
+	/// crosses zero.
+	Ridged,
+	/// Folded around zero, for rounded hills meeting in creases.
+	Billow,
+	/// Ignores the noise layers, leaving every tile at `height_offset`.
+	Flat,
+}
+
+impl NoiseType {
+	fn shape(self, value: f64) -> f64 {
+		return match self {
+			NoiseType::Simplex => (value + 1.) * 0.5,
+			NoiseType::Ridged => 1. - value.abs(),
+			NoiseType::Billow => value.abs(),
+			NoiseType::Flat => 0.,
+		};
+	}
+}
+
+impl NoiseLayer {
+	pub fn default_terrain() -> Vec<NoiseLayer> {
+		return vec![
+			NoiseLayer {
+				kind: NoiseLayerKind::Simple,
+				base_roughness: 2.14,
+				lacunarity: 0.87,
+				persistence: 0.77,
+				min_value: -0.2,
+				strength: 2.93,
+				octaves: 4,
+				masked: false,
+				weight: 1.,
+				seed_offset: 0,
+				blend: NoiseBlend::Add,
+				speed: 0.,
+			},
+			NoiseLayer {
+				kind: NoiseLayerKind::Simple,
+				base_roughness: 2.85,
+				lacunarity: 2.,
+				persistence: 1.,
+				min_value: 0.,
+				strength: -0.23,
+				octaves: 4,
+				masked: false,
+				weight: 1.,
+				seed_offset: 0,
+				blend: NoiseBlend::Add,
+				speed: 0.,
+			},
+			NoiseLayer {
+				kind: NoiseLayerKind::Rigid { weight_multi: 0.35 },
+				base_roughness: 2.6,
+				lacunarity: 4.,
+				persistence: 1.57,
+				min_value: 0.,
+				strength: 10.44,
+				octaves: 4,
+				masked: true,
+				weight: 1.,
+				seed_offset: 0,
+				blend: NoiseBlend::Add,
+				speed: 0.,
+			},
+			NoiseLayer {
+				kind: NoiseLayerKind::Rigid { weight_multi: 4.57 },
+				base_roughness: 3.87,
+				lacunarity: 5.8,
+				persistence: 0.,
+				min_value: 0.,
+				strength: -1.,
+				octaves: 3,
+				masked: true,
+				weight: 1.,
+				seed_offset: 0,
+				blend: NoiseBlend::Add,
+				speed: 0.,
+			},
+		];
+	}
+
+	/// Lowest and highest value the layer can produce, before masking.
+	fn bounds(&self) -> (f64, f64) {
+		let mut amp: f64 = 1.;
+		let (mut low, mut high) = (0., 0.);
+		for _ in 0..self.octaves {
+			low += amp.min(0.);
+			high += amp.max(0.);
+			amp *= self.persistence;
+		}
+		let low = (low - self.min_value) * self.strength * self.weight;
+		let high = (high - self.min_value) * self.strength * self.weight;
+		return (low.min(high), low.max(high));
+	}
+
+	fn sample<const N: usize>(
+		&self,
+		noise: &impl NoiseFn<f64, N>,
+		noise_type: NoiseType,
+		point: [f64; N],
+	) -> f64 {
+		let value = match self.kind {
+			NoiseLayerKind::Simple => sample_layer(
+				noise,
+				noise_type,
+				point,
+				self.base_roughness,
+				self.lacunarity,
+				self.persistence,
+				self.min_value,
+				self.strength,
+				self.octaves,
+			),
+			NoiseLayerKind::Rigid { weight_multi } => sample_layer_rigid(
+				noise,
+				point,
+				self.base_roughness,
+				self.lacunarity,
+				self.persistence,
+				self.min_value,
+				self.strength,
+				weight_multi,
+				self.octaves,
+			),
+		};
+		return value * self.weight;
+	}
+}
+
+/// The noise of every layer of `noise_layers`, seeded by the map seed plus
+/// the layer's `seed_offset`. Built by `HexGridSettings::noise`.
+#[derive(Clone, Debug)]
+pub struct TerrainNoise {
+	simplex: Arc<[SuperSimplex]>,
+	/// The same seeds for tori, as SuperSimplex stops at three dimensions.
+	open: Arc<[OpenSimplex]>,
+}
+
+impl TerrainNoise {
+	pub(super) fn new(seed: u32, layers: &[NoiseLayer]) -> Self {
+		let seeds = layers
+			.iter()
+			.map(|layer| seed.wrapping_add(layer.seed_offset));
+		return Self {
+			simplex: seeds.clone().map(SuperSimplex::new).collect(),
+			open: seeds.map(OpenSimplex::new).collect(),
+		};
+	}
+}
+
+pub(super) fn sample_height(
+	x: i32,
+	y: i32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> f32 {
+	let IVec2 { x, y } = settings.wrap_offset(IVec2::new(x, y));
+	if let Some(map) = settings.map.as_ref().filter(|map| map.is_captured(x, y)) {
+		return map.height(x, y);
+	}
+	return generated_height(x, y, noise, settings);
+}
+
+/// Height of tile (`x`, `y`) of the map generated from the heightmap image
+/// or the noise, ignoring `HexGridSettings::map`.
+pub(super) fn generated_height(
+	x: i32,
+	y: i32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> f32 {
+	if let Some(heightmap) = &settings.heightmap {
+		return image_height(IVec2::new(x, y), heightmap, settings);
+	}
+	if settings.noise_type == NoiseType::Flat {
+		return settings.height_offset;
+	}
+	return noise_height(DVec2::new(x as f64, y as f64), noise, settings);
+}
+
+/// Height of tile `offset` from the pixels of `heightmap` over its center.
+fn image_height(offset: IVec2, heightmap: &Heightmap, settings: &HexGridSettings) -> f32 {
+	let tiles = settings.map_tiles().as_vec2().max(Vec2::ONE);
+	// the image runs from the last row at the top to the first
+	let uv = (offset.as_vec2() + 0.5) / tiles;
+	let mut value = heightmap.sample(Vec2::new(uv.x, 1. - uv.y)) as f64;
+	if let Some(steps) = settings.height_steps {
+		value = quantize(value, (0., 1.), steps);
+	}
+	return value as f32 * settings.height_amplitude + settings.height_offset;
+}
+
+/// Height at world `xz` between tile centers, through every tile center at
+/// its `sample_height`. Noise maps sample the noise at the offset coordinates
+/// blended like the heights in `HexGrid::height_at_world`, so the terrain
+/// keeps its detail between centers. Stored, image and flat maps blend the
+/// heights.
+pub(super) fn sample_height_at(xz: Vec2, noise: &TerrainNoise, settings: &HexGridSettings) -> f32 {
+	let (tiles, weights) = center_triangle(xz, settings);
+	let blended = settings.map.is_some() || settings.heightmap.is_some();
+	if blended || settings.noise_type == NoiseType::Flat {
+		return (0..3)
+			.map(|i| {
+				let offset = tiles[i].to_offset();
+				return weights[i] * sample_height(offset.x, offset.y, noise, settings);
+			})
+			.sum();
+	}
+	let offset = (0..3)
+		.map(|i| tiles[i].to_offset().as_dvec2() * weights[i] as f64)
+		.sum::<DVec2>();
+	// like `wrap_offset`, for points between tiles
+	let size = settings.map_tiles().as_dvec2().max(DVec2::ONE);
+	let offset = match settings.wrap_mode() {
+		WrapMode::None => offset,
+		WrapMode::Cylinder => DVec2::new(offset.x.rem_euclid(size.x), offset.y),
+		WrapMode::Torus => DVec2::new(offset.x.rem_euclid(size.x), offset.y.rem_euclid(size.y)),
+	};
+	return noise_height(offset, noise, settings);
+}
+
+/// The tile containing world `xz` and the two neighbors whose centers span
+/// the triangle it falls in, with the barycentric weight of each. Neighbors
+/// are tried in `HexDirection::ALL` order, so points on a shared edge always
+/// land in the same triangle.
+pub(super) fn center_triangle(xz: Vec2, settings: &HexGridSettings) -> ([HexCoord; 3], [f32; 3]) {
+	let tile = HexCoord::from_world(xz.extend(0.).xzy(), settings);
+	let a = tile.to_world(settings).xz();
+	let neighbors = tile.neighbors();
+	for i in 0..6 {
+		let (n1, n2) = (neighbors[i], neighbors[(i + 1) % 6]);
+		let (b, c) = (n1.to_world(settings).xz(), n2.to_world(settings).xz());
+		let area = (b - a).perp_dot(c - a);
+		let w1 = (c - b).perp_dot(xz - b) / area;
+		let w2 = (a - c).perp_dot(xz - c) / area;
+		let w3 = 1. - w1 - w2;
+		if w1.min(w2).min(w3) >= -1e-5 {
+			return ([tile, n1, n2], [w1, w2, w3]);
+		}
+	}
+	// the tile lies within its six triangles, so only rounding gets here
+	return ([tile; 3], [1., 0., 0.]);
+}
+
+/// Height from the noise layers at fractional offset coordinates.
+fn noise_height(pos: DVec2, noise: &TerrainNoise, settings: &HexGridSettings) -> f32 {
+	// wrapped axes walk around a circle as long as the map, so the first and
+	// last tiles along them sample neighboring points of the noise
+	let tiles = settings.map_tiles().as_dvec2();
+	let DVec2 { x, y } = pos;
+	let circle = |t: f64, length: f64| {
+		let (sin, cos) = (t / length * TAU).sin_cos();
+		return [cos, sin].map(|v| v * length / TAU);
+	};
+	let scale = settings.noise_scale;
+	let time = settings.terrain_time;
+	let mut elevation = elevation_at(settings, |i, layer| {
+		let (simplex, open) = (&noise.simplex[i], &noise.open[i]);
+		let noise_type = settings.noise_type;
+		// moving layers slide through one more dimension, except on tori,
+		// which already take up all four and slide along two of them instead
+		let w = layer.speed * time;
+		let moving = layer.speed != 0.;
+		return match settings.wrap_mode() {
+			WrapMode::None if moving => {
+				layer.sample(simplex, noise_type, [x / scale, y / scale, w])
+			}
+			WrapMode::None => layer.sample(simplex, noise_type, [x / scale, y / scale]),
+			WrapMode::Cylinder => {
+				let [a, b] = circle(x, tiles.x).map(|v| v / scale);
+				match moving {
+					true => layer.sample(open, noise_type, [a, y / scale, b, w]),
+					false => layer.sample(simplex, noise_type, [a, y / scale, b]),
+				}
+			}
+			WrapMode::Torus => {
+				let [a, b] = circle(x, tiles.x).map(|v| v / scale);
+				let [c, d] = circle(y, tiles.y).map(|v| v / scale);
+				layer.sample(open, noise_type, [a + w, b, c, d + w])
+			}
+		};
+	});
+
+	if let Some(exponent) = settings.island_falloff {
+		// distance to the nearest edge, with each axis normalized so
+		// rectangular maps fall off on every side alike
+		let centered = (DVec2::new(x, y) + 0.5) / tiles * 2. - 1.;
+		let distance = centered.abs().max_element().min(1.);
+		let falloff = 1. - distance.powf(exponent.max(0.) as f64);
+		let (low, _) = elevation_bounds(&settings.noise_layers);
+		elevation = low + (elevation - low) * falloff;
+	}
+
+	if let Some(steps) = settings.height_steps {
+		elevation = quantize(elevation, elevation_bounds(&settings.noise_layers), steps);
+	}
+
+	return elevation as f32 * settings.height_amplitude + settings.height_offset;
+}
+
+/// The noise layers blended in order, `sample` giving the value of the
+/// layer at each index.
+fn elevation_at(
+	settings: &HexGridSettings,
+	mut sample: impl FnMut(usize, &NoiseLayer) -> f64,
+) -> f64 {
+	let mut elevation: f64 = 0.;
+
+	let mut first_layer = None;
+	for (i, layer) in settings.noise_layers.iter().enumerate() {
+		let value = sample(i, layer);
+		let Some(first_layer) = first_layer else {
+			first_layer = Some(value);
+			elevation = value;
+			continue;
+		};
+		let value = match layer.masked {
+			true => mask(first_layer, value),
+			false => value,
+		};
+		elevation = match layer.blend {
+			NoiseBlend::Add => elevation + value,
+			NoiseBlend::Multiply => elevation * value,
+			NoiseBlend::Max => elevation.max(value),
+		};
+	}
+	return elevation;
+}
+
+/// Brightness of the top of a tile at `height`, dimmed by up to `strength`
+/// the more its `neighbors` on the map rise above it. Tiles off the map
+/// count as open sky.
+pub(super) fn ambient_light(
+	height: f32,
+	neighbors: &[f32],
+	strength: f32,
+	settings: &HexGridSettings,
+) -> f32 {
+	let width = settings.inner_radius() * 2.;
+	let occlusion = neighbors
+		.iter()
+		.map(|neighbor| {
+			let rise = (neighbor - height).max(0.);
+			// approaches 1 as the neighbor becomes a sheer wall
+			return rise / (rise + width);
+		})
+		.sum::<f32>();
+	return 1. - strength.clamp(0., 1.) * occlusion / 6.;
+}
+
+/// Lowest and highest tile height the settings can produce.
+pub(super) fn height_bounds(settings: &HexGridSettings) -> (f32, f32) {
+	let (low, high) = elevation_bounds(&settings.noise_layers);
+	let low = low as f32 * settings.height_amplitude + settings.height_offset;
+	let high = high as f32 * settings.height_amplitude + settings.height_offset;
+	let generated = match settings.noise_type {
+		NoiseType::Flat => (settings.height_offset, settings.height_offset),
+		_ => (low.min(high), low.max(high)),
+	};
+	// images span the whole amplitude from black to white
+	let top = settings.height_offset + settings.height_amplitude;
+	let generated = match settings.heightmap {
+		Some(_) => (
+			settings.height_offset.min(top),
+			settings.height_offset.max(top),
+		),
+		None => generated,
+	};
+	let Some(map) = &settings.map else {
+		return generated;
+	};
+	let stored = map.height_bounds();
+	if map.is_filled() {
+		return stored.unwrap_or((0., 0.));
+	}
+	// tiles that aren't captured yet still follow the noise
+	return stored.map_or(generated, |(low, high)| {
+		(low.min(generated.0), high.max(generated.1))
+	});
+}
+
+pub(super) fn elevation_bounds(layers: &[NoiseLayer]) -> (f64, f64) {
+	let Some(first) = layers.first() else {
+		return (0., 0.);
+	};
+	let max_mask = (first.bounds().1 - SEA_LEVEL).max(0.);
+	let (mut low, mut high) = first.bounds();
+	for layer in &layers[1..] {
+		let (mut layer_low, mut layer_high) = layer.bounds();
+		if layer.masked {
+			layer_low = (layer_low * max_mask).min(0.);
+			layer_high = (layer_high * max_mask).max(0.);
+		}
+		(low, high) = match layer.blend {
+			NoiseBlend::Add => (low + layer_low, high + layer_high),
+			NoiseBlend::Multiply => {
+				let products = [
+					low * layer_low,
+					low * layer_high,
+					high * layer_low,
+					high * layer_high,
+				];
+				let low = products.into_iter().fold(f64::INFINITY, f64::min);
+				let high = products.into_iter().fold(f64::NEG_INFINITY, f64::max);
+				(low, high)
+			}
+			NoiseBlend::Max => (low.max(layer_low), high.max(layer_high)),
+		};
+	}
+	return (low, high);
+}
+
+/// Buckets `value` into `steps` levels spread evenly from `low` to `high`.
+fn quantize(value: f64, (low, high): (f64, f64), steps: u32) -> f64 {
+	if steps < 2 || high <= low {
+		return low;
+	}
+	let t = ((value - low) / (high - low)).clamp(0., 1.);
+	let level = (t * steps as f64).floor().min(steps as f64 - 1.);
+	return low + level / (steps - 1) as f64 * (high - low);
+}
+
+fn mask(first_layer: f64, value: f64) -> f64 {
+	let mask = (first_layer - SEA_LEVEL).max(0.);
+	return value * mask;
+}
+
+fn sample_layer<const N: usize>(
+	noise: &impl NoiseFn<f64, N>,
+	noise_type: NoiseType,
+	point: [f64; N],
+	base_roughness: f64,
+	lacunarity: f64,
+	persistence: f64,
+	min_value: f64,
+	strength: f64,
+	octaves: usize,
+) -> f64 {
+	let mut freq: f64 = base_roughness;
+	let mut amp: f64 = 1.;
+	let mut value = 0.;
+
+	for _ in 0..octaves {
+		let v = noise.get(point.map(|v| v * freq));
+		value += noise_type.shape(v) * amp;
+		freq *= lacunarity;
+		amp *= persistence;
+	}
+	value -= min_value;
+	return value * strength;
+}
+
+fn sample_layer_rigid<const N: usize>(
+	noise: &impl NoiseFn<f64, N>,
+	point: [f64; N],
+	base_roughness: f64,
+	lacunarity: f64,
+	persistence: f64,
+	min_value: f64,
+	strength: f64,
+	weight_multi: f64,
+	octaves: usize,
+) -> f64 {
+	let mut freq: f64 = base_roughness;
+	let mut amp: f64 = 1.;
+	let mut value = 0.;
+	let mut weight = 1.;
+	for _ in 0..octaves {
+		let mut v = 1. - noise.get(point.map(|v| v * freq)).abs();
+		v *= v;
+		v *= weight;
+		weight = v * weight_multi;
+		weight = weight.clamp(0., 1.);
+		value += v * amp;
+		freq *= lacunarity;
+		amp *= persistence;
+	}
+	value -= min_value;
+	return value * strength;
+}
+
+/// Advances `HexGridSettings::terrain_time` and rebuilds every chunk that
+/// isn't still building, while `animate_terrain` is set and a layer moves.
+pub(super) fn animate_terrain(
+	mut settings: ResMut<HexGridSettings>,
+	mut dirty: ResMut<DirtyChunks>,
+	chunks: Query<&Chunk, Without<ChunkTask>>,
+	time: Res<Time>,
+) {
+	let moving = settings.noise_layers.iter().any(|layer| layer.speed != 0.);
+	let still =
+		settings.heightmap.is_some() || settings.map.as_ref().is_some_and(|map| map.is_filled());
+	if !settings.animate_terrain || !moving || still {
+		return;
+	}
+	// a full regeneration every frame would tear down the chunks
+	settings.bypass_change_detection().terrain_time += time.delta_seconds_f64();
+	// chunks still building pick up the new time once they are done
+	dirty.extend(chunks.iter().map(|chunk| chunk.index));
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bevy::{ecs::system::RunSystemOnce, utils::Duration};
+
+	use super::*;
+	use crate::hex_grid::{biome::neighbor_heights, HexGrid, HexMap};
+
+	fn layer(octaves: usize) -> NoiseLayer {
+		return NoiseLayer {
+			kind: NoiseLayerKind::Simple,
+			base_roughness: 2.,
+			lacunarity: 2.,
+			persistence: 0.5,
+			min_value: 0.,
+			strength: 1.,
+			octaves,
+			masked: false,
+			weight: 1.,
+			blend: NoiseBlend::Add,
+			seed_offset: 0,
+			speed: 0.,
+		};
+	}
+
+	/// Samples of `layer` over a grid of points spanning a few of its
+	/// features.
+	fn samples(layer: &NoiseLayer, noise_type: NoiseType) -> Vec<f64> {
+		let noise = SuperSimplex::new(3);
+		return (0..64 * 64)
+			.map(|i| {
+				let (x, z) = ((i % 64) as f64 / 16., (i / 64) as f64 / 16.);
+				return layer.sample(&noise, noise_type, [x, z]);
+			})
+			.collect();
+	}
+
+	fn variance(values: &[f64]) -> f64 {
+		let mean = values.iter().sum::<f64>() / values.len() as f64;
+		return values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+	}
+
+	#[test]
+	fn octaves_stay_within_their_amplitudes() {
+		for octaves in 1..=6 {
+			let layer = layer(octaves);
+			let (low, high) = layer.bounds();
+			// 1 + 1/2 + 1/4 + ... for values shaped into 0 to 1
+			let sum = (1. - 0.5f64.powi(octaves as i32)) / 0.5;
+			assert!((high - sum).abs() < 1e-9 && low == 0.);
+			for value in samples(&layer, NoiseType::Simplex) {
+				assert!(
+					(low..=high).contains(&value),
+					"{value} outside {low}..{high}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn more_octaves_add_variance() {
+		let variances =
+			[1, 2, 4].map(|octaves| variance(&samples(&layer(octaves), NoiseType::Simplex)));
+		assert!(
+			variances.windows(2).all(|pair| pair[0] < pair[1]),
+			"{variances:?}"
+		);
+	}
+
+	/// Heights of every tile of a map of one plain layer with varied terrain
+	/// over a few chunks.
+	fn tile_heights(settings: &HexGridSettings) -> Vec<f32> {
+		let noise = settings.noise();
+		let tiles = (settings.map_size * settings.chunk_size).as_ivec2();
+		return (0..tiles.x * tiles.y)
+			.map(|i| sample_height(i % tiles.x, i / tiles.x, &noise, settings))
+			.collect();
+	}
+
+	fn hills() -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::splat(4),
+			chunk_size: 8,
+			noise_scale: 20.,
+			noise_layers: vec![layer(1)],
+			height_amplitude: 10.,
+			..default()
+		};
+	}
+
+	fn distinct(heights: &[f32]) -> usize {
+		let mut heights = heights.to_vec();
+		heights.sort_by(f32::total_cmp);
+		heights.dedup();
+		return heights.len();
+	}
+
+	#[test]
+	fn height_steps_snap_to_levels() {
+		let settings = HexGridSettings {
+			height_steps: Some(4),
+			..hills()
+		};
+		let heights = tile_heights(&settings);
+		assert_eq!(distinct(&heights), 4);
+		// evenly spaced from the lowest possible height to the highest
+		let (low, high) = elevation_bounds(&settings.noise_layers);
+		let scale = |value: f64| value as f32 * settings.height_amplitude;
+		let (low, high) = (scale(low), scale(high));
+		for height in heights {
+			let level = (height - low) / (high - low) * 3.;
+			assert!((level - level.round()).abs() < 1e-4, "{height}");
+		}
+		let continuous = tile_heights(&hills());
+		assert!(distinct(&continuous) > continuous.len() / 2);
+	}
+
+	/// A level chunk at height 6 with a pit sunk to 1 at (2, 2) and a peak
+	/// raised to 12 at (5, 5).
+	fn pit_and_peak() -> HexGridSettings {
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 8,
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = match i {
+				18 => 1.,
+				45 => 12.,
+				_ => 6.,
+			};
+		}
+		settings.map = Some(Arc::new(map));
+		return settings;
+	}
+
+	#[test]
+	fn pits_get_darker_than_peaks() {
+		let settings = pit_and_peak();
+		let noise = settings.noise();
+		let light = |x, z| {
+			let height = sample_height(x, z, &noise, &settings);
+			let neighbors = neighbor_heights(x, z, &noise, &settings);
+			return ambient_light(height, &neighbors, 0.8, &settings);
+		};
+		let (pit, peak, level) = (light(2, 2), light(5, 5), light(0, 6));
+		assert_eq!(peak, 1.);
+		assert_eq!(level, 1.);
+		assert!(pit < 0.5, "{pit}");
+		// the level tiles next to the peak sit in its shadow
+		assert!(light(4, 5) < 1. && light(4, 5) > pit);
+	}
+
+	/// Mean height step between tiles `a` and `b` of every row, and the
+	/// variances of the columns in the middle half of the map and outside it.
+	fn wrapped_terrain(wrap: WrapMode, a: i32, b: i32) -> (f32, f64, f64) {
+		let settings = HexGridSettings {
+			wrap,
+			map_size: UVec2::splat(8),
+			noise_scale: 8.,
+			..hills()
+		};
+		let heights = tile_heights(&settings);
+		let tiles = settings.map_tiles().as_ivec2();
+		let at = |x: i32, z: i32| heights[(z * tiles.x + x) as usize];
+		let step = (0..tiles.y)
+			.map(|z| (at(a, z) - at(b, z)).abs())
+			.sum::<f32>()
+			/ tiles.y as f32;
+		let (mut middle, mut outside) = (Vec::new(), Vec::new());
+		for z in 0..tiles.y {
+			for x in 0..tiles.x {
+				let band = match (x - tiles.x / 4) * 2 < tiles.x && x >= tiles.x / 4 {
+					true => &mut middle,
+					false => &mut outside,
+				};
+				band.push(at(x, z) as f64);
+			}
+		}
+		return (step, variance(&middle), variance(&outside));
+	}
+
+	#[test]
+	fn wrapped_seams_look_like_any_other_step() {
+		for wrap in [WrapMode::Cylinder, WrapMode::Torus] {
+			let (seam, middle, outside) = wrapped_terrain(wrap, 0, 63);
+			let (step, ..) = wrapped_terrain(wrap, 31, 32);
+			assert!(seam < step * 1.5, "{wrap:?}: seam {seam}, step {step}");
+			// blending two copies of the noise would flatten the middle
+			assert!(
+				middle > outside * 0.7 && middle < outside * 1.5,
+				"{wrap:?}: {middle} in the middle, {outside} outside"
+			);
+		}
+	}
+
+	#[test]
+	fn odd_tori_only_wrap_along_x() {
+		let settings = HexGridSettings {
+			wrap: WrapMode::Torus,
+			map_size: UVec2::new(2, 1),
+			chunk_size: 5,
+			..default()
+		};
+		assert_eq!(settings.wrap_mode(), WrapMode::Cylinder);
+		assert_eq!(settings.wrap_offset(IVec2::new(-1, -1)), IVec2::new(9, -1));
+		let even = HexGridSettings {
+			map_size: UVec2::new(2, 2),
+			..settings
+		};
+		assert_eq!(even.wrap_mode(), WrapMode::Torus);
+		assert_eq!(even.wrap_offset(IVec2::new(-1, -1)), IVec2::new(9, 9));
+	}
+
+	#[test]
+	fn layers_draw_on_their_own_seed() {
+		let heights = |offsets: &[u32]| {
+			let noise_layers = offsets
+				.iter()
+				.map(|&seed_offset| NoiseLayer {
+					seed_offset,
+					..layer(2)
+				})
+				.collect();
+			return tile_heights(&HexGridSettings {
+				noise_layers,
+				height_offset: 0.,
+				..hills()
+			});
+		};
+		let single = heights(&[0]);
+		// layers sharing a seed sample the same noise
+		for (double, single) in heights(&[0, 0]).iter().zip(&single) {
+			assert!((double - single * 2.).abs() < 1e-4);
+		}
+		let apart = heights(&[0, 1]);
+		let doubled = apart
+			.iter()
+			.zip(&single)
+			.filter(|(apart, single)| (*apart - *single * 2.).abs() < 1e-4)
+			.count();
+		assert!(doubled < apart.len() / 10, "{doubled} of {}", apart.len());
+		assert_ne!(heights(&[1]), single);
+	}
+
+	fn spread(heights: &[f32]) -> f32 {
+		let low = heights.iter().copied().fold(f32::INFINITY, f32::min);
+		let high = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+		return high - low;
+	}
+
+	#[test]
+	fn multiplying_by_a_faint_mask_flattens_the_terrain() {
+		let mask = NoiseLayer {
+			strength: 1e-3,
+			blend: NoiseBlend::Multiply,
+			..layer(1)
+		};
+		let settings = HexGridSettings {
+			noise_layers: vec![layer(3), mask],
+			height_offset: 2.,
+			..hills()
+		};
+		let heights = tile_heights(&settings);
+		assert!(spread(&tile_heights(&hills())) > 3.);
+		// the hills reach up to 1.75, the mask up to 1e-3
+		assert!(heights
+			.iter()
+			.all(|h| (2. ..2. + 1.75e-3 * 10.).contains(h)));
+		let (low, high) = height_bounds(&settings);
+		assert!(low >= 2. && high <= 2.02, "{low} {high}");
+	}
+
+	#[test]
+	fn ridges_peak_where_the_noise_crosses_zero() {
+		assert_eq!(NoiseType::Ridged.shape(0.), 1.);
+		assert_eq!(NoiseType::Ridged.shape(1.), 0.);
+		assert_eq!(NoiseType::Ridged.shape(-1.), 0.);
+		let layer = layer(1);
+		let ridged = samples(&layer, NoiseType::Ridged);
+		let raw = samples(&layer, NoiseType::Simplex)
+			.into_iter()
+			.map(|v| v * 2. - 1.)
+			.collect::<Vec<_>>();
+		let (_, high) = layer.bounds();
+		for (ridge, raw) in ridged.iter().zip(&raw) {
+			assert!((0. ..=high).contains(ridge));
+			assert!((ridge - (1. - raw.abs())).abs() < 1e-9);
+		}
+		// the highest ridge sits where the noise comes closest to zero
+		let highest = ridged.iter().copied().fold(0., f64::max);
+		let crossing = (0..raw.len()).min_by(|&a, &b| raw[a].abs().total_cmp(&raw[b].abs()));
+		assert_eq!(ridged[crossing.unwrap()], highest);
+		assert!(highest > 0.95);
+
+		let settings = HexGridSettings {
+			noise_type: NoiseType::Ridged,
+			..hills()
+		};
+		let (low, high) = height_bounds(&settings);
+		assert_eq!((low, high), (0., 10.));
+		assert!(tile_heights(&settings)
+			.iter()
+			.all(|h| (low..=high).contains(h)));
+	}
+
+	#[test]
+	fn island_falloff_sinks_the_edges() {
+		let island = HexGridSettings {
+			island_falloff: Some(2.),
+			height_offset: -1.,
+			sea_level: Some(3.2),
+			..hills()
+		};
+		let open = HexGridSettings {
+			island_falloff: None,
+			..island.clone()
+		};
+		let noise = island.noise();
+		let last = island.map_tiles().as_ivec2() - 1;
+		let sea_level = island.sea_level.unwrap();
+		for (x, z) in [(0, 0), (last.x, 0), (0, last.y), (last.x, last.y)] {
+			let height = sample_height(x, z, &noise, &island);
+			assert!(height < sea_level, "{height} at ({x}, {z})");
+			// all the way down to the lowest height
+			assert!((height - height_bounds(&island).0).abs() < 0.5);
+		}
+		let middle = last / 2 + 1;
+		let (sunk, kept) = (
+			sample_height(middle.x, middle.y, &noise, &island),
+			sample_height(middle.x, middle.y, &noise, &open),
+		);
+		assert!((sunk - kept).abs() < 0.01 * (kept + 1.), "{sunk} {kept}");
+	}
+
+	/// Runs `animate_terrain` for a frame of a map of two chunks with one
+	/// moving layer, returning the settings after and the chunks dirtied.
+	fn animated_frame(animate: bool) -> (HexGridSettings, DirtyChunks) {
+		let mut world = World::new();
+		world.insert_resource(HexGridSettings {
+			map_size: UVec2::new(2, 1),
+			chunk_size: 4,
+			noise_scale: 20.,
+			noise_layers: vec![NoiseLayer {
+				speed: 1.,
+				..layer(2)
+			}],
+			height_amplitude: 10.,
+			animate_terrain: animate,
+			..default()
+		});
+		world.init_resource::<DirtyChunks>();
+		let mut time = Time::<()>::default();
+		time.advance_by(Duration::from_millis(500));
+		world.insert_resource(time);
+		for index in [UVec2::ZERO, UVec2::X] {
+			world.spawn(Chunk { index, lod: 0 });
+		}
+		world.run_system_once(animate_terrain);
+		let dirty = world.remove_resource::<DirtyChunks>().unwrap();
+		return (world.remove_resource::<HexGridSettings>().unwrap(), dirty);
+	}
+
+	#[test]
+	fn still_terrain_keeps_its_vertices() {
+		let (settings, dirty) = animated_frame(false);
+		assert_eq!(settings.terrain_time, 0.);
+		assert!(dirty.is_empty());
+		// nothing rebuilds, and a rebuild would come out the same anyway
+		let (again, _) = animated_frame(false);
+		let before = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+		assert_eq!(HexGrid::chunk_buffers(UVec2::ZERO, &again), before);
+
+		// the same frame with animation on moves the terrain
+		let (moved, dirty) = animated_frame(true);
+		assert_eq!(moved.terrain_time, 0.5);
+		assert_eq!(dirty.len(), 2);
+		assert_ne!(HexGrid::chunk_buffers(UVec2::ZERO, &moved), before);
+	}
+
+	#[test]
+	fn heights_between_centers_rise_smoothly() {
+		// a tile at 2 next to one at 6, on ground at 4
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 6,
+			..default()
+		};
+		let (low, high) = (HexCoord::from_offset(2, 2), HexCoord::from_offset(3, 2));
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = match i {
+				14 => 2.,
+				15 => 6.,
+				_ => 4.,
+			};
+		}
+		settings.map = Some(Arc::new(map));
+		let (a, b) = (low.to_world(&settings).xz(), high.to_world(&settings).xz());
+		let heights = (0..=50)
+			.map(|i| HexGrid::height_at_world(a.lerp(b, i as f32 / 50.), &settings))
+			.collect::<Vec<_>>();
+		assert!((heights[0] - 2.).abs() < 1e-4);
+		assert!((heights[50] - 6.).abs() < 1e-4);
+		for step in heights.windows(2) {
+			assert!(step[1] >= step[0] - 1e-5);
+			assert!(step[1] - step[0] < 4. / 50. + 1e-4);
+		}
+	}
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+
+use super::{HexGridSettings, RegenerateMap};
+
+/// Brightness of a grayscale image, 0 for black and 1 for white, stretched
+/// over the whole map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+	pub size: UVec2,
+	/// Row by row from the top of the image.
+	pub values: Vec<f32>,
+}
+
+impl Heightmap {
+	/// The first channel of `image`, at 8 or 16 bits. Returns `None` for
+	/// empty images and other formats.
+	pub fn from_image(image: &Image) -> Option<Self> {
+		let size = image.size();
+		let pixels = (size.x * size.y) as usize;
+		// loaded 8-bit images come as RGBA whether or not they're gray
+		let values: Vec<f32> = match image.texture_descriptor.format {
+			TextureFormat::R8Unorm => image.data.iter().map(|&v| v as f32 / 255.).collect(),
+			TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image
+				.data
+				.chunks_exact(4)
+				.map(|p| p[0] as f32 / 255.)
+				.collect(),
+			TextureFormat::R16Uint | TextureFormat::R16Unorm => image
+				.data
+				.chunks_exact(2)
+				.map(|p| u16::from_le_bytes([p[0], p[1]]) as f32 / u16::MAX as f32)
+				.collect(),
+			_ => return None,
+		};
+		if pixels == 0 || values.len() < pixels {
+			return None;
+		}
+		return Some(Self { size, values });
+	}
+
+	/// Brightness at `uv`, from the top left corner of the image at (0, 0)
+	/// to the bottom right at (1, 1), blended between the four nearest pixel
+	/// centers and clamped at the edges.
+	pub fn sample(&self, uv: Vec2) -> f32 {
+		let max = (self.size.as_vec2() - 1.).max(Vec2::ZERO);
+		let pos = (uv * self.size.as_vec2() - 0.5).clamp(Vec2::ZERO, max);
+		let (first, f) = (pos.floor(), pos.fract());
+		let first = first.as_uvec2();
+		let last = (first + 1).min(max.as_uvec2());
+		let at = |x: u32, y: u32| self.values[(y * self.size.x + x) as usize];
+		let top = at(first.x, first.y) * (1. - f.x) + at(last.x, first.y) * f.x;
+		let bottom = at(first.x, last.y) * (1. - f.x) + at(last.x, last.y) * f.x;
+		return top * (1. - f.y) + bottom * f.y;
+	}
+}
+
+/// Reads `HexGridSettings::height_image` into `HexGridSettings::heightmap`
+/// like the color ramp, rebuilding the map whenever the image changes.
+pub(super) fn load_height_image(
+	mut loaded: Local<Option<AssetId<Image>>>,
+	mut events: EventReader<AssetEvent<Image>>,
+	mut regenerate: EventWriter<RegenerateMap>,
+	images: Res<Assets<Image>>,
+	mut settings: ResMut<HexGridSettings>,
+) {
+	let Some(handle) = settings.height_image.clone() else {
+		*loaded = None;
+		return;
+	};
+	let modified = events.read().any(|event| {
+		event.is_modified(handle.id()) || event.is_loaded_with_dependencies(handle.id())
+	});
+	if *loaded == Some(handle.id()) && !modified {
+		return;
+	}
+	let Some(image) = images.get(&handle) else {
+		return;
+	};
+	*loaded = Some(handle.id());
+	let heightmap = Heightmap::from_image(image);
+	if heightmap.is_none() {
+		warn!(
+			"height image has unsupported format {:?}",
+			image.texture_descriptor.format
+		);
+	}
+	settings.bypass_change_detection().heightmap = heightmap.map(Arc::new);
+	regenerate.send(RegenerateMap);
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension},
+	};
+
+	use super::*;
+	use crate::hex_grid::{HexCoord, HexGrid, NormalMode};
+
+	fn gray(size: UVec2, value: impl Fn(u32, u32) -> u8) -> Image {
+		let data = (0..size.x * size.y)
+			.map(|i| value(i % size.x, i / size.x))
+			.collect();
+		return Image::new(
+			Extent3d {
+				width: size.x,
+				height: size.y,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			data,
+			TextureFormat::R8Unorm,
+			RenderAssetUsages::MAIN_WORLD,
+		);
+	}
+
+	/// A map of 8×8 tiles shaped by `image`, 10 high from black to white.
+	fn shaped(image: &Image) -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 4,
+			height_amplitude: 10.,
+			height_offset: 1.,
+			normal_mode: NormalMode::Smooth,
+			heightmap: Some(Arc::new(Heightmap::from_image(image).unwrap())),
+			..default()
+		};
+	}
+
+	fn height(settings: &HexGridSettings, x: i32, z: i32) -> f32 {
+		return HexGrid::height_at(HexCoord::from_offset(x, z), settings);
+	}
+
+	#[test]
+	fn constant_gray_is_flat() {
+		let settings = shaped(&gray(UVec2::new(3, 5), |_, _| 51));
+		for (x, z) in (0..64).map(|i| (i % 8, i / 8)) {
+			assert!((height(&settings, x, z) - 3.).abs() < 1e-4);
+		}
+		// level tiles need no walls
+		let buffers = HexGrid::chunk_buffers(UVec2::ONE, &settings);
+		assert_eq!(buffers.positions.len(), 16 * 7);
+	}
+
+	#[test]
+	fn gradients_make_ramps() {
+		// one pixel per tile, brightening along x
+		let settings = shaped(&gray(UVec2::splat(8), |x, _| (x * 32) as u8));
+		for z in 0..8 {
+			for x in 0..8 {
+				let expected = 1. + (x * 32) as f32 / 255. * 10.;
+				assert!((height(&settings, x, z) - expected).abs() < 1e-4);
+			}
+		}
+		// a ramp down along z from a gradient down the image, whose top is +z
+		let settings = shaped(&gray(UVec2::splat(8), |_, y| (y * 32) as u8));
+		for z in 1..8 {
+			assert!(height(&settings, 0, z) < height(&settings, 0, z - 1));
+		}
+	}
+}
@@ -0,0 +1,213 @@
+// A port of `generated_height` in height.rs for unwrapped maps, writing the
+// height of every tile of the map. The noise is the 2D SuperSimplex of the
+// noise crate, hashed with the permutation table of each layer's seed.
+
+struct Params {
+	tiles: vec2<u32>,
+	layer_count: u32,
+	noise_type: u32,
+	noise_scale: f32,
+	height_amplitude: f32,
+	height_offset: f32,
+	// whether `island_falloff` and `height_steps` are set
+	island: u32,
+	island_falloff: f32,
+	stepped: u32,
+	height_steps: u32,
+	elevation_low: f32,
+	elevation_high: f32,
+}
+
+struct Layer {
+	rigid: u32,
+	weight_multi: f32,
+	base_roughness: f32,
+	lacunarity: f32,
+	persistence: f32,
+	min_value: f32,
+	strength: f32,
+	octaves: u32,
+	masked: u32,
+	weight: f32,
+	blend: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> layers: array<Layer>;
+// 256 entries for each layer
+@group(0) @binding(2) var<storage, read> permutations: array<u32>;
+@group(0) @binding(3) var<storage, read_write> heights: array<f32>;
+
+const SEA_LEVEL: f32 = 5.0;
+const TO_REAL: f32 = -0.211324865405187;
+const TO_SIMPLEX: f32 = 0.366025403784439;
+const NORM: f32 = 18.421989719106747;
+const DIAG: f32 = 0.7071067811865476;
+
+// the lattice points around each region of a simplex cell, four at a time
+var<private> LATTICE: array<vec2<i32>, 32> = array<vec2<i32>, 32>(
+	vec2(0, 0), vec2(1, 1), vec2(-1, 0), vec2(0, -1),
+	vec2(0, 0), vec2(1, 1), vec2(0, 1), vec2(1, 0),
+	vec2(0, 0), vec2(1, 1), vec2(1, 0), vec2(0, -1),
+	vec2(0, 0), vec2(1, 1), vec2(2, 1), vec2(1, 0),
+	vec2(0, 0), vec2(1, 1), vec2(-1, 0), vec2(0, 1),
+	vec2(0, 0), vec2(1, 1), vec2(0, 1), vec2(1, 2),
+	vec2(0, 0), vec2(1, 1), vec2(1, 0), vec2(0, 1),
+	vec2(0, 0), vec2(1, 1), vec2(2, 1), vec2(1, 2),
+);
+
+var<private> GRADIENTS: array<vec2<f32>, 8> = array<vec2<f32>, 8>(
+	vec2(1.0, 0.0), vec2(-1.0, 0.0), vec2(0.0, 1.0), vec2(0.0, -1.0),
+	vec2(DIAG, DIAG), vec2(-DIAG, DIAG), vec2(DIAG, -DIAG), vec2(-DIAG, -DIAG),
+);
+
+fn hash(point: vec2<i32>, layer: u32) -> u32 {
+	let table = layer * 256u;
+	let x = permutations[table + u32(point.x & 255)];
+	return permutations[table + (x ^ u32(point.y & 255))];
+}
+
+fn super_simplex(point: vec2<f32>, layer: u32) -> f32 {
+	let simplex_point = point + (point.x + point.y) * TO_SIMPLEX;
+	let base = floor(simplex_point);
+	let rel = simplex_point - base;
+
+	let region_sum = floor(rel.x + rel.y);
+	let index = select(0u, 4u, region_sum >= 1.0)
+		| select(0u, 8u, rel.x - rel.y * 0.5 + 1.0 - region_sum * 0.5 >= 1.0)
+		| select(0u, 16u, rel.y - rel.x * 0.5 + 1.0 - region_sum * 0.5 >= 1.0);
+	let real_rel = rel + (rel.x + rel.y) * TO_REAL;
+
+	var value = 0.0;
+	for (var i = index; i < index + 4u; i++) {
+		let lattice = LATTICE[i];
+		let l = vec2<f32>(lattice);
+		let dpos = real_rel - (l + (l.x + l.y) * TO_REAL);
+		let attn = 2.0 / 3.0 - dot(dpos, dpos);
+		if attn > 0.0 {
+			let gradient = GRADIENTS[hash(vec2<i32>(base) + lattice, layer) % 8u];
+			let attn2 = attn * attn;
+			value += attn2 * attn2 * dot(gradient, dpos);
+		}
+	}
+	return value * NORM;
+}
+
+fn shape(value: f32) -> f32 {
+	switch params.noise_type {
+		case 0u: {
+			return (value + 1.0) * 0.5;
+		}
+		case 1u: {
+			return 1.0 - abs(value);
+		}
+		case 2u: {
+			return abs(value);
+		}
+		default: {
+			return 0.0;
+		}
+	}
+}
+
+fn sample_layer(index: u32, point: vec2<f32>) -> f32 {
+	let layer = layers[index];
+	var freq = layer.base_roughness;
+	var amp = 1.0;
+	var value = 0.0;
+	var weight = 1.0;
+	for (var octave = 0u; octave < layer.octaves; octave++) {
+		let v = super_simplex(point * freq, index);
+		if layer.rigid == 1u {
+			var ridge = 1.0 - abs(v);
+			ridge *= ridge * weight;
+			weight = clamp(ridge * layer.weight_multi, 0.0, 1.0);
+			value += ridge * amp;
+		} else {
+			value += shape(v) * amp;
+		}
+		freq *= layer.lacunarity;
+		amp *= layer.persistence;
+	}
+	return (value - layer.min_value) * layer.strength * layer.weight;
+}
+
+fn elevation_at(point: vec2<f32>) -> f32 {
+	var elevation = 0.0;
+	var first_layer = 0.0;
+	for (var i = 0u; i < params.layer_count; i++) {
+		var value = sample_layer(i, point);
+		if i == 0u {
+			first_layer = value;
+			elevation = value;
+			continue;
+		}
+		let layer = layers[i];
+		if layer.masked == 1u {
+			value *= max(first_layer - SEA_LEVEL, 0.0);
+		}
+		switch layer.blend {
+			case 1u: {
+				elevation *= value;
+			}
+			case 2u: {
+				elevation = max(elevation, value);
+			}
+			default: {
+				elevation += value;
+			}
+		}
+	}
+	return elevation;
+}
+
+// `pow` is undefined for a zero base, which `powf` takes as 0, or 1 with a
+// zero exponent
+fn power(base: f32, exponent: f32) -> f32 {
+	if exponent == 0.0 {
+		return 1.0;
+	}
+	if base == 0.0 {
+		return 0.0;
+	}
+	return pow(base, exponent);
+}
+
+fn quantize(value: f32) -> f32 {
+	let low = params.elevation_low;
+	let high = params.elevation_high;
+	let steps = params.height_steps;
+	if steps < 2u || high <= low {
+		return low;
+	}
+	let t = clamp((value - low) / (high - low), 0.0, 1.0);
+	let level = min(floor(t * f32(steps)), f32(steps - 1u));
+	return low + level / f32(steps - 1u) * (high - low);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+	if any(id.xy >= params.tiles) {
+		return;
+	}
+	let i = id.y * params.tiles.x + id.x;
+	// flat
+	if params.noise_type == 3u {
+		heights[i] = params.height_offset;
+		return;
+	}
+
+	let tile = vec2<f32>(id.xy);
+	var elevation = elevation_at(tile / params.noise_scale);
+	if params.island == 1u {
+		let centered = (tile + 0.5) / vec2<f32>(params.tiles) * 2.0 - 1.0;
+		let distance = min(max(abs(centered.x), abs(centered.y)), 1.0);
+		let falloff = 1.0 - power(distance, params.island_falloff);
+		let low = params.elevation_low;
+		elevation = low + (elevation - low) * falloff;
+	}
+	if params.stepped == 1u {
+		elevation = quantize(elevation);
+	}
+	heights[i] = elevation * params.height_amplitude + params.height_offset;
+}
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use super::{
+	biome::{biome_neighbors, tile_biome},
+	create_quad, create_tile, finish_mesh,
+	height::height_bounds,
+	height::sample_height,
+	to_hex_pos, HexCoord, HexGridSettings,
+};
+
+/// Marks the entity drawing one tile in instanced mode.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TileInstance(pub HexCoord);
+
+/// A tile drawn as a scaled copy of the shared hex prism.
+pub(super) struct Instance {
+	pub coord: HexCoord,
+	pub transform: Transform,
+	/// Index into `HexGridSettings::biomes`.
+	pub biome: usize,
+}
+
+/// One instance per tile of the map. Prisms stand on the lowest possible
+/// height and are stretched up to the height of their tile.
+pub(super) fn tile_instances(settings: &HexGridSettings) -> Vec<Instance> {
+	let noise = settings.noise();
+	let (low, _) = height_bounds(settings);
+	let tiles = settings.map_tiles();
+	let mut instances = Vec::with_capacity((tiles.x * tiles.y) as usize);
+	for z in 0..tiles.y {
+		for x in 0..tiles.x {
+			let coord = HexCoord::from_offset(x as i32, z as i32);
+			if !settings.contains(coord) {
+				continue;
+			}
+			let height = sample_height(x as i32, z as i32, &noise, settings);
+			let pos =
+				settings.world_origin() + to_hex_pos(Vec3::new(x as f32, low, z as f32), settings);
+			let neighbors = biome_neighbors(x as i32, z as i32, &noise, settings);
+			let biome = tile_biome(x as i32, z as i32, height, &neighbors, settings)
+				.and_then(|biome| settings.biomes.iter().position(|b| std::ptr::eq(b, biome)))
+				.unwrap_or(0);
+			instances.push(Instance {
+				coord,
+				transform: Transform::from_translation(pos).with_scale(Vec3::new(
+					1.,
+					(height - low).max(f32::EPSILON),
+					1.,
+				)),
+				biome,
+			});
+		}
+	}
+	return instances;
+}
+
+/// A tile one unit tall with its walls reaching down to y = 0.
+fn hex_prism(settings: &HexGridSettings) -> Mesh {
+	let corners = settings.hex_corners();
+	// every instance shares the cell of the prism's unit height
+	let cell = settings.atlas_rect(1.);
+	let mut verts = Vec::new();
+	let mut uvs = Vec::new();
+	let mut normals = Vec::new();
+	let mut indices = Vec::new();
+	create_tile(
+		Vec3::Y,
+		cell,
+		&corners,
+		settings,
+		&mut verts,
+		&mut uvs,
+		&mut normals,
+		&mut indices,
+	);
+	for i in 0..6 {
+		let (a, b) = (Vec3::Y + corners[i], Vec3::Y + corners[(i + 1) % 6]);
+		let quad = [a, b, b - Vec3::Y, a - Vec3::Y];
+		create_quad(
+			quad,
+			cell,
+			settings,
+			&mut verts,
+			&mut uvs,
+			&mut normals,
+			&mut indices,
+		);
+	}
+	let mut mesh = finish_mesh(verts, uvs, normals, indices, &[], settings);
+	// instances are colored by their material
+	mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR);
+	return mesh;
+}
+
+/// Replaces every tile instance, so they follow changes to the settings.
+pub(super) fn spawn_tile_instances(
+	mut commands: Commands,
+	instances: Query<Entity, With<TileInstance>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	mut shared: Local<Option<(Handle<Mesh>, Vec<Handle<StandardMaterial>>)>>,
+	settings: Res<HexGridSettings>,
+) {
+	for entity in &instances {
+		commands.entity(entity).despawn_recursive();
+	}
+	if let Some((mesh, biome_materials)) = shared.take() {
+		meshes.remove(&mesh);
+		for material in &biome_materials {
+			materials.remove(material);
+		}
+	}
+	if !settings.instanced {
+		return;
+	}
+
+	// entities sharing a mesh and material are batched into instanced draws
+	let mesh = meshes.add(hex_prism(&settings));
+	let mut biome_materials = settings
+		.biomes
+		.iter()
+		.map(|biome| materials.add(StandardMaterial::from(biome.color)))
+		.collect::<Vec<_>>();
+	if biome_materials.is_empty() {
+		biome_materials.push(materials.add(StandardMaterial::default()));
+	}
+	commands.spawn_batch(
+		tile_instances(&settings)
+			.into_iter()
+			.map(|instance| {
+				return (
+					TileInstance(instance.coord),
+					PbrBundle {
+						mesh: mesh.clone(),
+						material: biome_materials[instance.biome].clone(),
+						transform: instance.transform,
+						..default()
+					},
+				);
+			})
+			.collect::<Vec<_>>(),
+	);
+	*shared = Some((mesh, biome_materials));
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::utils::HashSet;
+
+	use super::*;
+	use crate::hex_grid::MapShape;
+
+	#[test]
+	fn every_tile_has_one_instance() {
+		let base = HexGridSettings {
+			map_size: UVec2::new(3, 2),
+			chunk_size: 5,
+			height_amplitude: 4.,
+			instanced: true,
+			..default()
+		};
+		let hexagon = HexGridSettings {
+			shape: MapShape::Hexagon { radius: 4 },
+			..base.clone()
+		};
+		// 15 × 10 tiles, and a hexagon of radius 4 around (7, 5)
+		let center = HexCoord::from_offset(7, 5);
+		for (settings, count) in [(base, 15 * 10), (hexagon, 3 * 4 * 5 + 1)] {
+			let instances = tile_instances(&settings);
+			assert_eq!(instances.len(), count);
+			let coords = instances.iter().map(|i| i.coord).collect::<HashSet<_>>();
+			assert_eq!(coords.len(), instances.len());
+			if settings.shape != MapShape::Rectangle {
+				assert!(coords.iter().all(|coord| coord.distance(&center) <= 4));
+			}
+
+			// prisms one unit tall reach up to the height of their tile
+			let noise = settings.noise();
+			for instance in &instances {
+				let offset = instance.coord.to_offset();
+				let height = sample_height(offset.x, offset.y, &noise, &settings);
+				let top = instance.transform.translation.y + instance.transform.scale.y;
+				assert!((top - height).abs() < 1e-3, "{top} {height}");
+			}
+		}
+	}
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use super::{sample_height, HexCoord, HexGridSettings, TerrainNoise};
+
+/// A text node showing the coordinates of a tile near the camera focus.
+#[derive(Component)]
+pub(super) struct CoordLabel;
+
+/// World position a tile's label is pinned to, the middle of its top.
+pub(super) fn label_anchor(
+	coord: HexCoord,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Vec3 {
+	let offset = coord.to_offset();
+	let height = sample_height(offset.x, offset.y, noise, settings);
+	return coord.to_world(settings) + Vec3::Y * height;
+}
+
+pub(super) fn label_text(coord: HexCoord) -> String {
+	return format!("{}, {}", coord.q, coord.r);
+}
+
+/// Labels every tile within `HexGridSettings::coord_labels` tiles of the
+/// camera focus, reusing the text nodes from the frame before.
+pub(super) fn draw_coord_labels(
+	mut commands: Commands,
+	cameras: Query<(&Camera, &GlobalTransform, &PanOrbitCamera)>,
+	mut labels: Query<(&mut Text, &mut Style, &mut Visibility), With<CoordLabel>>,
+	settings: Res<HexGridSettings>,
+) {
+	let (Some(radius), Ok((camera, transform, orbit))) =
+		(settings.coord_labels, cameras.get_single())
+	else {
+		for (_, _, mut visibility) in &mut labels {
+			visibility.set_if_neq(Visibility::Hidden);
+		}
+		return;
+	};
+	let center = HexCoord::from_world(orbit.focus, &settings);
+	let noise = settings.noise();
+	let mut shown = HexCoord::spiral(center, radius)
+		.into_iter()
+		.filter(|&coord| settings.contains(coord))
+		.filter_map(|coord| {
+			let anchor = label_anchor(coord, &noise, &settings);
+			let screen = camera.world_to_viewport(transform, anchor)?;
+			return Some((label_text(coord), screen));
+		});
+
+	for (mut text, mut style, mut visibility) in &mut labels {
+		let Some((label, screen)) = shown.next() else {
+			visibility.set_if_neq(Visibility::Hidden);
+			continue;
+		};
+		// changed text is laid out again
+		if text.sections[0].value != label {
+			text.sections[0].value = label;
+		}
+		style.left = Val::Px(screen.x);
+		style.top = Val::Px(screen.y);
+		visibility.set_if_neq(Visibility::Inherited);
+	}
+	// the new nodes are placed from the next frame on
+	for _ in shown {
+		commands.spawn((CoordLabel, label_node()));
+	}
+}
+
+/// A hidden, absolutely positioned text node for a label.
+pub(super) fn label_node() -> TextBundle {
+	let text = TextBundle::from_section(
+		"",
+		TextStyle {
+			font_size: 14.,
+			color: Color::WHITE,
+			..default()
+		},
+	)
+	.with_style(Style {
+		position_type: PositionType::Absolute,
+		..default()
+	});
+	return TextBundle {
+		visibility: Visibility::Hidden,
+		..text
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::{HexGrid, HexOrientation};
+
+	#[test]
+	fn labels_name_the_tile_under_them() {
+		let base = HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 8,
+			height_amplitude: 4.,
+			..default()
+		};
+		let variants = [
+			base.clone(),
+			HexGridSettings {
+				orientation: HexOrientation::FlatTop,
+				origin: Vec3::new(-30., 2., 12.),
+				..base
+			},
+		];
+		for settings in variants {
+			let noise = settings.noise();
+			let bounds = settings.map_bounds();
+			for i in 0..97 {
+				// a scattered walk over the map
+				let t = Vec2::new((i as f32 * 0.618).fract(), (i as f32 * 0.382).fract());
+				let pos = (bounds.min + t * bounds.size()).extend(0.).xzy();
+				let coord = HexCoord::from_world(pos, &settings);
+				let anchor = label_anchor(coord, &noise, &settings);
+				let lift = anchor - coord.to_world(&settings);
+				assert!(lift.xz().length() < 1e-4);
+				assert!((lift.y - HexGrid::height_at(coord, &settings)).abs() < 1e-4);
+				let under = HexCoord::from_world(anchor, &settings);
+				assert_eq!(under, coord);
+				assert_eq!(label_text(under), format!("{}, {}", coord.q, coord.r));
+			}
+		}
+	}
+}
@@ -0,0 +1,341 @@
+use bevy::prelude::*;
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use super::{
+	chunk_center, chunk_task, finish_mesh, sample_height, to_chunk_pos, Chunk, ChunkTask, HexCoord,
+	HexGridSettings, TerrainNoise,
+};
+
+/// Coarse meshes for distant chunks. A chunk at LOD `n` samples every `2^n`th
+/// tile center into a plain height field grid with no cliff walls.
+///
+/// Neighboring coarse chunks sample the same tiles along their shared edge, so
+/// they meet exactly. Where a coarse chunk borders a finer one the surfaces
+/// differ by up to half a tile; every coarse chunk hangs a skirt of
+/// `skirt_depth` down from its border so the gap shows terrain, not sky.
+#[derive(Reflect, Clone, Debug)]
+pub struct LodSettings {
+	/// Camera distance at which each successive level starts, ascending.
+	pub distances: Vec<f32>,
+	pub skirt_depth: f32,
+}
+
+impl Default for LodSettings {
+	fn default() -> Self {
+		return Self {
+			distances: vec![150., 300., 600.],
+			skirt_depth: 5.,
+		};
+	}
+}
+
+pub(super) fn lod_for_distance(distance: f32, settings: &HexGridSettings) -> u32 {
+	let Some(lod) = &settings.lod else {
+		return 0;
+	};
+	let level = lod.distances.iter().filter(|d| distance > **d).count() as u32;
+	// a step wider than the chunk adds nothing
+	return level.min(settings.chunk_size.max(1).ilog2());
+}
+
+pub(super) fn update_chunk_lods(
+	mut commands: Commands,
+	cameras: Query<&GlobalTransform, With<PanOrbitCamera>>,
+	mut chunks: Query<(Entity, &mut Chunk), Without<ChunkTask>>,
+	settings: Res<HexGridSettings>,
+) {
+	let (Some(_), Ok(camera)) = (&settings.lod, cameras.get_single()) else {
+		return;
+	};
+	let noise = settings.noise();
+	for (entity, mut chunk) in &mut chunks {
+		let distance = chunk_center(chunk.index, &settings).distance(camera.translation());
+		let lod = lod_for_distance(distance, &settings);
+		if lod != chunk.lod {
+			chunk.lod = lod;
+			// the old mesh stays up until the new one is ready
+			let task = chunk_task(chunk.index, lod, &noise, &settings);
+			commands.entity(entity).insert(task);
+		}
+	}
+}
+
+pub(super) fn create_lod_chunk(
+	c_x: u32,
+	c_z: u32,
+	lod: u32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Mesh {
+	// an empty map has no tiles to sample
+	if settings.is_empty() {
+		return finish_mesh(
+			Vec::new(),
+			Vec::new(),
+			Vec::new(),
+			Vec::new(),
+			&[],
+			settings,
+		);
+	}
+	let chunk_size = settings.chunk_size;
+	let step = (1u32 << lod.min(31)).min(chunk_size);
+	let origin = UVec2::new(c_x, c_z) * chunk_size;
+	let last_tile = settings.map_tiles().saturating_sub(UVec2::ONE);
+	// one extra sample reaches the first tile of the next chunk
+	let samples = chunk_size.div_ceil(step) + 1;
+
+	let mut verts = Vec::new();
+	let mut uvs = Vec::new();
+	// samples outside the map shape or hidden leave holes
+	let mut present = Vec::new();
+	for j in 0..samples {
+		for i in 0..samples {
+			let local = UVec2::new(i * step, j * step).min(UVec2::splat(chunk_size));
+			let local = (origin + local).min(last_tile) - origin;
+			let global = (origin + local).as_ivec2();
+			present.push(settings.shows(HexCoord::from_offset(global.x, global.y)));
+			let height = sample_height(global.x, global.y, noise, settings);
+			let pos = Vec3::new(local.x as f32, height, local.y as f32);
+			let p = to_chunk_pos(pos, origin.y as i32, settings);
+			verts.push(p);
+			// coarse chunks are stretched across the whole chunk instead of per
+			// tile, or sample the middle of each vertex's atlas cell or of the
+			// grid lines' hexagon
+			uvs.push(match (&settings.atlas, settings.grid_lines) {
+				(None, None) => local.as_vec2() / chunk_size as f32,
+				_ => settings.atlas_rect(height).center(),
+			});
+		}
+	}
+
+	let mut indices = Vec::new();
+	let at = |i: u32, j: u32| j * samples + i;
+	for j in 0..samples - 1 {
+		for i in 0..samples - 1 {
+			let (a, b, c, d) = (at(i, j), at(i, j + 1), at(i + 1, j), at(i + 1, j + 1));
+			if [a, b, c, d].iter().all(|&v| present[v as usize]) {
+				indices.extend([a, b, c, c, b, d]);
+			}
+		}
+	}
+
+	// walk the -z edge towards +x first, then around, so the skirt faces out
+	let last = samples - 1;
+	let border = (0..last)
+		.map(|i| at(i, 0))
+		.chain((0..last).map(|j| at(last, j)))
+		.chain((1..=last).rev().map(|i| at(i, last)))
+		.chain((1..=last).rev().map(|j| at(0, j)))
+		.collect::<Vec<_>>();
+	let depth = settings.lod.as_ref().map_or(0., |lod| lod.skirt_depth);
+	let skirt_start = verts.len() as u32;
+	for &top in &border {
+		let p = verts[top as usize] - Vec3::Y * depth;
+		verts.push(p);
+		uvs.push(uvs[top as usize]);
+	}
+	for (k, &top) in border.iter().enumerate() {
+		let next = (k + 1) % border.len();
+		let (bottom, next_bottom) = (skirt_start + k as u32, skirt_start + next as u32);
+		if !present[top as usize] || !present[border[next] as usize] {
+			continue;
+		}
+		indices.extend([top, border[next], next_bottom, top, next_bottom, bottom]);
+	}
+
+	let normals = vec![Vec3::Y; verts.len()];
+	return finish_mesh(verts, uvs, normals, indices, &[], settings);
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{math::Vec3A, render::mesh::VertexAttributeValues};
+
+	use super::*;
+	use crate::hex_grid::{chunk_origin, create_chunk, ChunkBuffers, HexOrientation, NormalMode};
+
+	fn coarse_map() -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::splat(3),
+			chunk_size: 8,
+			height_amplitude: 4.,
+			// smooth shading keeps the vertices in the order they were built
+			normal_mode: NormalMode::Smooth,
+			lod: Some(LodSettings::default()),
+			..default()
+		};
+	}
+
+	/// Vertices of a chunk mesh in world space.
+	fn world_vertices(index: UVec2, lod: u32, settings: &HexGridSettings) -> Vec<Vec3> {
+		let noise = settings.noise();
+		let origin = chunk_origin(index, settings);
+		let mesh = create_chunk(index.x, index.y, lod, &noise, settings);
+		return mesh
+			.attribute(Mesh::ATTRIBUTE_POSITION)
+			.and_then(|positions| positions.as_float3())
+			.unwrap()
+			.iter()
+			.map(|p| origin + Vec3::from(*p))
+			.collect();
+	}
+
+	fn samples(lod: u32, settings: &HexGridSettings) -> u32 {
+		return settings.chunk_size.div_ceil(1 << lod) + 1;
+	}
+
+	/// Height field samples of a coarse chunk along its +x edge and its -x
+	/// edge, or its +z and -z edges when `along_z` is set.
+	fn edges(vertices: &[Vec3], samples: u32, along_z: bool) -> (Vec<Vec3>, Vec<Vec3>) {
+		let at = |i: u32, j: u32| match along_z {
+			false => vertices[(j * samples + i) as usize],
+			true => vertices[(i * samples + j) as usize],
+		};
+		let last = samples - 1;
+		return (
+			(0..samples).map(|j| at(last, j)).collect(),
+			(0..samples).map(|j| at(0, j)).collect(),
+		);
+	}
+
+	#[test]
+	fn coarse_neighbors_share_their_edges() {
+		let settings = coarse_map();
+		for (next, along_z) in [(UVec2::X, false), (UVec2::Y, true)] {
+			for lod in 1..=3 {
+				let count = samples(lod, &settings);
+				let first = world_vertices(UVec2::ZERO, lod, &settings);
+				let second = world_vertices(next, lod, &settings);
+				assert_eq!(
+					edges(&first, count, along_z).0,
+					edges(&second, count, along_z).1
+				);
+
+				// a coarser neighbor samples every other tile of a finer one
+				let coarser = world_vertices(next, lod + 1, &settings);
+				let shared = edges(&first, count, along_z).0;
+				for sample in edges(&coarser, samples(lod + 1, &settings), along_z).1 {
+					assert!(shared.contains(&sample), "{sample}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn skirts_cover_the_gap_to_finer_neighbors() {
+		let settings = coarse_map();
+		let depth = settings.lod.as_ref().unwrap().skirt_depth;
+		let size = settings.chunk_size;
+		let fine = world_vertices(UVec2::X, 0, &settings);
+		// the tops of the fine chunk's first column, the tiles the coarse
+		// chunk's +x edge samples
+		let fine_tops = (0..size)
+			.flat_map(|z| {
+				let first = (z * size * 7) as usize;
+				return fine[first..first + 7].to_vec();
+			})
+			.collect::<Vec<_>>();
+		for lod in 1..=3 {
+			let count = samples(lod, &settings);
+			let coarse = world_vertices(UVec2::ZERO, lod, &settings);
+			let (grid, skirt) = coarse.split_at((count * count) as usize);
+			assert_eq!(skirt.len() as u32, 4 * (count - 1));
+			// every skirt vertex hangs straight below a border sample
+			for bottom in skirt {
+				let top = *bottom + Vec3::Y * depth;
+				assert!(grid.iter().any(|v| v.distance(top) < 1e-4), "{bottom}");
+			}
+			let edge = edges(grid, count, false).0;
+			for top in &fine_tops {
+				let Some(pair) = edge.windows(2).find(|p| (p[0].z..=p[1].z).contains(&top.z))
+				else {
+					continue;
+				};
+				let t = (top.z - pair[0].z) / (pair[1].z - pair[0].z);
+				let bottom = pair[0].y.lerp(pair[1].y, t) - depth;
+				assert!(bottom <= top.y, "skirt ends at {bottom} above {top}");
+			}
+		}
+	}
+
+	#[test]
+	fn uvs_stay_within_the_texture() {
+		let settings = coarse_map();
+		let noise = settings.noise();
+		for lod in 1..=3 {
+			for index in [UVec2::ZERO, UVec2::new(2, 1), UVec2::splat(2)] {
+				let mesh = create_lod_chunk(index.x, index.y, lod, &noise, &settings);
+				let Some(VertexAttributeValues::Float32x2(uvs)) =
+					mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+				else {
+					panic!("chunk meshes have UVs");
+				};
+				for uv in uvs {
+					assert!(uv.iter().all(|c| (0. ..=1.).contains(c)), "{uv:?}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn chunk_aabbs_hold_every_vertex() {
+		let base = coarse_map();
+		let variants = [
+			base.clone(),
+			HexGridSettings {
+				orientation: HexOrientation::FlatTop,
+				..base.clone()
+			},
+			HexGridSettings {
+				chunk_skirt: Some(8.),
+				..base
+			},
+		];
+		for settings in variants {
+			let noise = settings.noise();
+			for lod in 0..=3 {
+				for index in [
+					UVec2::ZERO,
+					UVec2::new(2, 1),
+					UVec2::new(1, 2),
+					UVec2::splat(2),
+				] {
+					let aabb = settings.chunk_aabb(index);
+					let (min, max) = (aabb.min() - 1e-3, aabb.max() + 1e-3);
+					let origin = chunk_origin(index, &settings);
+					let mesh = create_chunk(index.x, index.y, lod, &noise, &settings);
+					let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+					for p in positions.as_float3().unwrap() {
+						let p = Vec3A::from(origin + Vec3::from(*p));
+						assert!(
+							p.cmpge(min).all() && p.cmple(max).all(),
+							"{p} outside {aabb:?}"
+						);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn tiny_maps_build_at_every_level() {
+		for size in 0..=2 {
+			for chunk_size in [1, 2, 3, 16] {
+				let settings = HexGridSettings {
+					map_size: UVec2::splat(size),
+					chunk_size,
+					..coarse_map()
+				};
+				let noise = settings.noise();
+				for lod in 1..=2 {
+					let mesh = create_lod_chunk(0, 0, lod, &noise, &settings);
+					let buffers = ChunkBuffers::from_mesh(&mesh);
+					let len = buffers.positions.len();
+					assert_eq!(len == 0, size == 0);
+					assert!(buffers.indices.iter().all(|&i| (i as usize) < len));
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,299 @@
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+	biome::{biome_neighbors, tile_biome},
+	height::generated_height,
+	HexGridSettings,
+};
+
+/// Stored tile heights that replace the noise when set as
+/// `HexGridSettings::map`, so edited maps can be saved and rebuilt.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HexMap {
+	/// Number of tiles along x and z.
+	pub size: UVec2,
+	/// Tiles row by row along x, starting at the origin.
+	pub tiles: Vec<HexTile>,
+	/// Which of `tiles` hold captured data, for maps captured lazily as they
+	/// are edited. Empty once every tile does.
+	#[serde(skip)]
+	pub captured: Vec<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HexTile {
+	pub height: f32,
+	/// Name of the biome the tile belonged to when captured.
+	pub biome: String,
+	/// Solid segments floating above `height`, from the lowest up, leaving
+	/// caves and overhangs between them and the ground.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub spans: Vec<TileSpan>,
+}
+
+/// A solid segment of a tile from `bottom` up to `top`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TileSpan {
+	pub bottom: f32,
+	pub top: f32,
+}
+
+impl HexMap {
+	/// Captures every tile generated by `settings`.
+	pub fn from_settings(settings: &HexGridSettings) -> Self {
+		let mut map = Self::lazy(settings);
+		map.fill(settings);
+		return map;
+	}
+
+	/// A map the size of the tiles of `settings` with none of them captured
+	/// yet. Tiles that aren't keep following the noise.
+	pub fn lazy(settings: &HexGridSettings) -> Self {
+		let size = settings.map_tiles();
+		let tiles = (size.x * size.y) as usize;
+		return Self {
+			size,
+			tiles: vec![HexTile::default(); tiles],
+			captured: vec![false; tiles],
+		};
+	}
+
+	/// Captures the tiles of chunk `index` generated by `settings` that
+	/// aren't yet.
+	pub fn capture(&mut self, index: UVec2, settings: &HexGridSettings) {
+		let start = index * settings.chunk_size;
+		let end = (start + settings.chunk_size).min(self.size);
+		self.capture_tiles(start, end, settings);
+	}
+
+	/// Captures every tile generated by `settings` that isn't yet.
+	pub fn fill(&mut self, settings: &HexGridSettings) {
+		self.capture_tiles(UVec2::ZERO, self.size, settings);
+	}
+
+	fn capture_tiles(&mut self, start: UVec2, end: UVec2, settings: &HexGridSettings) {
+		if self.is_filled() {
+			return;
+		}
+		let noise = settings.noise();
+		for z in start.y..end.y {
+			for x in start.x..end.x {
+				let i = (z * self.size.x + x) as usize;
+				if self.captured[i] {
+					continue;
+				}
+				let (x, z) = (x as i32, z as i32);
+				let height = generated_height(x, z, &noise, settings);
+				let neighbors = biome_neighbors(x, z, &noise, settings);
+				let biome = tile_biome(x, z, height, &neighbors, settings);
+				self.tiles[i] = HexTile {
+					height,
+					biome: biome.map_or_else(String::new, |biome| biome.name.clone()),
+					spans: Vec::new(),
+				};
+				self.captured[i] = true;
+			}
+		}
+		if self.captured.iter().all(|&captured| captured) {
+			self.captured = Vec::new();
+		}
+	}
+
+	/// Whether every tile is captured.
+	pub fn is_filled(&self) -> bool {
+		return self.captured.is_empty();
+	}
+
+	/// Fails for maps that aren't filled yet.
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		if !self.is_filled() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"the map has tiles that were never captured",
+			));
+		}
+		let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		return fs::write(path, ron);
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		let ron = fs::read_to_string(path)?;
+		let map: Self =
+			ron::from_str(&ron).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		if map.tiles.len() != (map.size.x * map.size.y) as usize {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"tile count doesn't match the map size",
+			));
+		}
+		return Ok(map);
+	}
+
+	/// Sets `settings` up to build this map with their own chunk size. Chunks
+	/// past the edge of a map that doesn't divide evenly repeat the edge tiles.
+	pub fn apply(self, settings: &mut HexGridSettings) {
+		let chunk_size = settings.chunk_size.max(1);
+		settings.map_size = UVec2::new(
+			self.size.x.div_ceil(chunk_size),
+			self.size.y.div_ceil(chunk_size),
+		);
+		settings.map = Some(self.into());
+	}
+
+	/// The tile at offset coordinates (`x`, `z`), clamped to the map, unless
+	/// it isn't captured yet.
+	pub fn tile(&self, x: i32, z: i32) -> Option<&HexTile> {
+		let max = (self.size.as_ivec2() - 1).max(IVec2::ZERO);
+		let tile = IVec2::new(x, z).clamp(IVec2::ZERO, max);
+		let i = (tile.y * self.size.x as i32 + tile.x) as usize;
+		if self.captured.get(i) == Some(&false) {
+			return None;
+		}
+		return self.tiles.get(i);
+	}
+
+	/// Whether the tile at offset coordinates (`x`, `z`), clamped to the map,
+	/// is captured.
+	pub fn is_captured(&self, x: i32, z: i32) -> bool {
+		return self.is_filled() || self.tile(x, z).is_some();
+	}
+
+	pub fn height(&self, x: i32, z: i32) -> f32 {
+		return self.tile(x, z).map_or(0., |tile| tile.height);
+	}
+
+	/// The spans of the tile at offset coordinates (`x`, `z`) that float
+	/// above its ground without overlapping the ones below.
+	pub fn spans(&self, x: i32, z: i32) -> Vec<TileSpan> {
+		let Some(tile) = self.tile(x, z) else {
+			return Vec::new();
+		};
+		let mut floor = tile.height;
+		let mut spans = Vec::with_capacity(tile.spans.len());
+		for &span in &tile.spans {
+			if span.bottom > floor && span.top > span.bottom {
+				spans.push(span);
+				floor = span.top;
+			}
+		}
+		return spans;
+	}
+
+	/// Lowest and highest height of the captured tiles, if there are any.
+	/// Spans reach above the ground, but never below it.
+	pub(super) fn height_bounds(&self) -> Option<(f32, f32)> {
+		return (0..self.tiles.len())
+			.filter(|&i| self.is_filled() || self.captured[i])
+			.map(|i| {
+				let tile = &self.tiles[i];
+				let top = tile
+					.spans
+					.iter()
+					.fold(tile.height, |top, span| top.max(span.top));
+				return (tile.height, top);
+			})
+			.fold(None, |bounds, (height, top)| match bounds {
+				None => Some((height, top)),
+				Some((low, high)) => Some((height.min(low), top.max(high))),
+			});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::utils::HashMap;
+
+	use super::*;
+	use crate::hex_grid::{chunk_origin, HexCoord, HexGrid, NormalMode};
+
+	fn generated() -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::new(2, 2),
+			chunk_size: 4,
+			height_amplitude: 4.,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+	}
+
+	/// `map` saved to disk and read back.
+	fn reload(map: &HexMap, name: &str) -> HexMap {
+		let path = std::env::temp_dir().join(format!("hex-grid-{}-{name}.ron", std::process::id()));
+		map.save(&path).unwrap();
+		let loaded = HexMap::load(&path);
+		fs::remove_file(&path).unwrap();
+		return loaded.unwrap();
+	}
+
+	/// Vertex positions of a chunk mesh, relative to the chunk origin.
+	fn positions(index: UVec2, settings: &HexGridSettings) -> Vec<[f32; 3]> {
+		let mesh = HexGrid::chunk_mesh(index, settings);
+		let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+		return positions.as_float3().unwrap().to_vec();
+	}
+
+	/// World position of the center of the top of every tile of the first
+	/// `tiles`, read from the chunk meshes.
+	fn tile_centers(tiles: UVec2, settings: &HexGridSettings) -> Vec<Vec3> {
+		let size = settings.chunk_size;
+		let chunks = (0..settings.map_size.y)
+			.flat_map(|z| (0..settings.map_size.x).map(move |x| UVec2::new(x, z)))
+			.map(|index| (index, positions(index, settings)))
+			.collect::<HashMap<_, _>>();
+		return (0..tiles.y)
+			.flat_map(|z| (0..tiles.x).map(move |x| UVec2::new(x, z)))
+			.map(|tile| {
+				let index = tile / size;
+				let local = tile % size;
+				let vertex = ((local.x + local.y * size) * 7) as usize;
+				return chunk_origin(index, settings) + Vec3::from(chunks[&index][vertex]);
+			})
+			.collect();
+	}
+
+	#[test]
+	fn saved_maps_rebuild_the_same_chunks() {
+		let settings = generated();
+		let map = HexMap::from_settings(&settings);
+		let loaded = reload(&map, "same");
+		assert_eq!(loaded, map);
+		let mut rebuilt = generated();
+		loaded.apply(&mut rebuilt);
+		assert_eq!(rebuilt.map_size, settings.map_size);
+		for index in [UVec2::ZERO, UVec2::X, UVec2::Y, UVec2::ONE] {
+			assert_eq!(positions(index, &rebuilt), positions(index, &settings));
+		}
+	}
+
+	#[test]
+	fn saved_maps_rebuild_with_other_chunk_sizes() {
+		let settings = generated();
+		let map = reload(&HexMap::from_settings(&settings), "resized");
+		let tiles = settings.map_tiles();
+		let expected = tile_centers(tiles, &settings);
+		for chunk_size in [3, 5, 8] {
+			let mut rebuilt = HexGridSettings {
+				chunk_size,
+				..generated()
+			};
+			map.clone().apply(&mut rebuilt);
+			assert!(rebuilt.map_tiles().cmpge(tiles).all());
+			let centers = tile_centers(tiles, &rebuilt);
+			for (a, b) in expected.iter().zip(&centers) {
+				assert!(a.distance(*b) < 1e-4, "{a} {b}");
+			}
+			// tiles past the stored map repeat its edge
+			let edge = HexCoord::from_offset(tiles.x as i32 - 1, 0);
+			let past = HexCoord::from_offset(rebuilt.map_tiles().x as i32 - 1, 0);
+			assert_eq!(
+				HexGrid::height_at(past, &rebuilt),
+				HexGrid::height_at(edge, &rebuilt)
+			);
+		}
+	}
+}
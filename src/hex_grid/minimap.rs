@@ -0,0 +1,106 @@
+use bevy::{
+	prelude::*,
+	render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat},
+	},
+};
+
+use super::{height::sample_height, HexCoord, HexGrid, HexGridSettings};
+
+impl HexGrid {
+	/// Top-down picture of the map colored like the tile tops, with +z at the
+	/// top of the image. Pixels outside the map are transparent.
+	pub fn minimap(size: UVec2, settings: &HexGridSettings) -> Image {
+		let noise = settings.noise();
+		let bounds = settings.map_bounds();
+		let size = size.max(UVec2::ONE);
+		let pixel = bounds.size() / size.as_vec2();
+		let mut data = Vec::with_capacity((size.x * size.y * 4) as usize);
+		for row in 0..size.y {
+			for column in 0..size.x {
+				// sample the middle of each pixel
+				let pos = Vec2::new(
+					bounds.min.x + (column as f32 + 0.5) * pixel.x,
+					bounds.max.y - (row as f32 + 0.5) * pixel.y,
+				);
+				let coord = HexCoord::from_world(pos.extend(0.).xzy(), settings);
+				let color = match settings.contains(coord) {
+					true => {
+						let offset = coord.to_offset();
+						let height = sample_height(offset.x, offset.y, &noise, settings);
+						let neighbors = settings.color_neighbors(offset.x, offset.y, &noise);
+						settings.tile_color(offset.x, offset.y, height, &neighbors)
+					}
+					false => Color::NONE,
+				};
+				data.extend(color.to_srgba().to_u8_array());
+			}
+		}
+		return Image::new(
+			Extent3d {
+				width: size.x,
+				height: size.y,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			data,
+			TextureFormat::Rgba8UnormSrgb,
+			RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::hex_grid::{Biome, HexMap};
+
+	#[test]
+	fn minimap_colors_tiles_by_their_band() {
+		// water on the left half of the map, grass on the right
+		let mut settings = HexGridSettings {
+			map_size: UVec2::new(2, 1),
+			chunk_size: 8,
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = if i % 16 < 8 { 1. } else { 5. };
+			tile.biome.clear();
+		}
+		settings.map = Some(Arc::new(map));
+
+		let size = UVec2::new(64, 24);
+		let image = HexGrid::minimap(size, &settings);
+		assert_eq!(image.size(), size);
+		assert_eq!(image.data.len(), (size.x * size.y * 4) as usize);
+		let bands = Biome::default_bands();
+		let [water, grass] = [&bands[0], &bands[2]].map(|b| b.color.to_srgba().to_u8_array());
+		let bounds = settings.map_bounds();
+		let pixel = bounds.size() / size.as_vec2();
+		let mut counts = [0; 3];
+		for (i, rgba) in image.data.chunks_exact(4).enumerate() {
+			let (column, row) = (i as u32 % size.x, i as u32 / size.x);
+			let pos = Vec2::new(
+				bounds.min.x + (column as f32 + 0.5) * pixel.x,
+				bounds.max.y - (row as f32 + 0.5) * pixel.y,
+			);
+			let coord = HexCoord::from_world(pos.extend(0.).xzy(), &settings);
+			let (expected, count) = match (settings.contains(coord), coord.to_offset().x < 8) {
+				(false, _) => ([0; 4], &mut counts[0]),
+				(true, true) => (water, &mut counts[1]),
+				(true, false) => (grass, &mut counts[2]),
+			};
+			assert_eq!(rgba[3], expected[3]);
+			if expected[3] > 0 {
+				assert_eq!(rgba, expected);
+			}
+			*count += 1;
+		}
+		// the staggered rows leave a few pixels along the sides empty
+		assert!(counts.iter().all(|&count| count > 0), "{counts:?}");
+	}
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+	bevy_egui::{EguiContexts, EguiPlugin},
+	egui,
+};
+
+use super::{HexGridSettings, RegenerateMap};
+
+/// A window of sliders for the terrain settings. Edits are collected into a
+/// draft and only applied once the sliders have been left alone for
+/// `debounce` seconds, so dragging doesn't rebuild the map every frame.
+pub struct TerrainPanelPlugin {
+	pub debounce: f32,
+}
+
+impl Default for TerrainPanelPlugin {
+	fn default() -> Self {
+		return Self { debounce: 0.3 };
+	}
+}
+
+impl Plugin for TerrainPanelPlugin {
+	fn build(&self, app: &mut App) {
+		if !app.is_plugin_added::<EguiPlugin>() {
+			app.add_plugins(EguiPlugin);
+		}
+		app.insert_resource(TerrainPanel {
+			draft: None,
+			edited_at: None,
+			debounce: self.debounce,
+		})
+		.add_systems(Update, terrain_panel);
+	}
+}
+
+#[derive(Resource)]
+struct TerrainPanel {
+	draft: Option<TerrainDraft>,
+	/// When the draft was last edited, while it differs from the settings.
+	edited_at: Option<f32>,
+	debounce: f32,
+}
+
+/// The settings the panel shows. Only these are copied back onto the live
+/// settings, which other systems edit in the meantime without flagging them
+/// as changed.
+#[derive(Clone, Debug, PartialEq)]
+struct TerrainDraft {
+	seed: u32,
+	noise_scale: f64,
+	height_amplitude: f32,
+	sea_level: Option<f32>,
+	/// Octaves of each noise layer, in order.
+	octaves: Vec<usize>,
+	/// Name and `max_height` of each biome.
+	max_heights: Vec<(String, f32)>,
+}
+
+impl TerrainDraft {
+	fn new(settings: &HexGridSettings) -> Self {
+		return Self {
+			seed: settings.seed,
+			noise_scale: settings.noise_scale,
+			height_amplitude: settings.height_amplitude,
+			sea_level: settings.sea_level,
+			octaves: settings
+				.noise_layers
+				.iter()
+				.map(|layer| layer.octaves)
+				.collect(),
+			max_heights: settings
+				.biomes
+				.iter()
+				.map(|biome| (biome.name.clone(), biome.max_height))
+				.collect(),
+		};
+	}
+
+	fn apply(&self, settings: &mut HexGridSettings) {
+		settings.seed = self.seed;
+		settings.noise_scale = self.noise_scale;
+		settings.height_amplitude = self.height_amplitude;
+		settings.sea_level = self.sea_level;
+		for (layer, &octaves) in settings.noise_layers.iter_mut().zip(&self.octaves) {
+			layer.octaves = octaves;
+		}
+		for (name, max_height) in &self.max_heights {
+			if let Some(biome) = settings.biomes.iter_mut().find(|b| &b.name == name) {
+				biome.max_height = *max_height;
+			}
+		}
+	}
+}
+
+fn terrain_panel(
+	mut contexts: EguiContexts,
+	mut panel: ResMut<TerrainPanel>,
+	mut settings: ResMut<HexGridSettings>,
+	mut events: EventWriter<RegenerateMap>,
+	time: Res<Time>,
+) {
+	let panel = &mut *panel;
+	// follow outside changes unless there are edits waiting
+	if panel.edited_at.is_none() && (panel.draft.is_none() || settings.is_changed()) {
+		panel.draft = Some(TerrainDraft::new(&settings));
+	}
+	let Some(draft) = panel.draft.as_mut() else {
+		return;
+	};
+
+	let mut changed = false;
+	egui::Window::new("Terrain").show(contexts.ctx_mut(), |ui| {
+		changed |= ui
+			.add(egui::DragValue::new(&mut draft.seed).prefix("seed "))
+			.changed();
+		changed |= ui
+			.add(egui::Slider::new(&mut draft.noise_scale, 10.0..=2000.).text("noise scale"))
+			.changed();
+		changed |= ui
+			.add(egui::Slider::new(&mut draft.height_amplitude, 0.0..=4.).text("amplitude"))
+			.changed();
+		if let Some(sea_level) = draft.sea_level.as_mut() {
+			changed |= ui
+				.add(egui::Slider::new(sea_level, -10.0..=30.).text("sea level"))
+				.changed();
+		}
+
+		ui.collapsing("Noise layers", |ui| {
+			for (i, octaves) in draft.octaves.iter_mut().enumerate() {
+				changed |= ui
+					.add(egui::Slider::new(octaves, 1..=8).text(format!("octaves {i}")))
+					.changed();
+			}
+		});
+		ui.collapsing("Biomes", |ui| {
+			for (name, max_height) in &mut draft.max_heights {
+				// the last band usually reaches up forever
+				if max_height.is_finite() {
+					changed |= ui
+						.add(egui::Slider::new(max_height, -10.0..=60.).text(name.as_str()))
+						.changed();
+				}
+			}
+		});
+	});
+
+	let now = time.elapsed_seconds();
+	if changed {
+		panel.edited_at = Some(now);
+	}
+	let Some(edited_at) = panel.edited_at else {
+		return;
+	};
+	if now - edited_at < panel.debounce {
+		return;
+	}
+	panel.edited_at = None;
+	draft.apply(&mut settings);
+	// settings that rebuild on change already send their own request
+	if !settings.regenerate_on_change {
+		events.send(RegenerateMap);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::ecs::system::RunSystemOnce;
+
+	use super::*;
+	use crate::hex_grid::{DirtyChunks, HexCoord, HexData, HexGrid};
+
+	#[test]
+	fn applying_keeps_tile_edits() {
+		let mut world = World::new();
+		world.insert_resource(HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 4,
+			..default()
+		});
+		world.init_resource::<HexData>();
+		world.init_resource::<DirtyChunks>();
+		let mut draft = TerrainDraft::new(world.resource::<HexGridSettings>());
+		let coord = HexCoord::from_offset(1, 2);
+		world.run_system_once(
+			move |mut settings: ResMut<HexGridSettings>,
+			      mut data: ResMut<HexData>,
+			      mut dirty: ResMut<DirtyChunks>| {
+				HexGrid::set_height(coord, 9., &mut settings, &mut data, &mut dirty);
+			},
+		);
+
+		draft.seed += 1;
+		draft.height_amplitude = 2.;
+		draft.apply(&mut world.resource_mut::<HexGridSettings>());
+		let settings = world.resource::<HexGridSettings>();
+		assert_eq!(TerrainDraft::new(settings), draft);
+		assert!(settings.map.is_some());
+		assert_eq!(HexGrid::height_at(coord, settings), 9.);
+	}
+}
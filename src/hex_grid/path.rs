@@ -0,0 +1,203 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::utils::HashMap;
+
+use super::{HexCoord, HexGridSettings, WrapMode};
+
+/// Finds the cheapest path of tiles from `start` to `goal`, both included.
+///
+/// `cost` gives the price of stepping between two neighboring tiles, or `None`
+/// when the step is impassable. Steps should cost at least 1, otherwise the
+/// hex distance heuristic can overestimate and the path may not be the
+/// cheapest. Returns `None` when either end lies outside the map or the goal
+/// can't be reached.
+pub fn find_path(
+	start: HexCoord,
+	goal: HexCoord,
+	settings: &HexGridSettings,
+	mut cost: impl FnMut(HexCoord, HexCoord) -> Option<f32>,
+) -> Option<Vec<HexCoord>> {
+	if !settings.contains(start) || !settings.contains(goal) {
+		return None;
+	}
+
+	// hex distance ignores shortcuts across wrapped edges, so wrapped maps
+	// fall back to a plain Dijkstra search
+	let heuristic = |coord: HexCoord| match settings.wrap_mode() {
+		WrapMode::None => coord.distance(&goal) as f32,
+		_ => 0.,
+	};
+	let mut open = BinaryHeap::from([Open {
+		estimate: heuristic(start),
+		coord: start,
+	}]);
+	let mut came_from = HashMap::<HexCoord, HexCoord>::new();
+	let mut best = HashMap::from([(start, 0.)]);
+
+	while let Some(Open { coord, estimate }) = open.pop() {
+		if coord == goal {
+			let mut path = vec![goal];
+			while let Some(&prev) = came_from.get(path.last().unwrap()) {
+				path.push(prev);
+			}
+			path.reverse();
+			return Some(path);
+		}
+		let so_far = best[&coord];
+		// stale entry for a tile that has since been reached more cheaply
+		if estimate > so_far + heuristic(coord) {
+			continue;
+		}
+		for next in coord.neighbors_on_map(settings) {
+			let Some(step) = cost(coord, next) else {
+				continue;
+			};
+			let total = so_far + step;
+			if best.get(&next).is_some_and(|&known| known <= total) {
+				continue;
+			}
+			best.insert(next, total);
+			came_from.insert(next, coord);
+			open.push(Open {
+				estimate: total + heuristic(next),
+				coord: next,
+			});
+		}
+	}
+	return None;
+}
+
+/// Every tile reachable from `start` for at most `budget`, with the cost of
+/// the cheapest way there. `cost` prices steps like in `find_path`.
+pub fn reachable(
+	start: HexCoord,
+	budget: f32,
+	settings: &HexGridSettings,
+	mut cost: impl FnMut(HexCoord, HexCoord) -> Option<f32>,
+) -> HashMap<HexCoord, f32> {
+	let mut best = HashMap::new();
+	if !settings.contains(start) {
+		return best;
+	}
+	best.insert(start, 0.);
+	let mut open = BinaryHeap::from([Open {
+		estimate: 0.,
+		coord: start,
+	}]);
+	while let Some(Open { coord, estimate }) = open.pop() {
+		if estimate > best[&coord] {
+			continue;
+		}
+		for next in coord.neighbors_on_map(settings) {
+			let Some(step) = cost(coord, next) else {
+				continue;
+			};
+			let total = estimate + step;
+			if total > budget || best.get(&next).is_some_and(|&known| known <= total) {
+				continue;
+			}
+			best.insert(next, total);
+			open.push(Open {
+				estimate: total,
+				coord: next,
+			});
+		}
+	}
+	return best;
+}
+
+/// A tile waiting to be expanded, ordered so the heap pops the lowest estimate.
+struct Open {
+	estimate: f32,
+	coord: HexCoord,
+}
+
+impl PartialEq for Open {
+	fn eq(&self, other: &Self) -> bool {
+		return self.cmp(other) == Ordering::Equal;
+	}
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		return Some(self.cmp(other));
+	}
+}
+
+impl Ord for Open {
+	fn cmp(&self, other: &Self) -> Ordering {
+		return other.estimate.total_cmp(&self.estimate);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{prelude::*, utils::HashSet};
+
+	use super::*;
+
+	fn map() -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 8,
+			..default()
+		};
+	}
+
+	/// Steps cost 1, except into `walls`, which can't be entered.
+	fn walled(walls: &HashSet<HexCoord>) -> impl FnMut(HexCoord, HexCoord) -> Option<f32> + '_ {
+		return move |_, to| match walls.contains(&to) {
+			true => None,
+			false => Some(1.),
+		};
+	}
+
+	fn assert_connected(path: &[HexCoord]) {
+		for step in path.windows(2) {
+			assert_eq!(step[0].distance(&step[1]), 1, "{path:?}");
+		}
+	}
+
+	#[test]
+	fn open_ground_walks_straight() {
+		let settings = map();
+		let (start, goal) = (HexCoord::from_offset(1, 2), HexCoord::from_offset(6, 2));
+		let path = find_path(start, goal, &settings, walled(&HashSet::new())).unwrap();
+		let row = (1..=6)
+			.map(|x| HexCoord::from_offset(x, 2))
+			.collect::<Vec<_>>();
+		assert_eq!(path, row);
+		assert_eq!(
+			find_path(start, start, &settings, |_, _| Some(1.)),
+			Some(vec![start])
+		);
+	}
+
+	#[test]
+	fn paths_detour_around_walls() {
+		let settings = map();
+		// a wall across every row but the last
+		let walls = (0..7).map(|z| HexCoord::from_offset(4, z)).collect();
+		let (start, goal) = (HexCoord::from_offset(1, 2), HexCoord::from_offset(6, 2));
+		let path = find_path(start, goal, &settings, walled(&walls)).unwrap();
+		assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+		assert_connected(&path);
+		assert!(path.iter().all(|tile| !walls.contains(tile)));
+		assert!(path.iter().any(|tile| tile.to_offset().y == 7));
+		assert!(path.len() > start.distance(&goal) as usize + 1);
+	}
+
+	#[test]
+	fn walled_off_goals_have_no_path() {
+		let settings = map();
+		let goal = HexCoord::from_offset(5, 5);
+		let walls = goal.neighbors().into_iter().collect();
+		let start = HexCoord::from_offset(1, 1);
+		assert_eq!(find_path(start, goal, &settings, walled(&walls)), None);
+		// and neither do tiles off the map
+		let outside = HexCoord::from_offset(8, 0);
+		assert_eq!(find_path(start, outside, &settings, |_, _| Some(1.)), None);
+	}
+}
@@ -0,0 +1,274 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use super::{
+	height::{height_bounds, sample_height},
+	HexCoord, HexGridSettings,
+};
+
+/// The tile currently under the cursor, if any.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Deref)]
+pub struct SelectedHex(pub Option<HexCoord>);
+
+/// Sent when the left mouse button is pressed over a tile.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexClicked(pub HexCoord);
+
+/// How the tile under the cursor is found.
+#[derive(Reflect, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum PickingMode {
+	/// Intersects the cursor ray with a level plane halfway up the map's
+	/// height range, exact on flat maps and close on nearly flat ones.
+	#[default]
+	Plane,
+	/// Marches the cursor ray across the height field, finding the tile in
+	/// front where hills hide the ones behind them.
+	Terrain,
+}
+
+pub(super) fn pick_hex(
+	windows: Query<&Window, With<PrimaryWindow>>,
+	cameras: Query<(&Camera, &GlobalTransform), With<PanOrbitCamera>>,
+	buttons: Res<ButtonInput<MouseButton>>,
+	settings: Res<HexGridSettings>,
+	mut selected: ResMut<SelectedHex>,
+	mut clicked: EventWriter<HexClicked>,
+) {
+	let ray = windows
+		.get_single()
+		.ok()
+		.and_then(|window| window.cursor_position())
+		.zip(cameras.get_single().ok())
+		.and_then(|(cursor, (camera, transform))| camera.viewport_to_world(transform, cursor));
+
+	let hit = ray.and_then(|ray| match settings.picking {
+		PickingMode::Plane => raycast_plane(ray, &settings),
+		PickingMode::Terrain => raycast_terrain(ray, &settings),
+	});
+	if selected.0 != hit {
+		selected.0 = hit;
+	}
+
+	if let Some(coord) = hit {
+		if buttons.just_pressed(MouseButton::Left) {
+			clicked.send(HexClicked(coord));
+		}
+	}
+}
+
+/// The tile where `ray` meets the level plane halfway up the map's height
+/// range, or `None` if the tile is off the map or the ray runs level or away
+/// from the plane, like a camera looking up at the sky.
+pub fn raycast_plane(ray: Ray3d, settings: &HexGridSettings) -> Option<HexCoord> {
+	let (low, high) = height_bounds(settings);
+	let ground = Vec3::Y * (settings.origin.y + (low + high) * 0.5);
+	let t = ray.intersect_plane(ground, InfinitePlane3d::new(Vec3::Y))?;
+	let coord = HexCoord::from_world(ray.get_point(t), settings);
+	return settings.contains(coord).then_some(coord);
+}
+
+/// Marches `ray` across the height field and returns the first tile whose
+/// column it enters, or `None` if it misses the map.
+pub fn raycast_terrain(ray: Ray3d, settings: &HexGridSettings) -> Option<HexCoord> {
+	let bounds = settings.map_bounds();
+	let (low, high) = height_bounds(settings);
+	// a hair below the lowest tile, so rays landing on it aren't lost to
+	// rounding, even on flat maps where the box has no height
+	let (low, high) = (low + settings.origin.y - 1e-3, high + settings.origin.y);
+	let (t_start, t_end) = ray_box(
+		ray,
+		Vec3::new(bounds.min.x, low, bounds.min.y),
+		Vec3::new(bounds.max.x, high, bounds.max.y),
+	)?;
+
+	let noise = settings.noise();
+	let step = settings.inner_radius() * 0.5;
+	let mut last = None;
+	let mut height = f32::NEG_INFINITY;
+	let mut t = t_start;
+	loop {
+		let p = ray.get_point(t);
+		let coord = HexCoord::from_world(p, settings);
+		if last != Some(coord) {
+			last = Some(coord);
+			height = if settings.contains(coord) {
+				let offset = coord.to_offset();
+				settings.origin.y + sample_height(offset.x, offset.y, &noise, settings)
+			} else {
+				f32::NEG_INFINITY
+			};
+		}
+		if p.y <= height {
+			return Some(coord);
+		}
+		// the last step lands on the far side of the box
+		if t >= t_end {
+			return None;
+		}
+		t = (t + step).min(t_end);
+	}
+}
+
+/// Range of `t` along `ray` that lies inside the box, clamped to `t >= 0`.
+fn ray_box(ray: Ray3d, min: Vec3, max: Vec3) -> Option<(f32, f32)> {
+	let inv = ray.direction.recip();
+	let a = (min - ray.origin) * inv;
+	let b = (max - ray.origin) * inv;
+	let t_start = a.min(b).max_element().max(0.);
+	let t_end = a.max(b).min_element();
+	if t_start > t_end {
+		return None;
+	}
+	return Some((t_start, t_end));
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::render::camera::CameraProjection;
+
+	use super::*;
+	use std::sync::Arc;
+
+	use crate::hex_grid::{HexGrid, HexMap, NoiseType};
+
+	const VIEWPORT: Vec2 = Vec2::new(1280., 720.);
+
+	/// A flat map away from the world origin, with a camera looking down at
+	/// its middle from an angle.
+	fn flat_map() -> (HexGridSettings, GlobalTransform, Mat4) {
+		let settings = HexGridSettings {
+			map_size: UVec2::new(2, 2),
+			chunk_size: 8,
+			noise_type: NoiseType::Flat,
+			height_offset: 2.,
+			origin: Vec3::new(3., 1., -2.),
+			..default()
+		};
+		let target = settings.map_bounds().center();
+		let target = Vec3::new(target.x, settings.origin.y + 2., target.y);
+		let camera = GlobalTransform::from(
+			Transform::from_translation(target + Vec3::new(4., 40., 24.))
+				.looking_at(target, Vec3::Y),
+		);
+		let mut projection = PerspectiveProjection::default();
+		projection.update(VIEWPORT.x, VIEWPORT.y);
+		return (settings, camera, projection.get_clip_from_view());
+	}
+
+	/// Where `pos` shows in the viewport, in pixels from the top left like
+	/// cursor positions.
+	fn to_screen(pos: Vec3, camera: &GlobalTransform, clip_from_view: Mat4) -> Vec2 {
+		let ndc = (clip_from_view * camera.compute_matrix().inverse()).project_point3(pos);
+		let screen = (ndc.truncate() + Vec2::ONE) * 0.5 * VIEWPORT;
+		return Vec2::new(screen.x, VIEWPORT.y - screen.y);
+	}
+
+	/// The ray under the cursor at `screen`, built like
+	/// `Camera::viewport_to_world`, which needs a render target to size the
+	/// viewport.
+	fn cursor_ray(screen: Vec2, camera: &GlobalTransform, clip_from_view: Mat4) -> Ray3d {
+		let ndc = Vec2::new(screen.x, VIEWPORT.y - screen.y) * 2. / VIEWPORT - Vec2::ONE;
+		let ndc_to_world = camera.compute_matrix() * clip_from_view.inverse();
+		let near = ndc_to_world.project_point3(ndc.extend(1.));
+		let far = ndc_to_world.project_point3(ndc.extend(f32::EPSILON));
+		return Ray3d::new(near, far - near);
+	}
+
+	#[test]
+	fn plane_picks_the_tile_under_the_cursor() {
+		let (settings, camera, clip_from_view) = flat_map();
+		let inner = settings.inner_radius();
+		for (x, z) in [(0, 0), (7, 3), (8, 8), (15, 15), (3, 12), (12, 1)] {
+			let coord = HexCoord::from_offset(x, z);
+			let center = coord.to_world(&settings) + Vec3::Y * HexGrid::height_at(coord, &settings);
+			// near the edges as well as the middle of the tile
+			for offset in [
+				Vec2::ZERO,
+				Vec2::X * 0.9,
+				Vec2::NEG_Y * 0.9,
+				Vec2::splat(-0.6),
+			] {
+				let pos = center + Vec3::new(offset.x, 0., offset.y) * inner;
+				let screen = to_screen(pos, &camera, clip_from_view);
+				assert!(screen.cmpge(Vec2::ZERO).all() && screen.cmple(VIEWPORT).all());
+				let ray = cursor_ray(screen, &camera, clip_from_view);
+				assert_eq!(raycast_plane(ray, &settings), Some(coord), "at {screen}");
+			}
+		}
+	}
+
+	#[test]
+	fn plane_misses_the_sky_and_off_the_map() {
+		let (settings, camera, clip_from_view) = flat_map();
+		let above = Vec3::new(10., 30., 10.);
+		assert_eq!(raycast_plane(Ray3d::new(above, Vec3::Y), &settings), None);
+		assert_eq!(raycast_plane(Ray3d::new(above, Vec3::X), &settings), None);
+		assert_eq!(
+			raycast_plane(Ray3d::new(above, Vec3::new(1., 0.2, 0.)), &settings),
+			None
+		);
+
+		let bounds = settings.map_bounds();
+		let outside = Vec3::new(bounds.max.x + 5., settings.origin.y + 2., bounds.min.y);
+		let screen = to_screen(outside, &camera, clip_from_view);
+		let ray = cursor_ray(screen, &camera, clip_from_view);
+		assert_eq!(raycast_plane(ray, &settings), None);
+	}
+
+	/// `flat_map` stored, with the tile at offset (`x`, `z`) raised to
+	/// `height`.
+	fn hill(x: i32, z: i32, height: f32) -> HexGridSettings {
+		let (mut settings, ..) = flat_map();
+		let mut map = HexMap::from_settings(&settings);
+		let width = settings.map_tiles().x as i32;
+		map.tiles[(z * width + x) as usize].height = height;
+		settings.map = Some(Arc::new(map));
+		return settings;
+	}
+
+	/// Top center of the tile at `coord`.
+	fn top(coord: HexCoord, settings: &HexGridSettings) -> Vec3 {
+		return coord.to_world(settings) + Vec3::Y * HexGrid::height_at(coord, settings);
+	}
+
+	#[test]
+	fn terrain_picks_the_hill_in_front() {
+		let (hill_coord, behind) = (HexCoord::from_offset(6, 8), HexCoord::from_offset(8, 8));
+		let settings = hill(6, 8, 10.);
+		let (peak, target) = (top(hill_coord, &settings), top(behind, &settings));
+		// through the middle of the hill's column, three quarters of the way
+		// up, to the top of the tile two steps behind it
+		let through = Vec3::new(peak.x, target.y + (peak.y - target.y) * 0.75, peak.z);
+		let origin = target + (through - target) * 3.;
+		let ray = Ray3d::new(origin, target - origin);
+		assert_eq!(raycast_terrain(ray, &settings), Some(hill_coord));
+		// the plane halfway up the map sees past the hill
+		assert_ne!(raycast_plane(ray, &settings), Some(hill_coord));
+
+		// with the hill leveled the ray reaches the tile behind it
+		let settings = hill(6, 8, target.y - settings.origin.y);
+		assert_eq!(raycast_terrain(ray, &settings), Some(behind));
+	}
+
+	#[test]
+	fn terrain_misses_the_sky_and_off_the_map() {
+		let settings = hill(6, 8, 10.);
+		let bounds = settings.map_bounds();
+		let above = Vec3::new(bounds.center().x, 30., bounds.center().y);
+		// up into the sky and level above the highest tile
+		assert_eq!(raycast_terrain(Ray3d::new(above, Vec3::Y), &settings), None);
+		assert_eq!(raycast_terrain(Ray3d::new(above, Vec3::X), &settings), None);
+		// down beside the map
+		let outside = Vec3::new(bounds.max.x + 5., 30., bounds.min.y);
+		assert_eq!(
+			raycast_terrain(Ray3d::new(outside, Vec3::NEG_Y), &settings),
+			None
+		);
+		// down at a slant that leaves the map before reaching the ground
+		let edge = Vec3::new(bounds.max.x - 0.1, 12., bounds.center().y);
+		assert_eq!(
+			raycast_terrain(Ray3d::new(edge, Vec3::new(1., -0.1, 0.)), &settings),
+			None
+		);
+	}
+}
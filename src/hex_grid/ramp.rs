@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+
+use super::{HexGridSettings, RegenerateMap};
+
+/// Colors spread evenly from the lowest to the highest terrain, like the
+/// pixels of a gradient image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+	pub colors: Vec<Color>,
+}
+
+impl ColorRamp {
+	/// The first row of `image`, which can be any width. Returns `None` for
+	/// empty images and formats other than 8-bit RGBA.
+	pub fn from_image(image: &Image) -> Option<Self> {
+		let width = image.width() as usize;
+		let srgb = match image.texture_descriptor.format {
+			TextureFormat::Rgba8UnormSrgb => true,
+			TextureFormat::Rgba8Unorm => false,
+			_ => return None,
+		};
+		let row = image.data.get(..width * 4)?;
+		let colors = row
+			.chunks_exact(4)
+			.map(|p| match srgb {
+				true => Color::srgba_u8(p[0], p[1], p[2], p[3]),
+				false => Color::linear_rgba(
+					p[0] as f32 / 255.,
+					p[1] as f32 / 255.,
+					p[2] as f32 / 255.,
+					p[3] as f32 / 255.,
+				),
+			})
+			.collect::<Vec<_>>();
+		return (!colors.is_empty()).then_some(Self { colors });
+	}
+
+	/// Color at `t` between 0, the first color, and 1, the last, blending
+	/// the two nearest colors and clamping outside.
+	pub fn sample(&self, t: f32) -> Color {
+		let Some(&last) = self.colors.last() else {
+			return Color::WHITE;
+		};
+		let pos = t.clamp(0., 1.) * (self.colors.len() - 1) as f32;
+		let i = pos.floor() as usize;
+		let Some(&next) = self.colors.get(i + 1) else {
+			return last;
+		};
+		let (a, b) = (
+			self.colors[i].to_srgba().to_f32_array(),
+			next.to_srgba().to_f32_array(),
+		);
+		let f = pos - i as f32;
+		let mix = |k: usize| a[k] + (b[k] - a[k]) * f;
+		return Color::srgba(mix(0), mix(1), mix(2), mix(3));
+	}
+}
+
+/// Reads `HexGridSettings::color_ramp` into `HexGridSettings::ramp` once
+/// the image loads, and again whenever it changes, rebuilding the map.
+pub(super) fn load_color_ramp(
+	mut loaded: Local<Option<AssetId<Image>>>,
+	mut events: EventReader<AssetEvent<Image>>,
+	mut regenerate: EventWriter<RegenerateMap>,
+	images: Res<Assets<Image>>,
+	mut settings: ResMut<HexGridSettings>,
+) {
+	let Some(handle) = settings.color_ramp.clone() else {
+		*loaded = None;
+		return;
+	};
+	let modified = events.read().any(|event| {
+		event.is_modified(handle.id()) || event.is_loaded_with_dependencies(handle.id())
+	});
+	if *loaded == Some(handle.id()) && !modified {
+		return;
+	}
+	let Some(image) = images.get(&handle) else {
+		return;
+	};
+	*loaded = Some(handle.id());
+	let ramp = ColorRamp::from_image(image);
+	if ramp.is_none() {
+		warn!(
+			"color ramp has unsupported format {:?}",
+			image.texture_descriptor.format
+		);
+	}
+	// only the chunks are rebuilt, not everything watching the settings
+	settings.bypass_change_detection().ramp = ramp.map(Arc::new);
+	regenerate.send(RegenerateMap);
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension},
+	};
+
+	use super::*;
+
+	fn gradient(pixels: &[[u8; 4]], format: TextureFormat) -> Image {
+		return Image::new(
+			Extent3d {
+				width: pixels.len() as u32,
+				height: 1,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			pixels.concat(),
+			format,
+			RenderAssetUsages::MAIN_WORLD,
+		);
+	}
+
+	#[test]
+	fn ramp_ends_are_the_first_and_last_pixel() {
+		let pixels = [[10, 40, 200, 255], [90, 90, 90, 255], [250, 120, 5, 128]];
+		let ramp =
+			ColorRamp::from_image(&gradient(&pixels, TextureFormat::Rgba8UnormSrgb)).unwrap();
+		let [first, .., last] = pixels.map(|[r, g, b, a]| Color::srgba_u8(r, g, b, a));
+		assert_eq!(ramp.sample(0.), first);
+		assert_eq!(ramp.sample(1.), last);
+		// clamped outside
+		assert_eq!(ramp.sample(-2.), first);
+		assert_eq!(ramp.sample(3.), last);
+
+		let single =
+			ColorRamp::from_image(&gradient(&pixels[..1], TextureFormat::Rgba8Unorm)).unwrap();
+		assert_eq!(single.sample(0.), single.sample(1.));
+		let gray = Image::new_fill(
+			Extent3d::default(),
+			TextureDimension::D2,
+			&[128],
+			TextureFormat::R8Unorm,
+			RenderAssetUsages::MAIN_WORLD,
+		);
+		assert!(ColorRamp::from_image(&gray).is_none());
+	}
+}
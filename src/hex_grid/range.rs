@@ -0,0 +1,180 @@
+use bevy::{color::palettes::css, prelude::*, utils::HashMap};
+
+use super::{
+	outline_tile, path::reachable, sample_height, HexCoord, HexData, HexGridSettings, SelectedHex,
+	TerrainNoise,
+};
+
+/// Outlines every tile a unit on the selected tile could move to.
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct MoveRange {
+	/// Movement a unit can spend, each step costing the `TileData::move_cost`
+	/// of the tile stepped onto.
+	pub steps: f32,
+	/// Height difference above which a step is too steep to take.
+	pub max_climb: f32,
+	pub color: Color,
+}
+
+impl Default for MoveRange {
+	fn default() -> Self {
+		return Self {
+			steps: 4.,
+			max_climb: 1.,
+			color: css::AQUA.into(),
+		};
+	}
+}
+
+impl MoveRange {
+	/// Tiles reachable from `start`, each step costing the move cost `data`
+	/// gives the tile stepped onto, or 1. Steps onto tiles `data` marks
+	/// impassable or climbing more than `max_climb` are blocked.
+	pub fn reachable(
+		&self,
+		start: HexCoord,
+		data: &HexData,
+		settings: &HexGridSettings,
+	) -> HashMap<HexCoord, f32> {
+		return self.reachable_with(start, data, &settings.noise(), settings);
+	}
+
+	/// `reachable`, sampling the heights from `noise`.
+	pub(super) fn reachable_with(
+		&self,
+		start: HexCoord,
+		data: &HexData,
+		noise: &TerrainNoise,
+		settings: &HexGridSettings,
+	) -> HashMap<HexCoord, f32> {
+		let mut heights = HashMap::new();
+		let mut height = |coord: HexCoord| {
+			return *heights.entry(coord).or_insert_with(|| {
+				let offset = coord.to_offset();
+				return sample_height(offset.x, offset.y, noise, settings);
+			});
+		};
+		return reachable(start, self.steps, settings, |from, to| {
+			let tile = data.get(to);
+			if tile.is_some_and(|tile| !tile.passable) {
+				return None;
+			}
+			let climb = (height(to) - height(from)).abs();
+			let cost = tile.map_or(1., |tile| tile.move_cost);
+			return (climb <= self.max_climb).then_some(cost);
+		});
+	}
+}
+
+/// Top centers of every tile in a `MoveRange`, kept between frames while
+/// nothing moves.
+#[derive(Default)]
+pub(super) struct RangeOutline(Vec<(HexCoord, Vec3)>);
+
+impl RangeOutline {
+	fn new(
+		move_range: &MoveRange,
+		start: HexCoord,
+		data: &HexData,
+		settings: &HexGridSettings,
+	) -> Self {
+		let noise = settings.noise();
+		let center = |coord: HexCoord| {
+			let offset = coord.to_offset();
+			let height = sample_height(offset.x, offset.y, &noise, settings);
+			return coord.to_world(settings) + Vec3::Y * height;
+		};
+		return Self(
+			move_range
+				.reachable_with(start, data, &noise, settings)
+				.into_keys()
+				.map(|coord| (coord, center(coord)))
+				.collect(),
+		);
+	}
+}
+
+pub(super) fn draw_move_range(
+	mut gizmos: Gizmos,
+	mut range: Local<RangeOutline>,
+	selected: Res<SelectedHex>,
+	data: Res<HexData>,
+	settings: Res<HexGridSettings>,
+) {
+	let (Some(move_range), Some(start)) = (settings.move_range, selected.0) else {
+		range.0.clear();
+		return;
+	};
+	// searching and sampling again every frame would be wasted while nothing
+	// moves
+	if range.0.is_empty() || selected.is_changed() || data.is_changed() || settings.is_changed() {
+		*range = RangeOutline::new(&move_range, start, &data, &settings);
+	}
+	for &(_, center) in range.0.iter() {
+		outline_tile(&mut gizmos, center, move_range.color, &settings);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bevy::utils::HashSet;
+
+	use super::*;
+	use crate::hex_grid::{find_path, HexMap};
+
+	#[test]
+	fn outlines_cover_the_tiles_within_reach() {
+		// level ground with a wall along x = 3 too high to climb, and a
+		// step up onto x = 0 low enough to take
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 6,
+			..default()
+		};
+		let height = |offset: IVec2| match offset.x {
+			0 => 1.8,
+			3 => 5.,
+			_ => 1.,
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = height(IVec2::new(i as i32 % 6, i as i32 / 6));
+		}
+		settings.map = Some(Arc::new(map));
+		let move_range = MoveRange {
+			steps: 3.,
+			..default()
+		};
+		let start = HexCoord::from_offset(1, 2);
+		let data = HexData::default();
+
+		// every tile a path of at most 3 climbable steps leads to
+		let climbable = |from: HexCoord, to: HexCoord| {
+			let climb = (height(to.to_offset()) - height(from.to_offset())).abs();
+			return (climb <= 1.).then_some(1.);
+		};
+		let expected = (0..36)
+			.map(|i| HexCoord::from_offset(i % 6, i / 6))
+			.filter(|&goal| {
+				find_path(start, goal, &settings, climbable).is_some_and(|path| path.len() <= 4)
+			})
+			.collect::<HashSet<_>>();
+
+		let outline = RangeOutline::new(&move_range, start, &data, &settings);
+		let outlined = outline
+			.0
+			.iter()
+			.map(|(coord, _)| *coord)
+			.collect::<HashSet<_>>();
+		assert_eq!(outlined, expected);
+		assert!(outlined.len() > 6);
+		assert!(outlined.iter().all(|coord| coord.to_offset().x < 3));
+		assert!(outlined.contains(&HexCoord::from_offset(0, 2)));
+		for (coord, center) in outline.0 {
+			let top = coord.to_world(&settings) + Vec3::Y * height(coord.to_offset());
+			assert!(center.distance(top) < 1e-5, "{center} {top}");
+		}
+	}
+}
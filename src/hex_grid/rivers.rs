@@ -0,0 +1,276 @@
+use bevy::prelude::*;
+
+use super::{HexCoord, HexGridSettings, HexMap};
+
+/// Rivers traced downhill over a `HexMap` from its highest tiles.
+#[derive(Reflect, Clone, Debug)]
+pub struct Rivers {
+	/// Most rivers to trace.
+	pub count: u32,
+	/// Lowest height a river can start at.
+	pub source_height: f32,
+	/// Fewest tiles between two river sources.
+	pub spacing: u32,
+	/// How far river tiles are carved below their original height.
+	pub depth: f32,
+	/// Biome given to river and lake tiles, which also picks their color.
+	pub biome: String,
+}
+
+impl Default for Rivers {
+	fn default() -> Self {
+		return Self {
+			count: 8,
+			source_height: 12.,
+			spacing: 8,
+			depth: 0.3,
+			biome: "water".into(),
+		};
+	}
+}
+
+impl HexMap {
+	/// Traces rivers down the steepest neighbor until they reach the sea, the
+	/// edge of the map or another river. Rivers stuck in a pit flood its
+	/// neighbors into a small lake. Returns the tiles of each river from its
+	/// source downstream, ending on the tile it merges into, if any. Fills the
+	/// map first.
+	pub fn carve_rivers(
+		&mut self,
+		rivers: &Rivers,
+		settings: &HexGridSettings,
+	) -> Vec<Vec<HexCoord>> {
+		self.fill(settings);
+		let neighbors = self.neighbor_indices();
+		let width = self.size.x as usize;
+		let coord = |i: usize| HexCoord::from_offset((i % width) as i32, (i / width) as i32);
+		let sea_level = settings.sea_level.unwrap_or(f32::NEG_INFINITY);
+
+		let mut heights = self
+			.tiles
+			.iter()
+			.map(|tile| tile.height)
+			.collect::<Vec<_>>();
+		let mut sources = (0..heights.len())
+			.filter(|&i| heights[i] >= rivers.source_height && heights[i] > sea_level)
+			.collect::<Vec<_>>();
+		sources.sort_by(|&a, &b| heights[b].total_cmp(&heights[a]));
+
+		let mut river = vec![false; heights.len()];
+		let mut started = Vec::<HexCoord>::new();
+		let mut paths = Vec::new();
+		for source in sources {
+			if started.len() as u32 >= rivers.count {
+				break;
+			}
+			let start = coord(source);
+			if river[source] || started.iter().any(|s| s.distance(&start) < rivers.spacing) {
+				continue;
+			}
+			started.push(start);
+
+			let mut path = vec![source];
+			let mut merged = false;
+			let mut lake = false;
+			loop {
+				let tile = *path.last().unwrap();
+				river[tile] = true;
+				// flows off the map
+				if neighbors[tile].len() < 6 {
+					break;
+				}
+				let next = neighbors[tile]
+					.iter()
+					.copied()
+					.min_by(|&a, &b| heights[a].total_cmp(&heights[b]))
+					.unwrap();
+				if heights[next] >= heights[tile] {
+					lake = true;
+					break;
+				}
+				if heights[next] <= sea_level {
+					break;
+				}
+				path.push(next);
+				if river[next] {
+					merged = true;
+					break;
+				}
+			}
+
+			// carve from the mouth upwards so no tile ends up below its
+			// downstream neighbor, leaving a river it merged into untouched
+			let carved = path.len() - merged as usize;
+			let mut below = match merged {
+				true => heights[path[carved]],
+				false => f32::NEG_INFINITY,
+			};
+			for &tile in path[..carved].iter().rev() {
+				heights[tile] = (heights[tile] - rivers.depth).max(below);
+				below = heights[tile];
+			}
+			if lake {
+				let pit = *path.last().unwrap();
+				for &n in &neighbors[pit] {
+					if !river[n] {
+						river[n] = true;
+						heights[n] = heights[n].min(heights[pit]);
+					}
+				}
+			}
+			paths.push(path.into_iter().map(coord).collect());
+		}
+
+		for ((tile, height), river) in self.tiles.iter_mut().zip(heights).zip(river) {
+			tile.height = height;
+			if river {
+				tile.biome.clone_from(&rivers.biome);
+			}
+		}
+		return paths;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::HexTile;
+
+	fn map(size: UVec2, height: impl Fn(IVec2) -> f32) -> HexMap {
+		let tiles = (0..size.y as i32)
+			.flat_map(|z| (0..size.x as i32).map(move |x| IVec2::new(x, z)))
+			.map(|offset| HexTile {
+				height: height(offset),
+				biome: String::new(),
+				spans: Vec::new(),
+			})
+			.collect();
+		return HexMap {
+			size,
+			tiles,
+			captured: Vec::new(),
+		};
+	}
+
+	fn carve(map: &mut HexMap, rivers: Rivers) -> Vec<Vec<HexCoord>> {
+		let settings = HexGridSettings {
+			sea_level: Some(1.),
+			..default()
+		};
+		let paths = map.carve_rivers(&rivers, &settings);
+		assert!(!paths.is_empty());
+		for path in &paths {
+			for pair in path.windows(2) {
+				assert_eq!(pair[0].distance(&pair[1]), 1);
+				let [up, down] = [pair[0], pair[1]].map(|c| c.to_offset());
+				assert!(
+					map.height(down.x, down.y) <= map.height(up.x, up.y),
+					"{path:?}"
+				);
+			}
+		}
+		return paths;
+	}
+
+	/// Rows rising towards the middle of the map, so rivers start away from
+	/// its edges.
+	fn ridge(z: i32, rows: i32) -> f32 {
+		return -((z as f32 - (rows - 1) as f32 * 0.5).abs()) * 0.3;
+	}
+
+	#[test]
+	fn rivers_flow_downhill_to_the_sea() {
+		// a ridge along column 12, falling steeply towards the first one, which
+		// lies below the sea
+		let mut map = map(UVec2::new(16, 8), |o| {
+			let fall = match o.x < 12 {
+				true => (12 - o.x) as f32 * 2.,
+				false => (o.x - 12) as f32,
+			};
+			return 20. - fall + ridge(o.y, 8);
+		});
+		let rivers = Rivers {
+			count: 2,
+			source_height: 18.,
+			spacing: 2,
+			..default()
+		};
+		for path in carve(&mut map, rivers) {
+			assert!(path.len() > 5, "{path:?}");
+			// the mouth borders the sea
+			let mouth = path.last().unwrap();
+			let sea = mouth.neighbors().into_iter().any(|n| {
+				let n = n.to_offset();
+				return map.height(n.x, n.y) <= 1.;
+			});
+			assert!(sea, "{path:?}");
+			for tile in &path {
+				let offset = tile.to_offset();
+				assert_eq!(map.tile(offset.x, offset.y).unwrap().biome, "water");
+			}
+		}
+	}
+
+	#[test]
+	fn rivers_stuck_in_a_pit_flood_a_lake() {
+		// a rim 4 tiles around (6, 6), dropping 2 per tile into its middle
+		// and gently outside it
+		let center = HexCoord::from_offset(6, 6);
+		let mut map = map(UVec2::splat(13), |o| {
+			let d = HexCoord::from_offset(o.x, o.y).distance(&center) as f32;
+			return match d <= 4. {
+				true => 10. + d * 2.,
+				false => 18. - (d - 4.) * 0.5,
+			};
+		});
+		let paths = carve(
+			&mut map,
+			Rivers {
+				count: 1,
+				source_height: 18.,
+				..default()
+			},
+		);
+		assert_eq!(paths[0].len(), 5);
+		assert_eq!(paths[0].last(), Some(&center));
+		// the pit's neighbors off the river flood to its height
+		let pit = map.height(6, 6);
+		for n in center.neighbors() {
+			let n = n.to_offset();
+			if paths[0].contains(&HexCoord::from_offset(n.x, n.y)) {
+				continue;
+			}
+			assert_eq!(map.height(n.x, n.y), pit);
+			assert_eq!(map.tile(n.x, n.y).unwrap().biome, "water");
+		}
+	}
+
+	#[test]
+	fn rivers_merge_into_earlier_ones() {
+		// a valley along column 6 between ridges along columns 2 and 10,
+		// falling steeply into it and towards the first row
+		let mut map = map(UVec2::new(13, 12), |o| {
+			let across = (o.x - 6).abs() as f32;
+			let across = match across <= 4. {
+				true => across * 2.,
+				false => 8. - (across - 4.) * 0.5,
+			};
+			let along = o.y.min(9) as f32 - (o.y - 9).max(0) as f32 * 0.25;
+			return 2. + across + along;
+		});
+		let paths = carve(
+			&mut map,
+			Rivers {
+				count: 2,
+				source_height: 18.,
+				spacing: 4,
+				..default()
+			},
+		);
+		assert_eq!(paths.len(), 2);
+		// the second river ends on a tile of the first
+		let joined = paths[1].last().unwrap();
+		assert!(paths[0].contains(joined), "{paths:?}");
+		assert!(!paths[0].contains(&paths[1][paths[1].len() - 2]));
+	}
+}
@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use super::{HexCoord, HexGridSettings};
+
+/// Mixed into the map seed, so the sequence has nothing in common with the
+/// noise seeded by it.
+const RNG_SALT: u64 = 0x5851_f42d_4c95_7f2d;
+
+/// Random numbers for placing things on the map, reseeded from
+/// `HexGridSettings::seed` whenever it changes. The same seed always gives
+/// the same sequence, and drawing from it never changes the terrain.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct GridRng {
+	seed: u32,
+	state: u64,
+}
+
+impl GridRng {
+	pub fn new(seed: u32) -> Self {
+		return Self {
+			seed,
+			state: seed as u64 ^ RNG_SALT,
+		};
+	}
+
+	/// A sequence of its own for `keys`, e.g. a tile's offset coordinates,
+	/// the same for the same seed and keys whatever is drawn elsewhere.
+	pub fn keyed(seed: u32, keys: &[u32]) -> Self {
+		let mut rng = Self::new(seed);
+		for &key in keys {
+			rng.state = rng.next_u64() ^ key as u64;
+		}
+		return rng;
+	}
+
+	/// The map seed the sequence started from.
+	pub fn seed(&self) -> u32 {
+		return self.seed;
+	}
+
+	/// Next number of the sequence, a SplitMix64 step.
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut x = self.state;
+		x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		return x ^ (x >> 31);
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		return (self.next_u64() >> 32) as u32;
+	}
+
+	/// Uniform in [0, 1).
+	pub fn next_f32(&mut self) -> f32 {
+		return (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+	}
+
+	/// Uniform in `range`, or its start when it's empty.
+	pub fn range(&mut self, range: Range<u32>) -> u32 {
+		let len = range.end.saturating_sub(range.start);
+		if len == 0 {
+			return range.start;
+		}
+		return range.start + (self.next_u64() % len as u64) as u32;
+	}
+
+	/// True with a chance of `p`.
+	pub fn chance(&mut self, p: f32) -> bool {
+		return self.next_f32() < p;
+	}
+
+	/// A tile of the map picked evenly among those `eligible` accepts, or
+	/// `None` if it accepts none. Visits every tile once, drawing a number
+	/// for each eligible one.
+	pub fn random_tile(
+		&mut self,
+		settings: &HexGridSettings,
+		mut eligible: impl FnMut(HexCoord) -> bool,
+	) -> Option<HexCoord> {
+		let tiles = settings.map_tiles().as_ivec2();
+		let mut picked = None;
+		let mut seen = 0;
+		for z in 0..tiles.y {
+			for x in 0..tiles.x {
+				let coord = HexCoord::from_offset(x, z);
+				if !settings.contains(coord) || !eligible(coord) {
+					continue;
+				}
+				// keeps each of the `seen` tiles so far with the same chance
+				seen += 1;
+				if self.range(0..seen) == 0 {
+					picked = Some(coord);
+				}
+			}
+		}
+		return picked;
+	}
+}
+
+pub(super) fn reseed_rng(settings: Res<HexGridSettings>, mut rng: ResMut<GridRng>) {
+	if rng.seed != settings.seed {
+		*rng = GridRng::new(settings.seed);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::ecs::system::RunSystemOnce;
+
+	use super::*;
+
+	fn draws(rng: &mut GridRng) -> Vec<u64> {
+		return (0..32).map(|_| rng.next_u64()).collect();
+	}
+
+	#[test]
+	fn seeds_repeat_their_sequence() {
+		let first = draws(&mut GridRng::new(7));
+		assert_eq!(draws(&mut GridRng::new(7)), first);
+		assert_ne!(draws(&mut GridRng::new(8)), first);
+
+		// reseeding starts over only when the seed changes
+		let mut world = World::new();
+		world.insert_resource(HexGridSettings {
+			seed: 7,
+			..default()
+		});
+		let mut drawn = GridRng::new(7);
+		drawn.next_u64();
+		world.insert_resource(drawn.clone());
+		world.run_system_once(reseed_rng);
+		assert_eq!(*world.resource::<GridRng>(), drawn);
+		world.resource_mut::<HexGridSettings>().seed = 8;
+		world.run_system_once(reseed_rng);
+		assert_eq!(
+			draws(&mut world.resource_mut::<GridRng>()),
+			draws(&mut GridRng::new(8))
+		);
+	}
+
+	#[test]
+	fn keyed_sequences_differ_by_key() {
+		let tile = draws(&mut GridRng::keyed(7, &[0, 3, 4]));
+		assert_eq!(draws(&mut GridRng::keyed(7, &[0, 3, 4])), tile);
+		assert_ne!(draws(&mut GridRng::keyed(7, &[0, 4, 3])), tile);
+		assert_ne!(draws(&mut GridRng::keyed(8, &[0, 3, 4])), tile);
+		let mut rng = GridRng::new(3);
+		for value in (0..1000).map(|_| rng.next_f32()) {
+			assert!((0. ..1.).contains(&value));
+		}
+	}
+}
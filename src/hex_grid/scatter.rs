@@ -0,0 +1,351 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+	biome::{neighbor_heights, tile_biome, tile_slope},
+	height::sample_height_at,
+	sample_height, to_chunk_pos, Chunk, ChunkRebuilt, GridRng, HexCoord, HexGrid, HexGridSettings,
+	TerrainNoise,
+};
+
+/// Decorations such as trees or rocks scattered over the tiles of some
+/// biomes.
+#[derive(Reflect, Clone, Debug)]
+pub struct Scatter {
+	/// Spawned once per placement, standing on the terrain.
+	pub scene: Handle<Scene>,
+	/// Chance of each eligible tile getting one, between 0 and 1.
+	pub density: f32,
+	/// Names of the biomes placements can land on, any biome when empty.
+	pub biomes: Vec<String>,
+	/// Largest height difference to a neighbor an eligible tile may have.
+	pub max_slope: f32,
+	/// Smallest and largest uniform scale of a placement.
+	pub scale: Vec2,
+}
+
+impl Default for Scatter {
+	fn default() -> Self {
+		return Self {
+			scene: Handle::default(),
+			density: 0.2,
+			biomes: Vec::new(),
+			max_slope: 1.,
+			scale: Vec2::new(0.8, 1.2),
+		};
+	}
+}
+
+/// One scattered decoration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeaturePlacement {
+	pub coord: HexCoord,
+	/// Index into `HexGridSettings::scatter`.
+	pub layer: usize,
+	/// In world space.
+	pub transform: Transform,
+}
+
+/// Marks the entity of a scattered decoration, a child of its chunk.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Feature {
+	pub coord: HexCoord,
+	pub layer: usize,
+}
+
+/// Fraction of the inner radius placements stray from the tile center.
+const FEATURE_SPREAD: f32 = 0.5;
+
+impl HexGrid {
+	/// Every decoration `HexGridSettings::scatter` places on the map. Each
+	/// tile gets at most one, from the first layer it passes the chance of,
+	/// and the same seed always places the same ones.
+	pub fn scatter_features(settings: &HexGridSettings) -> Vec<FeaturePlacement> {
+		let noise = settings.noise();
+		let tiles = settings.map_tiles().as_ivec2();
+		return (0..tiles.y)
+			.flat_map(|z| (0..tiles.x).map(move |x| (x, z)))
+			.filter_map(|(x, z)| {
+				let (layer, mut transform) = tile_feature(x, z, &noise, settings)?;
+				let coord = HexCoord::from_offset(x, z);
+				transform.translation += coord.to_world(settings);
+				return Some(FeaturePlacement {
+					coord,
+					layer,
+					transform,
+				});
+			})
+			.collect();
+	}
+}
+
+/// The decoration on tile (`x`, `z`), if any, relative to the tile center at
+/// height 0.
+fn tile_feature(
+	x: i32,
+	z: i32,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Option<(usize, Transform)> {
+	let coord = HexCoord::from_offset(x, z);
+	if settings.scatter.is_empty() || !settings.shows(coord) {
+		return None;
+	}
+	let height = sample_height(x, z, noise, settings);
+	if height < settings.sea_level.unwrap_or(f32::NEG_INFINITY) {
+		return None;
+	}
+	let neighbors = neighbor_heights(x, z, noise, settings);
+	let slope = tile_slope(height, &neighbors);
+	let biome = tile_biome(x, z, height, &neighbors, settings).map(|biome| biome.name.as_str());
+
+	// each layer and tile draws from its own sequence, so chunks place the
+	// same decorations in whatever order they are built
+	let placed = |(i, scatter): (usize, &Scatter)| {
+		let allowed = scatter.biomes.is_empty()
+			|| biome.is_some_and(|biome| scatter.biomes.iter().any(|b| b == biome));
+		let mut rng = GridRng::keyed(settings.seed, &[i as u32, x as u32, z as u32]);
+		let placed = allowed && slope <= scatter.max_slope && rng.chance(scatter.density);
+		return placed.then_some((i, rng));
+	};
+	let (layer, mut rng) = settings.scatter.iter().enumerate().find_map(placed)?;
+	let scatter = &settings.scatter[layer];
+
+	let angle = rng.next_f32() * TAU;
+	let distance = rng.next_f32().sqrt() * settings.inner_radius() * FEATURE_SPREAD;
+	let offset = Vec2::from_angle(angle) * distance;
+	// plain tile tops are flat, subdivided ones follow the terrain
+	let y = match settings.subdivisions {
+		0 => height,
+		_ => sample_height_at(coord.to_world(settings).xz() + offset, noise, settings),
+	};
+	let scale = scatter.scale.x + (scatter.scale.y - scatter.scale.x) * rng.next_f32();
+	let transform = Transform::from_xyz(offset.x, y, offset.y)
+		.with_rotation(Quat::from_rotation_y(rng.next_f32() * TAU))
+		.with_scale(Vec3::splat(scale));
+	return Some((layer, transform));
+}
+
+/// Spawns the decorations of new chunks as their children, and respawns
+/// those of chunks rebuilt after an edit.
+pub(super) fn spawn_features(
+	mut commands: Commands,
+	chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+	mut rebuilt: EventReader<ChunkRebuilt>,
+	features: Query<(Entity, &Parent), With<Feature>>,
+	settings: Res<HexGridSettings>,
+) {
+	let rebuilt = rebuilt
+		.read()
+		.map(|event| (event.entity, event.index))
+		.collect::<HashMap<_, _>>();
+	if settings.scatter.is_empty() || (chunks.is_empty() && rebuilt.is_empty()) {
+		return;
+	}
+	for (feature, parent) in &features {
+		if rebuilt.contains_key(&parent.get()) {
+			commands.entity(feature).despawn_recursive();
+		}
+	}
+	let noise = settings.noise();
+	let size = settings.chunk_size;
+	let chunks = chunks
+		.iter()
+		.map(|(entity, chunk)| (entity, chunk.index))
+		.chain(rebuilt)
+		.collect::<HashMap<_, _>>();
+	for (entity, index) in chunks {
+		let origin = (index * size).as_ivec2();
+		commands.entity(entity).with_children(|parent| {
+			for z in 0..size {
+				for x in 0..size {
+					let (g_x, g_z) = (origin.x + x as i32, origin.y + z as i32);
+					let Some((layer, mut transform)) = tile_feature(g_x, g_z, &noise, &settings)
+					else {
+						continue;
+					};
+					let center = Vec3::new(x as f32, 0., z as f32);
+					transform.translation += to_chunk_pos(center, origin.y, &settings);
+					parent.spawn((
+						Feature {
+							coord: HexCoord::from_offset(g_x, g_z),
+							layer,
+						},
+						SceneBundle {
+							scene: settings.scatter[layer].scene.clone(),
+							transform,
+							..default()
+						},
+					));
+				}
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bevy::{ecs::system::RunSystemOnce, tasks::AsyncComputeTaskPool};
+
+	use super::*;
+	use crate::hex_grid::{
+		chunk_origin, edit::rebuild_dirty_chunks, DirtyChunks, HexData, HexMap, LoadedChunks,
+		NormalMode,
+	};
+
+	/// Height of the tile tops of the chunk meshes at world `xz`, where they
+	/// cover it.
+	fn mesh_height(xz: Vec2, settings: &HexGridSettings) -> Option<f32> {
+		let index = HexCoord::from_world(xz.extend(0.).xzy(), settings).chunk(settings.chunk_size);
+		let origin = chunk_origin(index.as_uvec2(), settings);
+		let buffers = HexGrid::chunk_buffers(index.as_uvec2(), settings);
+		return buffers.indices.chunks_exact(3).find_map(|tri| {
+			let [a, b, c] =
+				[0, 1, 2].map(|i| origin + Vec3::from(buffers.positions[tri[i] as usize]));
+			// barycentric weights of `xz` in the triangle seen from above
+			let (ab, ac, ap) = ((b - a).xz(), (c - a).xz(), xz - a.xz());
+			let area = ab.perp_dot(ac);
+			if area.abs() < 1e-6 {
+				return None;
+			}
+			let (v, w) = (ap.perp_dot(ac) / area, ab.perp_dot(ap) / area);
+			let inside = v >= -1e-5 && w >= -1e-5 && v + w <= 1. + 1e-5;
+			return inside.then_some(a.y + v * (b.y - a.y) + w * (c.y - a.y));
+		});
+	}
+
+	#[test]
+	fn features_land_only_on_eligible_tiles() {
+		// grass on the first half of the rows and sand on the rest, with a
+		// steep bump on the grass at (2, 1)
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 8,
+			scatter: vec![Scatter {
+				density: 0.5,
+				biomes: vec!["grass".into()],
+				max_slope: 0.5,
+				..default()
+			}],
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for (i, tile) in map.tiles.iter_mut().enumerate() {
+			tile.height = if i == 8 + 2 { 7. } else { 5. };
+			tile.biome = if i < 32 { "grass" } else { "sand" }.into();
+		}
+		settings.map = Some(Arc::new(map));
+		let placements = HexGrid::scatter_features(&settings);
+		assert!(
+			placements.len() > 10,
+			"only {} placements",
+			placements.len()
+		);
+		let noise = settings.noise();
+		for placement in &placements {
+			let offset = placement.coord.to_offset();
+			let height = sample_height(offset.x, offset.y, &noise, &settings);
+			let neighbors = neighbor_heights(offset.x, offset.y, &noise, &settings);
+			let biome = tile_biome(offset.x, offset.y, height, &neighbors, &settings).unwrap();
+			assert_eq!(biome.name, "grass");
+			for n in placement.coord.neighbors_on_map(&settings) {
+				let n = n.to_offset();
+				let neighbor = sample_height(n.x, n.y, &noise, &settings);
+				assert!((neighbor - height).abs() <= 0.5);
+			}
+			assert!(placement.coord.distance(&HexCoord::from_offset(2, 1)) > 1);
+			let center = placement.coord.to_world(&settings);
+			let stray = (placement.transform.translation - center).xz().length();
+			assert!(stray <= settings.inner_radius() * FEATURE_SPREAD + 1e-4);
+			assert_eq!(placement.transform.translation.y, height);
+		}
+
+		assert_eq!(HexGrid::scatter_features(&settings), placements);
+		let reseeded = HexGridSettings {
+			seed: settings.seed + 1,
+			..settings
+		};
+		assert_ne!(HexGrid::scatter_features(&reseeded), placements);
+
+		// subdivided tiles follow the noise, finely enough to stand on it
+		let settings = HexGridSettings {
+			map_size: UVec2::new(2, 1),
+			chunk_size: 6,
+			subdivisions: 8,
+			height_amplitude: 4.,
+			sea_level: None,
+			normal_mode: NormalMode::Smooth,
+			scatter: vec![Scatter {
+				density: 0.5,
+				max_slope: f32::INFINITY,
+				..default()
+			}],
+			..default()
+		};
+		let placements = HexGrid::scatter_features(&settings);
+		assert!(placements.len() > 5, "only {} placements", placements.len());
+		for placement in &placements {
+			let pos = placement.transform.translation;
+			let surface = mesh_height(pos.xz(), &settings).unwrap();
+			// the mesh runs straight between its vertices, the noise doesn't
+			assert!((pos.y - surface).abs() < 0.05, "{pos} above {surface}");
+		}
+	}
+
+	/// Heights of the decorations standing on `coord`.
+	fn feature_heights(world: &mut World, coord: HexCoord) -> Vec<f32> {
+		return world
+			.query::<(&Feature, &Transform)>()
+			.iter(world)
+			.filter(|(feature, _)| feature.coord == coord)
+			.map(|(_, transform)| transform.translation.y)
+			.collect();
+	}
+
+	#[test]
+	fn features_follow_edited_tiles() {
+		AsyncComputeTaskPool::get_or_init(default);
+		let mut world = World::new();
+		world.insert_resource(HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 4,
+			sea_level: None,
+			scatter: vec![Scatter {
+				density: 1.,
+				max_slope: f32::INFINITY,
+				..default()
+			}],
+			..default()
+		});
+		world.init_resource::<HexData>();
+		world.init_resource::<DirtyChunks>();
+		world.init_resource::<Events<ChunkRebuilt>>();
+		let chunk = world
+			.spawn(Chunk {
+				index: UVec2::ZERO,
+				lod: 0,
+			})
+			.id();
+		world.insert_resource(LoadedChunks([(IVec2::ZERO, chunk)].into()));
+		// a system run once would take every chunk for a new one
+		let spawn = world.register_system(spawn_features);
+		world.run_system(spawn).unwrap();
+		let coord = HexCoord::from_offset(2, 1);
+		assert_eq!(feature_heights(&mut world, coord).len(), 1);
+
+		world.run_system_once(
+			move |mut settings: ResMut<HexGridSettings>,
+			      mut data: ResMut<HexData>,
+			      mut dirty: ResMut<DirtyChunks>| {
+				HexGrid::set_height(coord, 9., &mut settings, &mut data, &mut dirty);
+			},
+		);
+		world.run_system_once(rebuild_dirty_chunks);
+		world.run_system(spawn).unwrap();
+		assert_eq!(feature_heights(&mut world, coord), vec![9.]);
+		assert_eq!(world.query::<&Feature>().iter(&world).count(), 16);
+	}
+}
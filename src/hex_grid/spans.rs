@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+
+use super::{
+	create_quad, create_tile, sample_height, to_chunk_pos, HexCoord, HexDirection, HexGridSettings,
+	TerrainNoise, TileSpan, WALL_EPSILON,
+};
+
+/// Adds the top, the bottom and the uncovered walls of every span stored
+/// over the tiles of the chunk at (`c_x`, `c_z`).
+pub(super) fn add_tile_spans(
+	c_x: u32,
+	c_z: u32,
+	corners: &[Vec3; 6],
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+) {
+	let Some(map) = &settings.map else {
+		return;
+	};
+	let chunk_size = settings.chunk_size;
+	let origin = (UVec2::new(c_x, c_z) * chunk_size).as_ivec2();
+	// inset tiles don't touch, so nothing covers the sides of their spans
+	let touching = settings.tile_inset <= 0.;
+	for z in 0..chunk_size {
+		for x in 0..chunk_size {
+			let (g_x, g_z) = (origin.x + x as i32, origin.y + z as i32);
+			let offset = settings.wrap_offset(IVec2::new(g_x, g_z));
+			let spans = map.spans(offset.x, offset.y);
+			let coord = HexCoord::from_offset(g_x, g_z);
+			if spans.is_empty() || !settings.contains(coord) {
+				continue;
+			}
+			let center = to_chunk_pos(Vec3::new(x as f32, 0., z as f32), origin.y, settings);
+			// the ground and spans of each neighbor, which cover the walls
+			let solids = HexDirection::ALL.map(|dir| {
+				let neighbor = settings.wrap_coord(coord.neighbor(dir));
+				if !touching || !settings.contains(neighbor) {
+					return Vec::new();
+				}
+				let n = neighbor.to_offset();
+				let ground = TileSpan {
+					bottom: f32::NEG_INFINITY,
+					top: sample_height(n.x, n.y, noise, settings),
+				};
+				return std::iter::once(ground)
+					.chain(map.spans(n.x, n.y))
+					.collect::<Vec<_>>();
+			});
+
+			for span in spans {
+				create_tile(
+					center + Vec3::Y * span.top,
+					settings.atlas_rect(span.top),
+					corners,
+					settings,
+					verts,
+					uvs,
+					normals,
+					indices,
+				);
+				// the ceiling is a tile top turned to face down
+				let (first_vert, first_index) = (verts.len(), indices.len());
+				create_tile(
+					center + Vec3::Y * span.bottom,
+					settings.atlas_rect(span.bottom),
+					corners,
+					settings,
+					verts,
+					uvs,
+					normals,
+					indices,
+				);
+				for tri in indices[first_index..].chunks_exact_mut(3) {
+					tri.swap(1, 2);
+				}
+				normals[first_vert..].fill(Vec3::NEG_Y);
+
+				for dir in HexDirection::ALL {
+					let i = dir.index();
+					let (a, b) = (center + corners[i], center + corners[(i + 1) % 6]);
+					for (low, high) in uncovered(span, &solids[i]) {
+						let quad = [
+							a + Vec3::Y * high,
+							b + Vec3::Y * high,
+							b + Vec3::Y * low,
+							a + Vec3::Y * low,
+						];
+						let cell = settings.atlas_rect(high);
+						create_quad(quad, cell, settings, verts, uvs, normals, indices);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// The height ranges of `span` that none of `solids` cover.
+fn uncovered(span: TileSpan, solids: &[TileSpan]) -> Vec<(f32, f32)> {
+	let mut open = vec![(span.bottom, span.top)];
+	for solid in solids {
+		open = open
+			.into_iter()
+			.flat_map(|(low, high)| [(low, high.min(solid.bottom)), (low.max(solid.top), high)])
+			.filter(|(low, high)| high - low > WALL_EPSILON)
+			.collect();
+	}
+	return open;
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::hex_grid::{HexGrid, HexMap, NormalMode};
+
+	#[test]
+	fn spans_get_a_floor_and_a_ceiling() {
+		// level ground at 2 with two spans floating over (1, 1)
+		let mut settings = HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 4,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+		let mut map = HexMap::from_settings(&settings);
+		for tile in &mut map.tiles {
+			tile.height = 2.;
+		}
+		map.tiles[5].spans = vec![
+			TileSpan {
+				bottom: 4.,
+				top: 5.,
+			},
+			TileSpan {
+				bottom: 7.,
+				top: 9.,
+			},
+		];
+		settings.map = Some(Arc::new(map));
+
+		let buffers = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+		// level triangles by height, counting those facing up and down
+		let mut faces = Vec::<(f32, usize, usize)>::new();
+		let mut walls = 0;
+		for tri in buffers.indices.chunks_exact(3) {
+			let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(buffers.positions[tri[i] as usize]));
+			let normal = (b - a).cross(c - a);
+			if a.y != b.y || a.y != c.y {
+				walls += 1;
+				continue;
+			}
+			let up = (normal.y > 0.) as usize;
+			match faces.iter_mut().find(|(y, _, _)| *y == a.y) {
+				Some(face) => {
+					face.1 += up;
+					face.2 += 1 - up;
+				}
+				None => faces.push((a.y, up, 1 - up)),
+			}
+		}
+		faces.sort_by(|a, b| a.0.total_cmp(&b.0));
+		assert_eq!(
+			faces,
+			vec![
+				(2., 16 * 6, 0),
+				(4., 0, 6),
+				(5., 6, 0),
+				(7., 0, 6),
+				(9., 6, 0)
+			]
+		);
+		// both spans stand clear of the ground all around
+		assert_eq!(walls, 2 * 6 * 2);
+	}
+}
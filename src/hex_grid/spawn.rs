@@ -0,0 +1,97 @@
+use super::{height::sample_height, HexCoord, HexGrid, HexGridSettings};
+
+/// Largest height difference to a neighbor a spawn point may have.
+const SPAWN_MAX_SLOPE: f32 = 1.;
+
+impl HexGrid {
+	/// Up to `count` tiles above `HexGridSettings::sea_level`, none of them
+	/// more than `SPAWN_MAX_SLOPE` off a neighbor's height, and each at least
+	/// `min_separation` tiles from the others. The flattest tiles are taken
+	/// first, so maps short on suitable land return fewer points.
+	pub fn find_spawn_points(
+		count: usize,
+		min_separation: u32,
+		settings: &HexGridSettings,
+	) -> Vec<HexCoord> {
+		let noise = settings.noise();
+		let tiles = settings.map_tiles().as_ivec2();
+		let heights = (0..tiles.y)
+			.flat_map(|z| (0..tiles.x).map(move |x| (x, z)))
+			.map(|(x, z)| sample_height(x, z, &noise, settings))
+			.collect::<Vec<_>>();
+		let height = |coord: HexCoord| {
+			let offset = coord.to_offset();
+			return heights[(offset.y * tiles.x + offset.x) as usize];
+		};
+
+		let sea_level = settings.sea_level.unwrap_or(f32::NEG_INFINITY);
+		let mut candidates = (0..tiles.y)
+			.flat_map(|z| (0..tiles.x).map(move |x| HexCoord::from_offset(x, z)))
+			.filter(|&coord| settings.contains(coord))
+			.filter(|&coord| height(coord) >= sea_level)
+			.filter_map(|coord| {
+				let slope = coord
+					.neighbors_on_map(settings)
+					.into_iter()
+					.map(|n| (height(n) - height(coord)).abs())
+					.fold(0., f32::max);
+				return (slope <= SPAWN_MAX_SLOPE).then_some((coord, slope));
+			})
+			.collect::<Vec<_>>();
+		candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+		let mut points = Vec::<HexCoord>::new();
+		for (coord, _) in candidates {
+			if points.len() >= count {
+				break;
+			}
+			if points.iter().all(|p| p.distance(&coord) >= min_separation) {
+				points.push(coord);
+			}
+		}
+		return points;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::prelude::*;
+
+	use super::*;
+	use crate::hex_grid::MapShape;
+
+	#[test]
+	fn spawn_points_keep_apart_on_flat_land() {
+		let settings = HexGridSettings {
+			map_size: UVec2::splat(4),
+			chunk_size: 8,
+			height_amplitude: 4.,
+			sea_level: Some(3.2),
+			..default()
+		};
+		let sea_level = settings.sea_level.unwrap();
+		let points = HexGrid::find_spawn_points(12, 6, &settings);
+		assert!(points.len() > 4, "only {} spawn points", points.len());
+		for (i, point) in points.iter().enumerate() {
+			let height = HexGrid::height_at(*point, &settings);
+			assert!(height >= sea_level);
+			for n in point.neighbors_on_map(&settings) {
+				assert!((HexGrid::height_at(n, &settings) - height).abs() <= SPAWN_MAX_SLOPE);
+			}
+			assert!(points[i + 1..].iter().all(|p| p.distance(point) >= 6));
+		}
+	}
+
+	#[test]
+	fn spawn_points_stay_on_shaped_maps() {
+		let settings = HexGridSettings {
+			map_size: UVec2::splat(4),
+			chunk_size: 8,
+			shape: MapShape::Hexagon { radius: 12 },
+			..default()
+		};
+		let points = HexGrid::find_spawn_points(50, 3, &settings);
+		assert!(!points.is_empty());
+		assert!(points.iter().all(|&point| settings.contains(point)));
+	}
+}
@@ -0,0 +1,72 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_panorbit_camera::PanOrbitCamera;
+
+use super::{chunk_center, lod::lod_for_distance, spawn_chunk, HexGridSettings};
+
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct ChunkStreaming {
+	/// Chunks whose center is within this distance of the camera focus are loaded.
+	pub load_radius: f32,
+	/// Extra distance a loaded chunk may drift past `load_radius` before it is
+	/// unloaded, so chunks on the edge don't flicker in and out.
+	pub hysteresis: f32,
+}
+
+impl Default for ChunkStreaming {
+	fn default() -> Self {
+		return Self {
+			load_radius: 200.,
+			hysteresis: 30.,
+		};
+	}
+}
+
+/// Every spawned chunk entity, keyed by chunk index.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct LoadedChunks(pub HashMap<IVec2, Entity>);
+
+pub(super) fn stream_chunks(
+	mut commands: Commands,
+	cameras: Query<(&PanOrbitCamera, &GlobalTransform)>,
+	chunks: Query<&Handle<Mesh>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut loaded: ResMut<LoadedChunks>,
+	settings: Res<HexGridSettings>,
+) {
+	let (Some(streaming), Ok((camera, transform))) = (settings.streaming, cameras.get_single())
+	else {
+		return;
+	};
+	if settings.instanced {
+		return;
+	}
+	let focus = camera.focus.xz();
+	let distance = |index: UVec2| chunk_center(index, &settings).xz().distance(focus);
+
+	loaded.retain(|index, entity| {
+		if distance(index.as_uvec2()) <= streaming.load_radius + streaming.hysteresis {
+			return true;
+		}
+		if let Ok(mesh) = chunks.get(*entity) {
+			meshes.remove(mesh);
+		}
+		commands.entity(*entity).despawn_recursive();
+		return false;
+	});
+
+	let noise = settings.noise();
+	for z in 0..settings.map_size.y {
+		for x in 0..settings.map_size.x {
+			let index = UVec2::new(x, z);
+			if loaded.contains_key(&index.as_ivec2()) || distance(index) > streaming.load_radius {
+				continue;
+			}
+			let lod = lod_for_distance(
+				chunk_center(index, &settings).distance(transform.translation()),
+				&settings,
+			);
+			let entity = spawn_chunk(&mut commands, index, lod, &noise, &settings);
+			loaded.insert(index.as_ivec2(), entity);
+		}
+	}
+}
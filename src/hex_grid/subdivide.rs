@@ -0,0 +1,198 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{height::sample_height_at, HexGridSettings, TerrainNoise};
+
+/// Corners of a hexagon in multiples of its first two corners, which every
+/// point of its triangular lattice is a whole combination of.
+const CORNERS: [IVec2; 6] = [
+	IVec2::new(1, 0),
+	IVec2::new(0, 1),
+	IVec2::new(-1, 1),
+	IVec2::new(-1, 0),
+	IVec2::new(0, -1),
+	IVec2::new(1, -1),
+];
+
+/// Vertex layout of a tile top split into `subdivisions + 1` triangles along
+/// each edge. Like the plain tile, the center comes first and corner `i` at
+/// `i + 1`, followed by the points along each edge and then the inside.
+pub(super) struct TileLattice {
+	/// Segments along each edge.
+	segments: i32,
+	/// Position of every vertex as a combination of the first two corners,
+	/// scaled by `segments`.
+	points: Vec<IVec2>,
+	pub triangles: Vec<[u32; 3]>,
+	/// Vertices along each edge `i`, from corner `i` to corner `i + 1`.
+	pub edges: [Vec<u32>; 6],
+}
+
+impl TileLattice {
+	pub fn new(subdivisions: u32) -> Self {
+		let k = subdivisions as i32 + 1;
+		let step = |i: usize| CORNERS[(i + 1) % 6] - CORNERS[i];
+		let mut points = vec![IVec2::ZERO];
+		points.extend(CORNERS.map(|corner| corner * k));
+		for (i, &corner) in CORNERS.iter().enumerate() {
+			points.extend((1..k).map(|j| corner * k + step(i) * j));
+		}
+		for r in 1..k {
+			for (i, &corner) in CORNERS.iter().enumerate() {
+				points.extend((0..r).map(|j| corner * r + step(i) * j));
+			}
+		}
+		let index = points
+			.iter()
+			.enumerate()
+			.map(|(i, &point)| (point, i as u32))
+			.collect::<HashMap<_, _>>();
+
+		// each sector between the center and two corners winds like the fan
+		// of a plain tile
+		let mut triangles = Vec::with_capacity(6 * (k * k) as usize);
+		for i in 0..6 {
+			let at = |a: i32, b: i32| index[&(CORNERS[i] * a + CORNERS[(i + 1) % 6] * b)];
+			for a in 0..k {
+				for b in 0..k - a {
+					triangles.push([at(a, b), at(a + 1, b), at(a, b + 1)]);
+					if a + b + 2 <= k {
+						triangles.push([at(a + 1, b), at(a + 1, b + 1), at(a, b + 1)]);
+					}
+				}
+			}
+		}
+		let edges = std::array::from_fn(|i| {
+			let inner = (1..k).map(|j| 7 + (i as i32 * (k - 1) + j - 1) as u32);
+			return std::iter::once(1 + i as u32)
+				.chain(inner)
+				.chain(std::iter::once(1 + ((i + 1) % 6) as u32))
+				.collect();
+		});
+		return Self {
+			segments: k,
+			points,
+			triangles,
+			edges,
+		};
+	}
+
+	/// Number of vertices per tile.
+	pub fn stride(&self) -> usize {
+		return self.points.len();
+	}
+}
+
+/// Like `create_tile`, with every vertex but the center at the height of the
+/// terrain below it. `world` is the tile center in world space.
+pub(super) fn create_subdivided_tile(
+	pos: Vec3,
+	world: Vec2,
+	cell: Rect,
+	lattice: &TileLattice,
+	corners: &[Vec3; 6],
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+	verts: &mut Vec<Vec3>,
+	uvs: &mut Vec<Vec2>,
+	normals: &mut Vec<Vec3>,
+	indices: &mut Vec<u32>,
+) {
+	let idx = verts.len() as u32;
+	let center = Vec3::new(pos.x, 0., pos.z);
+	for (i, point) in lattice.points.iter().enumerate() {
+		let offset =
+			(corners[0] * point.x as f32 + corners[1] * point.y as f32) / lattice.segments as f32;
+		// the center keeps the exact height of the tile
+		let height = match i {
+			0 => pos.y,
+			_ => sample_height_at(world + offset.xz(), noise, settings),
+		};
+		let p = Vec3::new(pos.x + offset.x, height, pos.z + offset.z);
+		verts.push(p);
+		let uv = Vec2::splat(0.5) + offset.xz() / (2. * settings.outer_radius);
+		uvs.push(cell.min + uv * cell.size());
+		normals.push(match i {
+			0 => Vec3::Y,
+			_ => (p - center).normalize(),
+		});
+	}
+	indices.extend(lattice.triangles.iter().flatten().map(|i| idx + i));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::{create_tile, HexGrid, NoiseType, NormalMode};
+
+	fn flat(subdivisions: u32) -> HexGridSettings {
+		return HexGridSettings {
+			map_size: UVec2::ONE,
+			chunk_size: 4,
+			noise_type: NoiseType::Flat,
+			height_offset: 2.,
+			normal_mode: NormalMode::Smooth,
+			subdivisions,
+			..default()
+		};
+	}
+
+	#[test]
+	fn no_subdivisions_build_the_plain_tile() {
+		let lattice = TileLattice::new(0);
+		assert_eq!(lattice.stride(), 7);
+		let fan = (0..6)
+			.map(|i| [0, 1 + i, 1 + (i + 1) % 6])
+			.collect::<Vec<_>>();
+		assert_eq!(lattice.triangles, fan);
+		for (i, edge) in lattice.edges.iter().enumerate() {
+			assert_eq!(edge[..], [1 + i as u32, 1 + (i as u32 + 1) % 6]);
+		}
+
+		let settings = flat(0);
+		let noise = settings.noise();
+		let corners = settings.hex_corners();
+		let pos = Vec3::new(3., 2., 5.);
+		let cell = settings.atlas_rect(pos.y);
+		let mut plain = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+		create_tile(
+			pos,
+			cell,
+			&corners,
+			&settings,
+			&mut plain.0,
+			&mut plain.1,
+			&mut plain.2,
+			&mut plain.3,
+		);
+		let mut lattice_tile = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+		create_subdivided_tile(
+			pos,
+			pos.xz(),
+			cell,
+			&lattice,
+			&corners,
+			&noise,
+			&settings,
+			&mut lattice_tile.0,
+			&mut lattice_tile.1,
+			&mut lattice_tile.2,
+			&mut lattice_tile.3,
+		);
+		assert_eq!(lattice_tile, plain);
+	}
+
+	#[test]
+	fn subdivisions_add_triangles() {
+		let mut last = 0;
+		for subdivisions in 0..=3 {
+			let settings = flat(subdivisions);
+			let buffers = HexGrid::chunk_buffers(UVec2::ZERO, &settings);
+			let triangles = buffers.indices.len() / 3;
+			let k = subdivisions as usize + 1;
+			assert_eq!(triangles, 16 * 6 * k * k);
+			assert!(triangles > last);
+			last = triangles;
+			assert!(buffers.positions.iter().all(|p| (p[1] - 2.).abs() < 1e-5));
+		}
+	}
+}
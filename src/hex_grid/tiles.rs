@@ -0,0 +1,199 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+	sample_height, to_chunk_pos, Chunk, ChunkRebuilt, HexCoord, HexGridSettings, TerrainNoise,
+};
+
+/// The entity of every tile of a loaded chunk, spawned as children of the
+/// chunk when `HexGridSettings::tile_entities` is set.
+#[derive(Resource, Default, Debug, Deref)]
+pub struct TileEntities {
+	#[deref]
+	tiles: HashMap<HexCoord, Entity>,
+	/// The tiles spawned under each chunk entity, to drop them all at once.
+	chunks: HashMap<Entity, Vec<(HexCoord, Entity)>>,
+}
+
+impl TileEntities {
+	pub fn get(&self, coord: HexCoord) -> Option<Entity> {
+		return self.tiles.get(&coord).copied();
+	}
+}
+
+/// Spawns the tiles of new chunks as their children, and moves the tiles of
+/// chunks rebuilt after an edit onto their new heights.
+pub(super) fn spawn_tile_entities(
+	mut commands: Commands,
+	chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+	mut rebuilt: EventReader<ChunkRebuilt>,
+	mut transforms: Query<&mut Transform, With<HexCoord>>,
+	mut tiles: ResMut<TileEntities>,
+	settings: Res<HexGridSettings>,
+) {
+	if !settings.tile_entities || (chunks.is_empty() && rebuilt.is_empty()) {
+		rebuilt.clear();
+		return;
+	}
+	let noise = settings.noise();
+	let size = settings.chunk_size;
+	for (entity, chunk) in &chunks {
+		let origin = (chunk.index * size).as_ivec2();
+		let mut spawned = Vec::new();
+		commands.entity(entity).with_children(|parent| {
+			for z in 0..size {
+				for x in 0..size {
+					let (g_x, g_z) = (origin.x + x as i32, origin.y + z as i32);
+					let coord = HexCoord::from_offset(g_x, g_z);
+					if !settings.contains(coord) {
+						continue;
+					}
+					let center = tile_center(coord, origin, &noise, &settings);
+					let tile = parent.spawn((
+						coord,
+						SpatialBundle::from_transform(Transform::from_translation(center)),
+					));
+					spawned.push((coord, tile.id()));
+				}
+			}
+		});
+		tiles.tiles.extend(spawned.iter().copied());
+		tiles.chunks.insert(entity, spawned);
+	}
+	// the tiles keep their entities, and whatever was attached to them
+	for event in rebuilt.read() {
+		let origin = (event.index * size).as_ivec2();
+		for &(coord, tile) in tiles.chunks.get(&event.entity).into_iter().flatten() {
+			if let Ok(mut transform) = transforms.get_mut(tile) {
+				transform.translation = tile_center(coord, origin, &noise, &settings);
+			}
+		}
+	}
+}
+
+/// Middle of the top of `coord`, relative to the chunk whose first tile is
+/// at offset coordinates `origin`.
+fn tile_center(
+	coord: HexCoord,
+	origin: IVec2,
+	noise: &TerrainNoise,
+	settings: &HexGridSettings,
+) -> Vec3 {
+	let offset = coord.to_offset();
+	let height = sample_height(offset.x, offset.y, noise, settings);
+	let local = (offset - origin).as_vec2();
+	return to_chunk_pos(Vec3::new(local.x, height, local.y), origin.y, settings);
+}
+
+/// Drops the tiles of chunks that were streamed out or regenerated.
+pub(super) fn forget_tile_entities(
+	mut removed: RemovedComponents<Chunk>,
+	mut tiles: ResMut<TileEntities>,
+) {
+	for chunk in removed.read() {
+		let Some(spawned) = tiles.chunks.remove(&chunk) else {
+			continue;
+		};
+		for (coord, entity) in spawned {
+			// a chunk spawned over the same tiles keeps its own entities
+			if tiles.tiles.get(&coord) == Some(&entity) {
+				tiles.tiles.remove(&coord);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{
+		ecs::system::{RunSystemOnce, SystemId},
+		tasks::AsyncComputeTaskPool,
+	};
+
+	use super::*;
+	use crate::hex_grid::{
+		edit::rebuild_dirty_chunks, DirtyChunks, HexData, HexGrid, LoadedChunks,
+	};
+
+	/// A 2×2 map of 4 tile chunks, with the tiles spawned by the returned
+	/// system, which only sees chunks added since.
+	fn world() -> (World, SystemId) {
+		let mut world = World::new();
+		world.insert_resource(HexGridSettings {
+			map_size: UVec2::splat(2),
+			chunk_size: 4,
+			tile_entities: true,
+			..default()
+		});
+		world.init_resource::<TileEntities>();
+		world.init_resource::<Events<ChunkRebuilt>>();
+		for index in [UVec2::ZERO, UVec2::X, UVec2::Y, UVec2::ONE] {
+			world.spawn(Chunk { index, lod: 0 });
+		}
+		let spawn = world.register_system(spawn_tile_entities);
+		world.run_system(spawn).unwrap();
+		return (world, spawn);
+	}
+
+	#[test]
+	fn every_tile_gets_one_entity() {
+		let (mut world, _) = world();
+		let count = world.query::<&HexCoord>().iter(&world).count();
+		let tiles = world.resource::<TileEntities>();
+		assert_eq!(count, 64);
+		assert_eq!(tiles.len(), 64);
+		for (&coord, &entity) in tiles.iter() {
+			assert_eq!(world.get::<HexCoord>(entity), Some(&coord));
+		}
+		for z in 0..8 {
+			for x in 0..8 {
+				let coord = HexCoord::from_offset(x, z);
+				let entity = tiles.get(coord).unwrap();
+				assert_eq!(world.get::<HexCoord>(entity), Some(&coord));
+			}
+		}
+	}
+
+	#[test]
+	fn edited_tiles_follow_their_height() {
+		AsyncComputeTaskPool::get_or_init(default);
+		let (mut world, spawn) = world();
+		world.init_resource::<HexData>();
+		world.init_resource::<DirtyChunks>();
+		let loaded = world
+			.query::<(Entity, &Chunk)>()
+			.iter(&world)
+			.map(|(entity, chunk)| (chunk.index.as_ivec2(), entity))
+			.collect();
+		world.insert_resource(LoadedChunks(loaded));
+		let coord = HexCoord::from_offset(5, 2);
+		let tile = world.resource::<TileEntities>().get(coord).unwrap();
+		world.run_system_once(
+			move |mut settings: ResMut<HexGridSettings>,
+			      mut data: ResMut<HexData>,
+			      mut dirty: ResMut<DirtyChunks>| {
+				HexGrid::set_height(coord, 9., &mut settings, &mut data, &mut dirty);
+			},
+		);
+		world.run_system_once(rebuild_dirty_chunks);
+		world.run_system(spawn).unwrap();
+		assert_eq!(world.get::<Transform>(tile).unwrap().translation.y, 9.);
+		assert_eq!(world.resource::<TileEntities>().get(coord), Some(tile));
+	}
+
+	#[test]
+	fn despawned_chunks_forget_their_tiles() {
+		let (mut world, _) = world();
+		let chunk = world
+			.query::<(Entity, &Chunk)>()
+			.iter(&world)
+			.find(|(_, chunk)| chunk.index == UVec2::X)
+			.unwrap()
+			.0;
+		world.entity_mut(chunk).despawn_recursive();
+		world.run_system_once(forget_tile_entities);
+		let tiles = world.resource::<TileEntities>();
+		assert_eq!(tiles.len(), 48);
+		assert!(tiles.get(HexCoord::from_offset(5, 2)).is_none());
+		assert!(tiles.get(HexCoord::from_offset(2, 5)).is_some());
+	}
+}
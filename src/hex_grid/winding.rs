@@ -0,0 +1,169 @@
+use bevy::{prelude::*, render::mesh::Indices};
+
+use super::HexGrid;
+
+/// Twice the area below which a triangle has no direction to check.
+const DEGENERATE_AREA: f32 = 1e-8;
+
+impl HexGrid {
+	/// Indices of the triangles of `mesh` wound against their own normals,
+	/// where the dot product of the face normal given by the winding and the
+	/// stored normal of one of the vertices is below `-tolerance`.
+	///
+	/// Smooth normals shared across a fold sharper than a right angle, like
+	/// the top edge of a skirt on steep terrain, lean away from some of the
+	/// faces they are averaged over without those being inverted, so a
+	/// tolerance of 0.5 lets them through. Flat normals come from the
+	/// winding itself and never disagree with it.
+	///
+	/// Triangles without area and vertices without a normal are skipped, and
+	/// meshes without positions or normals have nothing to check.
+	pub fn inverted_triangles(mesh: &Mesh, tolerance: f32) -> Vec<usize> {
+		let positions = mesh
+			.attribute(Mesh::ATTRIBUTE_POSITION)
+			.and_then(|v| v.as_float3());
+		let normals = mesh
+			.attribute(Mesh::ATTRIBUTE_NORMAL)
+			.and_then(|v| v.as_float3());
+		let (Some(positions), Some(normals)) = (positions, normals) else {
+			return Vec::new();
+		};
+		// flat shaded meshes keep three vertices per triangle and no indices
+		let indices: Vec<usize> = match mesh.indices() {
+			Some(Indices::U16(indices)) => indices.iter().map(|&i| i as usize).collect(),
+			Some(Indices::U32(indices)) => indices.iter().map(|&i| i as usize).collect(),
+			None => (0..positions.len()).collect(),
+		};
+		return indices
+			.chunks_exact(3)
+			.enumerate()
+			.filter(|(_, tri)| {
+				let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vec3::from(positions[i]));
+				let face = (b - a).cross(c - a);
+				if face.length() < DEGENERATE_AREA {
+					return false;
+				}
+				let face = face.normalize();
+				return tri.iter().any(|&i| {
+					let normal = Vec3::from(normals[i]).normalize_or_zero();
+					return normal != Vec3::ZERO && face.dot(normal) < -tolerance;
+				});
+			})
+			.map(|(i, _)| i)
+			.collect();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hex_grid::{create_chunk, HexGridSettings, HexOrientation, LodSettings, NormalMode};
+
+	/// Small maps of a few kinds, shaded smoothly so the normals come from
+	/// the faces around each vertex instead of each face on its own.
+	fn terrains() -> Vec<HexGridSettings> {
+		let base = HexGridSettings {
+			map_size: UVec2::new(2, 2),
+			chunk_size: 6,
+			height_amplitude: 4.,
+			normal_mode: NormalMode::Smooth,
+			..default()
+		};
+		return vec![
+			base.clone(),
+			HexGridSettings {
+				orientation: HexOrientation::FlatTop,
+				..base.clone()
+			},
+			HexGridSettings {
+				subdivisions: 2,
+				..base.clone()
+			},
+			HexGridSettings {
+				tile_inset: 0.2,
+				..base.clone()
+			},
+			HexGridSettings {
+				chunk_skirt: Some(2.),
+				..base.clone()
+			},
+			HexGridSettings {
+				merge_flat_tops: true,
+				height_steps: Some(3),
+				..base.clone()
+			},
+			HexGridSettings {
+				lod: Some(LodSettings::default()),
+				..base
+			},
+		];
+	}
+
+	fn chunks(settings: &HexGridSettings, lod: u32) -> impl Iterator<Item = Mesh> + '_ {
+		let noise = settings.noise();
+		return [UVec2::ZERO, UVec2::X, UVec2::ONE]
+			.into_iter()
+			.map(move |index| create_chunk(index.x, index.y, lod, &noise, settings));
+	}
+
+	#[test]
+	fn chunks_face_their_normals() {
+		for settings in terrains() {
+			for mesh in chunks(&settings, 0) {
+				assert_eq!(HexGrid::inverted_triangles(&mesh, 0.), Vec::<usize>::new());
+			}
+		}
+	}
+
+	#[test]
+	fn lod_chunks_face_their_normals() {
+		let settings = terrains().pop().unwrap();
+		for lod in 1..=2 {
+			for mesh in chunks(&settings, lod) {
+				assert_eq!(HexGrid::inverted_triangles(&mesh, 0.5), Vec::<usize>::new());
+			}
+		}
+	}
+
+	#[test]
+	fn flat_chunks_face_their_normals() {
+		for settings in terrains() {
+			let settings = HexGridSettings {
+				normal_mode: NormalMode::Flat,
+				..settings
+			};
+			for mesh in chunks(&settings, 0) {
+				assert!(mesh.indices().is_none());
+				assert_eq!(HexGrid::inverted_triangles(&mesh, 0.), Vec::<usize>::new());
+			}
+		}
+	}
+
+	#[test]
+	fn tile_tops_face_up() {
+		// without floating spans nothing faces down, and flat normals follow
+		// the winding, so a fan wound backwards shows as a face pointing down
+		for settings in terrains() {
+			let settings = HexGridSettings {
+				normal_mode: NormalMode::Flat,
+				..settings
+			};
+			for mesh in chunks(&settings, 0) {
+				let normals = mesh.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap();
+				assert!(normals.as_float3().unwrap().iter().all(|n| n[1] > -1e-4));
+			}
+		}
+	}
+
+	#[test]
+	fn flipped_triangle_is_inverted() {
+		let settings = terrains().remove(0);
+		let mut mesh = chunks(&settings, 0).next().unwrap();
+		let Some(Indices::U32(indices)) = mesh.indices_mut() else {
+			panic!("chunk meshes have 32 bit indices");
+		};
+		// the second triangle of the first tile's fan
+		indices.swap(4, 5);
+		assert_eq!(HexGrid::inverted_triangles(&mesh, 0.5), vec![1]);
+	}
+}
@@ -0,0 +1,9 @@
+#![allow(
+	clippy::needless_return,
+	clippy::too_many_arguments,
+	clippy::type_complexity
+)]
+
+mod hex_grid;
+
+pub use hex_grid::*;